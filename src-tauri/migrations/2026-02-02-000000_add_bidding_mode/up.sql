@@ -0,0 +1 @@
+ALTER TABLE requests ADD COLUMN IF NOT EXISTS bidding_mode VARCHAR NOT NULL DEFAULT 'open' CHECK (bidding_mode IN ('open', 'sealed'));
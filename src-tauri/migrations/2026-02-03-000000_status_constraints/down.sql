@@ -0,0 +1,3 @@
+ALTER TABLE offers DROP CONSTRAINT IF EXISTS offers_status_check;
+ALTER TABLE requests DROP CONSTRAINT IF EXISTS requests_status_check;
+UPDATE requests SET status = 'open' WHERE status = 'published';
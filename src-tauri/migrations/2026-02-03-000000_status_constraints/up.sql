@@ -0,0 +1,15 @@
+-- Legacy values: ERP imports used 'open', manual close used 'closed'
+UPDATE requests SET status = 'published' WHERE status = 'open';
+UPDATE requests SET status = 'in_evaluation' WHERE status = 'closed';
+UPDATE requests SET status = 'published'
+    WHERE status NOT IN ('draft', 'published', 'in_evaluation', 'awarded', 'cancelled', 'expired');
+
+ALTER TABLE requests
+    ADD CONSTRAINT requests_status_check
+    CHECK (status IN ('draft', 'published', 'in_evaluation', 'awarded', 'cancelled', 'expired'));
+
+UPDATE offers SET status = 'sent' WHERE status NOT IN ('sent', 'ganadora', 'rechazada');
+
+ALTER TABLE offers
+    ADD CONSTRAINT offers_status_check
+    CHECK (status IN ('sent', 'ganadora', 'rechazada'));
@@ -0,0 +1,4 @@
+ALTER TABLE offers DROP COLUMN IF EXISTS needs_reconfirmation;
+ALTER TABLE offers DROP COLUMN IF EXISTS request_version;
+DROP TABLE IF EXISTS request_versions;
+ALTER TABLE requests DROP COLUMN IF EXISTS version;
@@ -0,0 +1,24 @@
+ALTER TABLE requests ADD COLUMN IF NOT EXISTS version INTEGER NOT NULL DEFAULT 1;
+
+CREATE TABLE IF NOT EXISTS request_versions (
+    id SERIAL PRIMARY KEY,
+    request_id INTEGER NOT NULL REFERENCES requests(id) ON DELETE CASCADE,
+    version INTEGER NOT NULL,
+    title VARCHAR NOT NULL,
+    description TEXT NOT NULL,
+    deadline TIMESTAMP NOT NULL,
+    quantity INTEGER NOT NULL,
+    units VARCHAR NOT NULL,
+    tags TEXT NOT NULL,
+    reason TEXT NOT NULL DEFAULT '',
+    amended_by INTEGER REFERENCES users(id),
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    UNIQUE (request_id, version)
+);
+
+-- Existing requests start at version 1
+INSERT INTO request_versions (request_id, version, title, description, deadline, quantity, units, tags)
+SELECT id, 1, title, description, deadline, quantity, units, tags FROM requests;
+
+ALTER TABLE offers ADD COLUMN IF NOT EXISTS request_version INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE offers ADD COLUMN IF NOT EXISTS needs_reconfirmation BOOLEAN NOT NULL DEFAULT FALSE;
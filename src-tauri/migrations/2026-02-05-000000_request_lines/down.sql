@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS offer_lines;
+DROP TABLE IF EXISTS request_lines;
@@ -0,0 +1,30 @@
+CREATE TABLE IF NOT EXISTS request_lines (
+    id SERIAL PRIMARY KEY,
+    request_id INTEGER NOT NULL REFERENCES requests(id) ON DELETE CASCADE,
+    line_no INTEGER NOT NULL,
+    description TEXT NOT NULL,
+    quantity INTEGER NOT NULL,
+    units VARCHAR NOT NULL,
+    UNIQUE (request_id, line_no)
+);
+
+-- Every existing request becomes a single-line bill of materials
+INSERT INTO request_lines (request_id, line_no, description, quantity, units)
+SELECT id, 1, title, quantity, units FROM requests;
+
+CREATE TABLE IF NOT EXISTS offer_lines (
+    id SERIAL PRIMARY KEY,
+    offer_id INTEGER NOT NULL REFERENCES offers(id) ON DELETE CASCADE,
+    request_line_id INTEGER NOT NULL REFERENCES request_lines(id) ON DELETE CASCADE,
+    price DOUBLE PRECISION NOT NULL,
+    delivery_time VARCHAR NOT NULL,
+    status VARCHAR NOT NULL DEFAULT 'sent',
+    UNIQUE (offer_id, request_line_id),
+    CONSTRAINT offer_lines_status_check CHECK (status IN ('sent', 'ganadora', 'rechazada'))
+);
+
+-- Existing offers quote the whole (single) line of their request
+INSERT INTO offer_lines (offer_id, request_line_id, price, delivery_time, status)
+SELECT o.id, l.id, o.price, o.delivery_time, o.status
+FROM offers o
+JOIN request_lines l ON l.request_id = o.request_id AND l.line_no = 1;
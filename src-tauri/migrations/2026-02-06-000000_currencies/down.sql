@@ -0,0 +1,5 @@
+DROP TABLE IF EXISTS exchange_rates;
+ALTER TABLE offers DROP COLUMN IF EXISTS currency;
+ALTER TABLE requests DROP COLUMN IF EXISTS currency;
+ALTER TABLE offer_lines ALTER COLUMN price TYPE DOUBLE PRECISION;
+ALTER TABLE offers ALTER COLUMN price TYPE DOUBLE PRECISION;
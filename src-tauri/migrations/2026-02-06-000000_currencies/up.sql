@@ -0,0 +1,16 @@
+-- Amounts are stored as exact decimals so totals reconcile with the ERP
+ALTER TABLE offers ALTER COLUMN price TYPE NUMERIC(14, 2) USING ROUND(price::NUMERIC, 2);
+ALTER TABLE offer_lines ALTER COLUMN price TYPE NUMERIC(14, 2) USING ROUND(price::NUMERIC, 2);
+
+ALTER TABLE requests ADD COLUMN IF NOT EXISTS currency VARCHAR(3) NOT NULL DEFAULT 'MXN';
+ALTER TABLE offers ADD COLUMN IF NOT EXISTS currency VARCHAR(3) NOT NULL DEFAULT 'MXN';
+
+-- Value of one unit of `currency` in MXN, valid from `effective_date` until the next entry
+CREATE TABLE IF NOT EXISTS exchange_rates (
+    id SERIAL PRIMARY KEY,
+    currency VARCHAR(3) NOT NULL,
+    rate NUMERIC(18, 6) NOT NULL CHECK (rate > 0),
+    effective_date DATE NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    UNIQUE (currency, effective_date)
+);
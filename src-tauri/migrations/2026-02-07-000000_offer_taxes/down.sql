@@ -0,0 +1,7 @@
+ALTER TABLE offers DROP COLUMN IF EXISTS total;
+ALTER TABLE offers DROP COLUMN IF EXISTS withholding_amount;
+ALTER TABLE offers DROP COLUMN IF EXISTS withholding_rate;
+ALTER TABLE offers DROP COLUMN IF EXISTS tax_amount;
+ALTER TABLE offers DROP COLUMN IF EXISTS tax_rate;
+ALTER TABLE offer_lines DROP COLUMN IF EXISTS unit_price;
+DROP TABLE IF EXISTS tax_settings;
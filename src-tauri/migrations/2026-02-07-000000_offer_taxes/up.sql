@@ -0,0 +1,30 @@
+-- Single row with the tax rates applied to new offers (fractions, 0.16 = 16%)
+CREATE TABLE IF NOT EXISTS tax_settings (
+    id INTEGER PRIMARY KEY DEFAULT 1 CHECK (id = 1),
+    iva_rate NUMERIC(6, 4) NOT NULL DEFAULT 0.16 CHECK (iva_rate >= 0 AND iva_rate < 1),
+    withholding_rate NUMERIC(6, 4) NOT NULL DEFAULT 0 CHECK (withholding_rate >= 0 AND withholding_rate < 1),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+INSERT INTO tax_settings (id) VALUES (1) ON CONFLICT (id) DO NOTHING;
+
+-- Line `price` stays the line total; the unit price is stored next to it
+ALTER TABLE offer_lines ADD COLUMN IF NOT EXISTS unit_price NUMERIC(14, 4);
+
+UPDATE offer_lines ol
+SET unit_price = ROUND(ol.price / rl.quantity, 4)
+FROM request_lines rl
+WHERE rl.id = ol.request_line_id AND rl.quantity > 0;
+
+UPDATE offer_lines SET unit_price = price WHERE unit_price IS NULL;
+ALTER TABLE offer_lines ALTER COLUMN unit_price SET NOT NULL;
+
+-- Offer `price` is the subtotal before taxes; rates are kept as quoted
+ALTER TABLE offers ADD COLUMN IF NOT EXISTS tax_rate NUMERIC(6, 4) NOT NULL DEFAULT 0.16;
+ALTER TABLE offers ADD COLUMN IF NOT EXISTS tax_amount NUMERIC(14, 2) NOT NULL DEFAULT 0;
+ALTER TABLE offers ADD COLUMN IF NOT EXISTS withholding_rate NUMERIC(6, 4) NOT NULL DEFAULT 0;
+ALTER TABLE offers ADD COLUMN IF NOT EXISTS withholding_amount NUMERIC(14, 2) NOT NULL DEFAULT 0;
+ALTER TABLE offers ADD COLUMN IF NOT EXISTS total NUMERIC(14, 2) NOT NULL DEFAULT 0;
+
+UPDATE offers SET tax_amount = ROUND(price * tax_rate, 2);
+UPDATE offers SET total = price + tax_amount - withholding_amount;
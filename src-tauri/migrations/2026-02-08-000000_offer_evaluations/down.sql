@@ -0,0 +1,3 @@
+ALTER TABLE requests DROP COLUMN IF EXISTS awarded_evaluation_id;
+DROP TABLE IF EXISTS offer_evaluations;
+DROP TABLE IF EXISTS scoring_weights;
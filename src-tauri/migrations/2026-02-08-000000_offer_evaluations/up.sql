@@ -0,0 +1,23 @@
+-- Default weights for offer evaluations; buyers may override them per evaluation
+CREATE TABLE IF NOT EXISTS scoring_weights (
+    id INTEGER PRIMARY KEY DEFAULT 1 CHECK (id = 1),
+    price DOUBLE PRECISION NOT NULL DEFAULT 50,
+    delivery DOUBLE PRECISION NOT NULL DEFAULT 20,
+    compliance DOUBLE PRECISION NOT NULL DEFAULT 20,
+    experience DOUBLE PRECISION NOT NULL DEFAULT 10
+);
+
+INSERT INTO scoring_weights (id) VALUES (1) ON CONFLICT (id) DO NOTHING;
+
+-- Immutable snapshot of each evaluation; weights and ranking are stored as JSON text
+CREATE TABLE IF NOT EXISTS offer_evaluations (
+    id SERIAL PRIMARY KEY,
+    request_id INTEGER NOT NULL REFERENCES requests(id) ON DELETE CASCADE,
+    weights TEXT NOT NULL,
+    ranking TEXT NOT NULL,
+    created_by INTEGER NOT NULL REFERENCES users(id),
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+ALTER TABLE requests ADD COLUMN IF NOT EXISTS awarded_evaluation_id INTEGER
+    REFERENCES offer_evaluations(id) ON DELETE SET NULL;
@@ -0,0 +1,5 @@
+ALTER TABLE offer_lines DROP COLUMN IF EXISTS promised_date;
+ALTER TABLE offer_lines DROP COLUMN IF EXISTS delivery_days;
+ALTER TABLE offers DROP COLUMN IF EXISTS promised_date;
+ALTER TABLE offers DROP COLUMN IF EXISTS delivery_days;
+ALTER TABLE requests DROP COLUMN IF EXISTS required_date;
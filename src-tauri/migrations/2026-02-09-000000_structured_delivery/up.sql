@@ -0,0 +1,8 @@
+-- Date by which the goods are needed; when empty the request deadline is used
+ALTER TABLE requests ADD COLUMN IF NOT EXISTS required_date DATE;
+
+-- Structured delivery promise; older offers only have the free-text delivery_time
+ALTER TABLE offers ADD COLUMN IF NOT EXISTS delivery_days INTEGER CHECK (delivery_days >= 0);
+ALTER TABLE offers ADD COLUMN IF NOT EXISTS promised_date DATE;
+ALTER TABLE offer_lines ADD COLUMN IF NOT EXISTS delivery_days INTEGER CHECK (delivery_days >= 0);
+ALTER TABLE offer_lines ADD COLUMN IF NOT EXISTS promised_date DATE;
@@ -0,0 +1,15 @@
+DROP TABLE IF EXISTS offer_revisions;
+ALTER TABLE offers DROP COLUMN IF EXISTS updated_at;
+ALTER TABLE offers DROP COLUMN IF EXISTS revision;
+
+UPDATE offer_lines SET status = 'rechazada' WHERE status = 'retirada';
+ALTER TABLE offer_lines DROP CONSTRAINT IF EXISTS offer_lines_status_check;
+ALTER TABLE offer_lines
+    ADD CONSTRAINT offer_lines_status_check
+    CHECK (status IN ('sent', 'ganadora', 'rechazada'));
+
+UPDATE offers SET status = 'rechazada' WHERE status = 'retirada';
+ALTER TABLE offers DROP CONSTRAINT IF EXISTS offers_status_check;
+ALTER TABLE offers
+    ADD CONSTRAINT offers_status_check
+    CHECK (status IN ('sent', 'ganadora', 'rechazada'));
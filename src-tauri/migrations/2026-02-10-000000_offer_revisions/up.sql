@@ -0,0 +1,46 @@
+ALTER TABLE offers DROP CONSTRAINT IF EXISTS offers_status_check;
+ALTER TABLE offers
+    ADD CONSTRAINT offers_status_check
+    CHECK (status IN ('sent', 'ganadora', 'rechazada', 'retirada'));
+
+ALTER TABLE offer_lines DROP CONSTRAINT IF EXISTS offer_lines_status_check;
+ALTER TABLE offer_lines
+    ADD CONSTRAINT offer_lines_status_check
+    CHECK (status IN ('sent', 'ganadora', 'rechazada', 'retirada'));
+
+ALTER TABLE offers ADD COLUMN IF NOT EXISTS revision INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE offers ADD COLUMN IF NOT EXISTS updated_at TIMESTAMP NOT NULL DEFAULT NOW();
+UPDATE offers SET updated_at = created_at;
+
+-- Immutable copy of every revision of an offer; lines are stored as JSON text
+CREATE TABLE IF NOT EXISTS offer_revisions (
+    id SERIAL PRIMARY KEY,
+    offer_id INTEGER NOT NULL REFERENCES offers(id) ON DELETE CASCADE,
+    revision INTEGER NOT NULL,
+    status VARCHAR NOT NULL,
+    price NUMERIC(14, 2) NOT NULL,
+    currency VARCHAR(3) NOT NULL,
+    tax_amount NUMERIC(14, 2) NOT NULL,
+    withholding_amount NUMERIC(14, 2) NOT NULL,
+    total NUMERIC(14, 2) NOT NULL,
+    delivery_days INTEGER,
+    promised_date DATE,
+    delivery_time VARCHAR NOT NULL,
+    conditions TEXT NOT NULL,
+    attachments TEXT NOT NULL,
+    photo TEXT,
+    lines TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    UNIQUE (offer_id, revision)
+);
+
+-- Existing offers start their history with the current values
+INSERT INTO offer_revisions (
+    offer_id, revision, status, price, currency, tax_amount, withholding_amount, total,
+    delivery_days, promised_date, delivery_time, conditions, attachments, photo, lines, created_at
+)
+SELECT o.id, 1, o.status, o.price, o.currency, o.tax_amount, o.withholding_amount, o.total,
+    o.delivery_days, o.promised_date, o.delivery_time, o.conditions, o.attachments, o.photo,
+    COALESCE((SELECT json_agg(ol ORDER BY ol.id)::TEXT FROM offer_lines ol WHERE ol.offer_id = o.id), '[]'),
+    o.created_at
+FROM offers o;
@@ -0,0 +1,8 @@
+ALTER TABLE requests DROP COLUMN IF EXISTS auction_extension_minutes;
+ALTER TABLE requests DROP COLUMN IF EXISTS auction_min_decrement;
+
+UPDATE requests SET bidding_mode = 'open' WHERE bidding_mode = 'auction';
+ALTER TABLE requests DROP CONSTRAINT IF EXISTS requests_bidding_mode_check;
+ALTER TABLE requests
+    ADD CONSTRAINT requests_bidding_mode_check
+    CHECK (bidding_mode IN ('open', 'sealed'));
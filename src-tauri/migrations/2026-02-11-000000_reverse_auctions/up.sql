@@ -0,0 +1,11 @@
+ALTER TABLE requests DROP CONSTRAINT IF EXISTS requests_bidding_mode_check;
+ALTER TABLE requests
+    ADD CONSTRAINT requests_bidding_mode_check
+    CHECK (bidding_mode IN ('open', 'sealed', 'auction'));
+
+-- Each new bid of a supplier must undercut their previous one by at least this amount
+ALTER TABLE requests ADD COLUMN IF NOT EXISTS auction_min_decrement NUMERIC(14,2) NOT NULL DEFAULT 0
+    CHECK (auction_min_decrement >= 0);
+-- A bid received this close to the deadline pushes it back by the same number of minutes
+ALTER TABLE requests ADD COLUMN IF NOT EXISTS auction_extension_minutes INTEGER NOT NULL DEFAULT 5
+    CHECK (auction_extension_minutes BETWEEN 0 AND 60);
@@ -0,0 +1,7 @@
+DROP INDEX IF EXISTS suppliers_search_idx;
+ALTER TABLE suppliers DROP COLUMN IF EXISTS search_vector;
+
+DROP INDEX IF EXISTS requests_search_idx;
+ALTER TABLE requests DROP COLUMN IF EXISTS search_vector;
+
+DROP TEXT SEARCH CONFIGURATION IF EXISTS es_unaccent;
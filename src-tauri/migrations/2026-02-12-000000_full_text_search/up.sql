@@ -0,0 +1,29 @@
+CREATE EXTENSION IF NOT EXISTS unaccent;
+
+-- Spanish stemming that ignores accents: "camión" and "camion" match "camiones"
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM pg_ts_config WHERE cfgname = 'es_unaccent') THEN
+        CREATE TEXT SEARCH CONFIGURATION es_unaccent (COPY = spanish);
+        ALTER TEXT SEARCH CONFIGURATION es_unaccent
+            ALTER MAPPING FOR hword, hword_part, word WITH unaccent, spanish_stem;
+    END IF;
+END
+$$;
+
+-- Title weighs more than tags, and tags more than the description
+ALTER TABLE requests ADD COLUMN IF NOT EXISTS search_vector TSVECTOR
+    GENERATED ALWAYS AS (
+        setweight(to_tsvector('es_unaccent'::regconfig, coalesce(title, '')), 'A') ||
+        setweight(to_tsvector('es_unaccent'::regconfig, replace(coalesce(tags, ''), ',', ' ')), 'B') ||
+        setweight(to_tsvector('es_unaccent'::regconfig, coalesce(description, '')), 'C')
+    ) STORED;
+CREATE INDEX IF NOT EXISTS requests_search_idx ON requests USING GIN (search_vector);
+
+ALTER TABLE suppliers ADD COLUMN IF NOT EXISTS search_vector TSVECTOR
+    GENERATED ALWAYS AS (
+        setweight(to_tsvector('es_unaccent'::regconfig, coalesce(name, '')), 'A') ||
+        setweight(to_tsvector('es_unaccent'::regconfig, coalesce(contact, '')), 'B') ||
+        setweight(to_tsvector('es_unaccent'::regconfig, coalesce(email, '')), 'C')
+    ) STORED;
+CREATE INDEX IF NOT EXISTS suppliers_search_idx ON suppliers USING GIN (search_vector);
@@ -0,0 +1,3 @@
+DROP TABLE IF EXISTS supplier_categories;
+DROP TABLE IF EXISTS request_categories;
+DROP TABLE IF EXISTS categories;
@@ -0,0 +1,41 @@
+CREATE TABLE IF NOT EXISTS categories (
+    id SERIAL PRIMARY KEY,
+    slug VARCHAR NOT NULL UNIQUE,
+    name VARCHAR NOT NULL,
+    active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS request_categories (
+    request_id INTEGER NOT NULL REFERENCES requests(id) ON DELETE CASCADE,
+    category_id INTEGER NOT NULL REFERENCES categories(id) ON DELETE CASCADE,
+    PRIMARY KEY (request_id, category_id)
+);
+CREATE INDEX IF NOT EXISTS request_categories_category_idx ON request_categories (category_id);
+
+-- Categories a supplier serves; it is notified of new requests in them
+CREATE TABLE IF NOT EXISTS supplier_categories (
+    supplier_id INTEGER NOT NULL REFERENCES suppliers(id) ON DELETE CASCADE,
+    category_id INTEGER NOT NULL REFERENCES categories(id) ON DELETE CASCADE,
+    PRIMARY KEY (supplier_id, category_id)
+);
+CREATE INDEX IF NOT EXISTS supplier_categories_category_idx ON supplier_categories (category_id);
+
+-- Every distinct tag used so far becomes a category
+INSERT INTO categories (slug, name)
+SELECT DISTINCT ON (slug) slug, trim(t.tag)
+FROM requests r
+CROSS JOIN LATERAL unnest(string_to_array(r.tags, ',')) AS t(tag)
+CROSS JOIN LATERAL (
+    SELECT trim(BOTH '-' FROM regexp_replace(lower(trim(t.tag)), '[^[:alnum:]]+', '-', 'g')) AS slug
+) s
+WHERE s.slug <> ''
+ORDER BY slug, trim(t.tag)
+ON CONFLICT (slug) DO NOTHING;
+
+INSERT INTO request_categories (request_id, category_id)
+SELECT DISTINCT r.id, c.id
+FROM requests r
+CROSS JOIN LATERAL unnest(string_to_array(r.tags, ',')) AS t(tag)
+JOIN categories c ON c.slug = trim(BOTH '-' FROM regexp_replace(lower(trim(t.tag)), '[^[:alnum:]]+', '-', 'g'))
+ON CONFLICT DO NOTHING;
@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS request_invitations;
+ALTER TABLE requests DROP COLUMN IF EXISTS invite_only;
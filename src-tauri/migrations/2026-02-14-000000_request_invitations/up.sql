@@ -0,0 +1,17 @@
+-- Invite-only requests are hidden from every supplier that wasn't invited
+ALTER TABLE requests ADD COLUMN IF NOT EXISTS invite_only BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE TABLE IF NOT EXISTS request_invitations (
+    id SERIAL PRIMARY KEY,
+    request_id INTEGER NOT NULL REFERENCES requests(id) ON DELETE CASCADE,
+    supplier_id INTEGER NOT NULL REFERENCES suppliers(id) ON DELETE CASCADE,
+    status VARCHAR NOT NULL DEFAULT 'pending'
+        CHECK (status IN ('pending', 'accepted', 'declined')),
+    invited_by INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    responded_at TIMESTAMP,
+    -- Set once the invitation email went out; drafts are only emailed when published
+    notified_at TIMESTAMP,
+    UNIQUE (request_id, supplier_id)
+);
+CREATE INDEX IF NOT EXISTS request_invitations_supplier_idx ON request_invitations (supplier_id);
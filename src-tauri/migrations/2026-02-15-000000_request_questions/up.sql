@@ -0,0 +1,17 @@
+-- Clarification questions of suppliers about a request and the buyers' answers.
+-- Rows are never deleted or rewritten so the thread stays auditable.
+CREATE TABLE IF NOT EXISTS request_questions (
+    id SERIAL PRIMARY KEY,
+    request_id INTEGER NOT NULL REFERENCES requests(id) ON DELETE CASCADE,
+    supplier_id INTEGER REFERENCES suppliers(id) ON DELETE SET NULL,
+    question TEXT NOT NULL,
+    -- Wording shown to every bidder when the answer is public; the original question is kept
+    public_question TEXT,
+    answer TEXT,
+    visibility VARCHAR CHECK (visibility IN ('private', 'public')),
+    answered_by INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    answered_at TIMESTAMP,
+    CHECK ((answer IS NULL) = (visibility IS NULL))
+);
+CREATE INDEX IF NOT EXISTS request_questions_request_idx ON request_questions (request_id);
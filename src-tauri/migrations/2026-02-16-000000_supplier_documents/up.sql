@@ -0,0 +1,21 @@
+-- One row per uploaded compliance document; a new upload of the same type supersedes the
+-- previous one but both are kept. suppliers.documents stays for the files uploaded before.
+CREATE TABLE IF NOT EXISTS supplier_documents (
+    id SERIAL PRIMARY KEY,
+    supplier_id INTEGER NOT NULL REFERENCES suppliers(id) ON DELETE CASCADE,
+    doc_type VARCHAR NOT NULL
+        CHECK (doc_type IN ('rfc', 'constancia_situacion_fiscal', 'estado_cuenta', 'certificado_iso')),
+    -- Name returned by POST /api/upload
+    file VARCHAR NOT NULL,
+    issued_on DATE,
+    expires_on DATE,
+    review_status VARCHAR NOT NULL DEFAULT 'pending'
+        CHECK (review_status IN ('pending', 'approved', 'rejected')),
+    review_notes TEXT NOT NULL DEFAULT '',
+    reviewed_by INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    reviewed_at TIMESTAMP,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    CHECK (issued_on IS NULL OR expires_on IS NULL OR expires_on >= issued_on)
+);
+CREATE INDEX IF NOT EXISTS supplier_documents_supplier_idx ON supplier_documents (supplier_id, doc_type);
+CREATE INDEX IF NOT EXISTS supplier_documents_review_idx ON supplier_documents (review_status);
@@ -0,0 +1,8 @@
+DROP INDEX IF EXISTS supplier_documents_expiry_idx;
+ALTER TABLE supplier_documents DROP COLUMN IF EXISTS lapsed_at;
+ALTER TABLE supplier_documents DROP COLUMN IF EXISTS reminder_days;
+
+DELETE FROM supplier_documents WHERE doc_type = 'opinion_cumplimiento';
+ALTER TABLE supplier_documents DROP CONSTRAINT IF EXISTS supplier_documents_doc_type_check;
+ALTER TABLE supplier_documents ADD CONSTRAINT supplier_documents_doc_type_check
+    CHECK (doc_type IN ('rfc', 'constancia_situacion_fiscal', 'estado_cuenta', 'certificado_iso'));
@@ -0,0 +1,12 @@
+-- Opinión de cumplimiento (SAT 32-D); it expires, so suppliers renew it periodically
+ALTER TABLE supplier_documents DROP CONSTRAINT IF EXISTS supplier_documents_doc_type_check;
+ALTER TABLE supplier_documents ADD CONSTRAINT supplier_documents_doc_type_check
+    CHECK (doc_type IN ('rfc', 'constancia_situacion_fiscal', 'opinion_cumplimiento', 'estado_cuenta', 'certificado_iso'));
+
+-- Smallest "days before expiry" reminder already emailed (30, 15 or 1), so each one goes out once
+ALTER TABLE supplier_documents ADD COLUMN IF NOT EXISTS reminder_days INTEGER;
+-- Set once the scheduler has handled the document passing its expiry date
+ALTER TABLE supplier_documents ADD COLUMN IF NOT EXISTS lapsed_at TIMESTAMP;
+
+CREATE INDEX IF NOT EXISTS supplier_documents_expiry_idx ON supplier_documents (expires_on)
+    WHERE expires_on IS NOT NULL AND lapsed_at IS NULL;
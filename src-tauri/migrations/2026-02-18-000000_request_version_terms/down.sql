@@ -0,0 +1,6 @@
+ALTER TABLE request_versions DROP COLUMN IF EXISTS lines;
+ALTER TABLE request_versions DROP COLUMN IF EXISTS auction_extension_minutes;
+ALTER TABLE request_versions DROP COLUMN IF EXISTS auction_min_decrement;
+ALTER TABLE request_versions DROP COLUMN IF EXISTS required_date;
+ALTER TABLE request_versions DROP COLUMN IF EXISTS currency;
+ALTER TABLE request_versions DROP COLUMN IF EXISTS bidding_mode;
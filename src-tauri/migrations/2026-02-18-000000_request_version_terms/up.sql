@@ -0,0 +1,19 @@
+-- Versions also snapshot the commercial terms and the lines, so amendments that change
+-- them can be audited
+ALTER TABLE request_versions ADD COLUMN IF NOT EXISTS bidding_mode VARCHAR NOT NULL DEFAULT 'open';
+ALTER TABLE request_versions ADD COLUMN IF NOT EXISTS currency VARCHAR NOT NULL DEFAULT 'MXN';
+ALTER TABLE request_versions ADD COLUMN IF NOT EXISTS required_date DATE;
+ALTER TABLE request_versions ADD COLUMN IF NOT EXISTS auction_min_decrement NUMERIC(14,2) NOT NULL DEFAULT 0;
+ALTER TABLE request_versions ADD COLUMN IF NOT EXISTS auction_extension_minutes INTEGER NOT NULL DEFAULT 5;
+-- JSON array of the request lines at that version
+ALTER TABLE request_versions ADD COLUMN IF NOT EXISTS lines TEXT NOT NULL DEFAULT '[]';
+
+-- Older versions didn't record these; the current values of the request are the best known
+UPDATE request_versions v
+SET bidding_mode = r.bidding_mode,
+    currency = r.currency,
+    required_date = r.required_date,
+    auction_min_decrement = r.auction_min_decrement,
+    auction_extension_minutes = r.auction_extension_minutes
+FROM requests r
+WHERE r.id = v.request_id;
@@ -0,0 +1 @@
+DROP INDEX IF EXISTS offers_single_bid_idx;
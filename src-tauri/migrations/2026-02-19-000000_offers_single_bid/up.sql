@@ -0,0 +1,20 @@
+-- A supplier has at most one live offer per request. Offers sent before the rule was
+-- enforced keep the winner or else the newest; the rest are withdrawn.
+WITH ranked AS (
+    SELECT id, row_number() OVER (
+        PARTITION BY request_id, supplier_id
+        ORDER BY (status = 'ganadora') DESC, id DESC
+    ) AS rn
+    FROM offers
+    WHERE status <> 'retirada'
+), withdrawn AS (
+    UPDATE offers SET status = 'retirada'
+    FROM ranked
+    WHERE offers.id = ranked.id AND ranked.rn > 1
+    RETURNING offers.id
+)
+UPDATE offer_lines SET status = 'retirada'
+WHERE offer_id IN (SELECT id FROM withdrawn);
+
+CREATE UNIQUE INDEX IF NOT EXISTS offers_single_bid_idx ON offers (request_id, supplier_id)
+    WHERE status <> 'retirada';
@@ -0,0 +1,26 @@
+-- Who uploaded each file returned by POST /api/upload. Suppliers can only register their own
+-- files as documents, and their files are only served to staff and to themselves.
+CREATE TABLE IF NOT EXISTS uploads (
+    file VARCHAR PRIMARY KEY,
+    -- Set when a supplier uploaded the file; NULL for staff uploads such as the login image
+    supplier_id INTEGER REFERENCES suppliers(id) ON DELETE CASCADE,
+    user_id INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+-- Files uploaded before belong to the supplier that registered them
+INSERT INTO uploads (file, supplier_id)
+SELECT DISTINCT ON (file) file, supplier_id FROM supplier_documents ORDER BY file, id
+ON CONFLICT DO NOTHING;
+
+INSERT INTO uploads (file, supplier_id)
+SELECT DISTINCT ON (name) name, id
+FROM (SELECT trim(unnest(string_to_array(documents, ','))) AS name, id FROM suppliers) legacy
+WHERE name <> ''
+ORDER BY name, id
+ON CONFLICT DO NOTHING;
+
+INSERT INTO uploads (file)
+SELECT login_image_url FROM email_config
+WHERE login_image_url <> '' AND login_image_url NOT LIKE 'http%'
+ON CONFLICT DO NOTHING;
@@ -0,0 +1,5 @@
+ALTER TABLE request_versions DROP COLUMN IF EXISTS auction_round_minutes;
+ALTER TABLE request_versions DROP COLUMN IF EXISTS auction_rounds;
+ALTER TABLE requests DROP COLUMN IF EXISTS auction_round;
+ALTER TABLE requests DROP COLUMN IF EXISTS auction_round_minutes;
+ALTER TABLE requests DROP COLUMN IF EXISTS auction_rounds;
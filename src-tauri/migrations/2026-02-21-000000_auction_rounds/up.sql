@@ -0,0 +1,7 @@
+-- Auctions can run in several timed rounds; `auction_round` is the one taking bids
+ALTER TABLE requests ADD COLUMN IF NOT EXISTS auction_rounds INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE requests ADD COLUMN IF NOT EXISTS auction_round_minutes INTEGER NOT NULL DEFAULT 10;
+ALTER TABLE requests ADD COLUMN IF NOT EXISTS auction_round INTEGER NOT NULL DEFAULT 1;
+
+ALTER TABLE request_versions ADD COLUMN IF NOT EXISTS auction_rounds INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE request_versions ADD COLUMN IF NOT EXISTS auction_round_minutes INTEGER NOT NULL DEFAULT 10;
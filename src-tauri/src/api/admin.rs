@@ -1,43 +1,76 @@
 use actix_web::{web, HttpResponse, Responder};
 use serde::Deserialize;
-use crate::db::{DbPool, models::Supplier};
+use crate::db::{DbPool, models::Supplier, schema::suppliers};
+use diesel::pg::Pg;
 use diesel::prelude::*;
 use crate::email_service;
+use crate::api::{audit, events::{Event, EventHub, EventKind}, pagination::{self, ListQuery, Page}, security::AuthUser};
 
-pub async fn list_pending_suppliers(
-    pool: web::Data<DbPool>,
-) -> impl Responder {
+const SUPPLIER_SORTS: &[&str] = &["name", "created_at", "earnings_count"];
+
+/// Suppliers by account state; `from`/`to` filter on the registration date.
+fn filtered_suppliers(query: &ListQuery, is_active: bool) -> suppliers::BoxedQuery<'static, Pg> {
+    let mut q = suppliers::table.filter(suppliers::active.eq(is_active)).into_boxed();
+    if let Some(start) = query.starts_at() {
+        q = q.filter(suppliers::created_at.ge(start));
+    }
+    if let Some(end) = query.ends_before() {
+        q = q.filter(suppliers::created_at.lt(end));
+    }
+    q
+}
+
+fn list_suppliers(pool: &DbPool, query: ListQuery, is_active: bool, default_sort: &str) -> HttpResponse {
+    let Some((sort, desc)) = query.sort(SUPPLIER_SORTS, default_sort) else {
+        return pagination::invalid_sort(SUPPLIER_SORTS);
+    };
     let mut conn = pool.get().expect("couldn't get db connection from pool");
-    use crate::db::schema::suppliers::dsl::*;
 
-    let results = suppliers
-        .filter(active.eq(false))
+    let total = match filtered_suppliers(&query, is_active).count().get_result::<i64>(&mut conn) {
+        Ok(t) => t,
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    };
+
+    let q = filtered_suppliers(&query, is_active);
+    let ordered = match (sort, desc) {
+        ("created_at", false) => q.order(suppliers::created_at.asc()),
+        ("created_at", true) => q.order(suppliers::created_at.desc()),
+        ("earnings_count", false) => q.order(suppliers::earnings_count.asc()),
+        ("earnings_count", true) => q.order(suppliers::earnings_count.desc()),
+        (_, false) => q.order(suppliers::name.asc()),
+        (_, true) => q.order(suppliers::name.desc()),
+    };
+
+    let results = ordered
+        .then_order_by(suppliers::id.asc())
+        .limit(query.per_page())
+        .offset(query.offset())
         .load::<Supplier>(&mut conn);
 
     match results {
-        Ok(list) => HttpResponse::Ok().json(list),
+        Ok(list) => HttpResponse::Ok().json(Page::new(list, total, &query)),
         Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
     }
 }
 
-pub async fn list_approved_suppliers(
+/// Registrations waiting for approval, oldest first.
+pub async fn list_pending_suppliers(
     pool: web::Data<DbPool>,
+    query: web::Query<ListQuery>,
 ) -> impl Responder {
-    let mut conn = pool.get().expect("couldn't get db connection from pool");
-    use crate::db::schema::suppliers::dsl::*;
-
-    let results = suppliers
-        .filter(active.eq(true))
-        .load::<Supplier>(&mut conn);
+    list_suppliers(&pool, query.into_inner(), false, "created_at")
+}
 
-    match results {
-        Ok(list) => HttpResponse::Ok().json(list),
-        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
-    }
+pub async fn list_approved_suppliers(
+    pool: web::Data<DbPool>,
+    query: web::Query<ListQuery>,
+) -> impl Responder {
+    list_suppliers(&pool, query.into_inner(), true, "name")
 }
 
 pub async fn reject_supplier(
     pool: web::Data<DbPool>,
+    actor: AuthUser,
     path: web::Path<i32>,
 ) -> impl Responder {
     let supp_id = path.into_inner();
@@ -56,6 +89,7 @@ pub async fn reject_supplier(
 
     match deleted {
         Ok(_) => {
+            audit::record(&mut conn, &actor, "reject_supplier", "supplier", Some(supp_id));
             if !supplier_email.is_empty() {
                  email_service::send_supplier_rejected_email(&pool, &supplier_email);
             }
@@ -67,6 +101,8 @@ pub async fn reject_supplier(
 
 pub async fn approve_supplier(
     pool: web::Data<DbPool>,
+    events: web::Data<EventHub>,
+    actor: AuthUser,
     path: web::Path<i32>,
 ) -> impl Responder {
     let supp_id = path.into_inner();
@@ -79,6 +115,8 @@ pub async fn approve_supplier(
 
     match updated {
         Ok(supplier) => {
+            audit::record(&mut conn, &actor, "approve_supplier", "supplier", Some(supplier.id));
+            events.publish(Event::supplier(EventKind::SupplierApproved, supplier.id));
             // Send email
             email_service::send_approved_email(&pool, &supplier.email);
             HttpResponse::Ok().json(supplier)
@@ -89,6 +127,7 @@ pub async fn approve_supplier(
 
 pub async fn reset_database(
     pool: web::Data<DbPool>,
+    actor: AuthUser,
 ) -> impl Responder {
     let mut conn = pool.get().expect("couldn't get db connection from pool");
     use crate::db::schema::{offers, requests, suppliers};
@@ -103,6 +142,8 @@ pub async fn reset_database(
     // For now we wipe all to clear "registros".
     let _ = diesel::delete(suppliers::table).execute(&mut conn);
 
+    audit::record(&mut conn, &actor, "reset_database", "database", None);
+
     HttpResponse::Ok().json("Database reset successfully")
 }
 
@@ -116,6 +157,8 @@ pub struct ComplianceUpdate {
 
 pub async fn update_compliance(
     pool: web::Data<DbPool>,
+    events: web::Data<EventHub>,
+    actor: AuthUser,
     path: web::Path<i32>,
     item: web::Json<ComplianceUpdate>,
 ) -> impl Responder {
@@ -133,7 +176,11 @@ pub async fn update_compliance(
         .execute(&mut conn);
 
     match res {
-        Ok(_) => HttpResponse::Ok().json("Estado de cumplimiento actualizado"),
+        Ok(_) => {
+            audit::record(&mut conn, &actor, "update_compliance", "supplier", Some(supplier_id));
+            events.publish(Event::supplier(EventKind::SupplierUpdated, supplier_id));
+            HttpResponse::Ok().json("Estado de cumplimiento actualizado")
+        },
         Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
     }
 }
@@ -0,0 +1,374 @@
+use actix_web::{web, HttpResponse, Responder};
+use chrono::NaiveDateTime;
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use crate::db::{DbPool, models::{Request, Supplier}, schema::{offers, requests, suppliers}};
+use crate::db::status::{BiddingMode, OfferStatus, RequestStatus};
+use crate::api::{audit, events::EventHub, invitations, offers::{award_error_response, award_offer, normalized_offers, notify_award, AwardError, ComparedOffer}, security::AuthUser, validation::Violation};
+
+/// Longest automatic extension a request can configure, in minutes.
+pub const MAX_EXTENSION_MINUTES: i32 = 60;
+
+/// Most bidding rounds an auction can run.
+pub const MAX_ROUNDS: i32 = 10;
+
+/// Longest round after the first one, in minutes.
+pub const MAX_ROUND_MINUTES: i32 = 24 * 60;
+
+pub fn is_auction(req: &Request) -> bool {
+    req.bidding_mode == BiddingMode::Auction
+}
+
+/// An auction is live while it takes bids or has rounds left; it can't be awarded until
+/// its last round closes.
+pub fn auction_live(req: &Request) -> bool {
+    is_auction(req)
+        && req.status.is_bidding()
+        && (req.deadline > chrono::Local::now().naive_local() || has_rounds_left(req))
+}
+
+pub fn has_rounds_left(req: &Request) -> bool {
+    req.auction_round < req.auction_rounds
+}
+
+/// Opens the next round of an auction whose current round just closed; it runs for
+/// `auction_round_minutes` from `now`.
+pub(crate) fn start_next_round(conn: &mut PgConnection, req: &Request, now: NaiveDateTime) -> QueryResult<Request> {
+    diesel::update(requests::table.find(req.id))
+        .set((
+            requests::auction_round.eq(req.auction_round + 1),
+            requests::deadline.eq(now + chrono::Duration::minutes(req.auction_round_minutes.into())),
+        ))
+        .get_result::<Request>(conn)
+}
+
+pub(crate) fn open_response(req: &Request) -> HttpResponse {
+    HttpResponse::Conflict().json(serde_json::json!({
+        "error": "auction_open",
+        "message": "La subasta sigue abierta; se puede adjudicar cuando cierre",
+        "deadline": req.deadline
+    }))
+}
+
+/// Lowest price a supplier has bid in an auction, withdrawn bids included. Bids are quoted in
+/// the request currency, so prices compare directly.
+fn own_best_bid(conn: &mut PgConnection, req_id: i32, supplier_id: i32) -> QueryResult<Option<Decimal>> {
+    offers::table
+        .filter(offers::request_id.eq(req_id))
+        .filter(offers::supplier_id.eq(supplier_id))
+        .order(offers::price.asc())
+        .select(offers::price)
+        .first::<Decimal>(conn)
+        .optional()
+}
+
+/// A bid must be below the bidder's own previous bid by at least `min_decrement`; the first
+/// bid is free.
+fn improves_on(bid: Decimal, previous: Option<Decimal>, min_decrement: Decimal) -> bool {
+    match previous {
+        Some(own) => bid < own && bid <= own - min_decrement,
+        None => true,
+    }
+}
+
+/// Violation for a bid that doesn't improve on the supplier's own previous bid by the minimum
+/// decrement, or that comes from a newcomer after the first round; `None` when it's
+/// accepted. Rival prices play no part, suppliers never learn them.
+pub(crate) fn check_own_bid(conn: &mut PgConnection, req: &Request, supplier_id: i32, subtotal: Decimal) -> QueryResult<Option<Violation>> {
+    let previous = own_best_bid(conn, req.id, supplier_id)?;
+    if previous.is_none() && req.auction_round > 1 {
+        return Ok(Some(Violation::new(
+            "auction_round",
+            "A partir de la segunda ronda solo pueden pujar los proveedores que ya pujaron",
+        )));
+    }
+    if improves_on(subtotal, previous, req.auction_min_decrement) {
+        return Ok(None);
+    }
+    let max_bid = previous.unwrap_or_default() - req.auction_min_decrement;
+    Ok(Some(Violation::new(
+        "auction_decrement",
+        format!(
+            "Tu puja debe ser menor a tu puja anterior por al menos {} {} (máximo {} {})",
+            req.auction_min_decrement, req.currency, max_bid, req.currency
+        ),
+    )))
+}
+
+/// Auction rules for a bid: it is quoted in the request currency and improves on the
+/// supplier's own previous bid by at least the minimum decrement. The decrement is checked
+/// again once the auction is locked for the bid, see `offers::place_bid`.
+pub(crate) fn check_bid(
+    conn: &mut PgConnection,
+    violations: &mut Vec<Violation>,
+    req: &Request,
+    supplier_id: i32,
+    currency_code: &str,
+    subtotal: Decimal,
+) {
+    if currency_code != req.currency {
+        violations.push(Violation::new(
+            "auction_currency",
+            format!("Las pujas de esta subasta deben cotizarse en {}", req.currency),
+        ));
+    }
+
+    if let Ok(Some(violation)) = check_own_bid(conn, req, supplier_id, subtotal) {
+        violations.push(violation);
+    }
+}
+
+pub(crate) struct BidWindow {
+    pub request: Request,
+    pub extended: bool,
+}
+
+/// Locks an auction for an incoming bid; `None` when it closed in the meantime.
+/// A bid received in the last minutes pushes the deadline back so rivals can answer it.
+pub(crate) fn lock_for_bid(conn: &mut PgConnection, req_id: i32) -> QueryResult<Option<BidWindow>> {
+    let req = requests::table.find(req_id).for_update().first::<Request>(conn)?;
+    let now = chrono::Local::now().naive_local();
+    if !req.status.is_bidding() || req.deadline < now {
+        return Ok(None);
+    }
+
+    let Some(deadline) = extended_deadline(req.deadline, now, req.auction_extension_minutes) else {
+        return Ok(Some(BidWindow { request: req, extended: false }));
+    };
+
+    let request = diesel::update(requests::table.find(req_id))
+        .set(requests::deadline.eq(deadline))
+        .get_result::<Request>(conn)?;
+    Ok(Some(BidWindow { request, extended: true }))
+}
+
+/// Deadline `extension_minutes` after a bid placed at `now`, when that is later than the
+/// current `deadline`; `None` when the bid doesn't extend the auction.
+fn extended_deadline(deadline: NaiveDateTime, now: NaiveDateTime, extension_minutes: i32) -> Option<NaiveDateTime> {
+    let extended = now + chrono::Duration::minutes(extension_minutes.into());
+    (extended > deadline).then_some(extended)
+}
+
+/// Bids of an auction, best first: lowest price, and the earliest bid on a tie.
+fn ranked_bids(conn: &mut PgConnection, req: &Request) -> QueryResult<Vec<ComparedOffer>> {
+    let today = chrono::Local::now().date_naive();
+    let mut bids = normalized_offers(conn, req, today)?;
+    bids.sort_by_key(|b| (b.normalized_price.is_none(), b.normalized_price, b.offer.updated_at));
+    Ok(bids)
+}
+
+#[derive(Serialize)]
+pub struct AuctionPosition {
+    pub rank: usize,
+    pub offer_id: i32,
+    pub supplier_id: i32,
+    pub supplier_name: String,
+    /// Subtotal in the request currency
+    pub price: Option<Decimal>,
+    /// Number of bids placed, i.e. the revision of the offer
+    pub bids: i32,
+    pub last_bid_at: NaiveDateTime,
+    pub status: OfferStatus,
+    pub needs_reconfirmation: bool,
+}
+
+/// State of an auction. Suppliers only learn their own position; staff get the full ranking.
+#[derive(Serialize)]
+pub struct AuctionView {
+    pub request_id: i32,
+    pub currency: String,
+    pub deadline: NaiveDateTime,
+    pub live: bool,
+    pub min_decrement: Decimal,
+    pub extension_minutes: i32,
+    /// Round taking bids, out of `rounds`
+    pub round: i32,
+    pub rounds: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rank: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub own_price: Option<Decimal>,
+    /// Staff only, like the ranking
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bidders: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ranking: Option<Vec<AuctionPosition>>,
+}
+
+pub async fn auction_status(
+    pool: web::Data<DbPool>,
+    actor: AuthUser,
+    path: web::Path<i32>,
+) -> impl Responder {
+    let req_id = path.into_inner();
+    let mut conn = pool.get().expect("couldn't get db connection from pool");
+
+    let request = match requests::table.find(req_id).first::<Request>(&mut conn) {
+        Ok(r) => r,
+        Err(diesel::result::Error::NotFound) => return HttpResponse::NotFound().body("Request not found"),
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    };
+    if !actor.is_staff() && !invitations::can_see(&mut conn, &request, actor.id).unwrap_or(false) {
+        return HttpResponse::NotFound().body("Request not found");
+    }
+    if !is_auction(&request) {
+        return HttpResponse::Conflict().body("La solicitud no es una subasta");
+    }
+
+    let bids = match ranked_bids(&mut conn, &request) {
+        Ok(b) => b,
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    };
+
+    let mut view = AuctionView {
+        request_id: request.id,
+        currency: request.currency.clone(),
+        deadline: request.deadline,
+        live: auction_live(&request),
+        min_decrement: request.auction_min_decrement,
+        extension_minutes: request.auction_extension_minutes,
+        round: request.auction_round,
+        rounds: request.auction_rounds,
+        rank: None,
+        own_price: None,
+        bidders: None,
+        ranking: None,
+    };
+
+    if !actor.is_staff() {
+        // Rank only: suppliers never see rival prices, names or anything derived from them
+        if let Some((i, own)) = bids.iter().enumerate().find(|(_, b)| b.offer.supplier_id == actor.id) {
+            view.rank = Some(i + 1);
+            view.own_price = Some(own.offer.price);
+        }
+        return HttpResponse::Ok().json(view);
+    }
+
+    let supplier_ids: Vec<i32> = bids.iter().map(|b| b.offer.supplier_id).collect();
+    let bidders = match suppliers::table
+        .filter(suppliers::id.eq_any(&supplier_ids))
+        .load::<Supplier>(&mut conn)
+    {
+        Ok(list) => list,
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    };
+
+    let ranking: Vec<AuctionPosition> = bids
+        .into_iter()
+        .enumerate()
+        .map(|(i, b)| AuctionPosition {
+            rank: i + 1,
+            offer_id: b.offer.id,
+            supplier_id: b.offer.supplier_id,
+            supplier_name: bidders
+                .iter()
+                .find(|s| s.id == b.offer.supplier_id)
+                .map(|s| s.name.clone())
+                .unwrap_or_default(),
+            price: b.normalized_price,
+            bids: b.offer.revision,
+            last_bid_at: b.offer.updated_at,
+            status: b.offer.status,
+            needs_reconfirmation: b.offer.needs_reconfirmation,
+        })
+        .collect();
+    view.bidders = Some(ranking.len());
+    view.ranking = Some(ranking);
+
+    HttpResponse::Ok().json(view)
+}
+
+/// Awards a closed auction to its best-ranked bid that can still win.
+pub async fn award_auction(
+    pool: web::Data<DbPool>,
+    events: web::Data<EventHub>,
+    actor: AuthUser,
+    path: web::Path<i32>,
+) -> impl Responder {
+    let req_id = path.into_inner();
+    let mut conn = pool.get().expect("couldn't get db connection from pool");
+
+    let request = match requests::table.find(req_id).first::<Request>(&mut conn) {
+        Ok(r) => r,
+        Err(diesel::result::Error::NotFound) => return HttpResponse::NotFound().body("Request not found"),
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    };
+    if !is_auction(&request) {
+        return HttpResponse::Conflict().body("La solicitud no es una subasta");
+    }
+    if auction_live(&request) {
+        return open_response(&request);
+    }
+    if request.status == RequestStatus::Awarded {
+        return award_error_response(AwardError::AlreadyAwarded);
+    }
+
+    let candidates: Vec<i32> = match ranked_bids(&mut conn, &request) {
+        Ok(bids) => bids
+            .into_iter()
+            .filter(|b| b.normalized_price.is_some())
+            .filter(|b| b.offer.status == OfferStatus::Sent && !b.offer.needs_reconfirmation)
+            .map(|b| b.offer.id)
+            .collect(),
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    };
+
+    for off_id in candidates {
+        match award_offer(&mut conn, off_id, None) {
+            Ok(award) => {
+                if award.newly_awarded {
+                    audit::record(&mut conn, &actor, "award_auction", "offer", Some(award.winner.id));
+                    notify_award(&pool, &events, &mut conn, &award);
+                }
+                return HttpResponse::Ok().json(award.winner);
+            },
+            Err(AwardError::IncompleteOffer) => continue,
+            Err(e) => return award_error_response(e),
+        }
+    }
+
+    HttpResponse::Conflict().body("La subasta no tiene pujas que se puedan adjudicar")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::test_support::dec;
+
+    fn at(hour: u32, minute: u32) -> NaiveDateTime {
+        chrono::NaiveDate::from_ymd_opt(2026, 3, 1).unwrap().and_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn first_bid_needs_no_decrement() {
+        assert!(improves_on(dec("1000"), None, dec("10")));
+    }
+
+    #[test]
+    fn bid_must_beat_the_own_previous_bid_by_the_decrement() {
+        let previous = Some(dec("1000"));
+        assert!(improves_on(dec("990"), previous, dec("10")));
+        assert!(improves_on(dec("900"), previous, dec("10")));
+        assert!(!improves_on(dec("995"), previous, dec("10")));
+        assert!(!improves_on(dec("1000"), previous, dec("10")));
+        // Without a decrement the bid still has to be lower
+        assert!(!improves_on(dec("1000"), previous, dec("0")));
+        assert!(improves_on(dec("999.99"), previous, dec("0")));
+    }
+
+    #[test]
+    fn late_bids_push_the_deadline_back() {
+        assert_eq!(extended_deadline(at(12, 0), at(11, 57), 5), Some(at(12, 2)));
+        assert_eq!(extended_deadline(at(12, 0), at(11, 50), 5), None);
+        assert_eq!(extended_deadline(at(12, 0), at(11, 59), 0), None);
+    }
+
+    #[test]
+    fn bid_exactly_one_window_before_the_close_does_not_extend() {
+        // 11:55 + 5 minutes lands on the current deadline, which is not an extension
+        assert_eq!(extended_deadline(at(12, 0), at(11, 55), 5), None);
+        assert_eq!(extended_deadline(at(12, 0), at(11, 56), 5), Some(at(12, 1)));
+    }
+}
@@ -0,0 +1,67 @@
+use actix_web::{web, HttpResponse, Responder};
+use diesel::pg::{Pg, PgConnection};
+use diesel::prelude::*;
+use crate::api::{pagination::{self, ListQuery, Page}, security::AuthUser};
+use crate::db::{DbPool, models::{AuditEntry, NewAuditEntry}, schema::audit_log};
+
+/// Records that an internal user performed `action` on `entity`.
+/// Failures are logged but never abort the action being audited.
+pub fn record(conn: &mut PgConnection, actor: &AuthUser, action: &str, entity: &str, entity_id: Option<i32>) {
+    let entry = NewAuditEntry {
+        user_id: actor.id,
+        action: action.to_string(),
+        entity: entity.to_string(),
+        entity_id,
+    };
+
+    if let Err(e) = diesel::insert_into(audit_log::table).values(&entry).execute(conn) {
+        eprintln!("Error recording audit entry '{}': {}", action, e);
+    }
+}
+
+const AUDIT_SORTS: &[&str] = &["created_at"];
+
+/// Audit entries, newest first; `from`/`to` filter on the date of the action.
+fn filtered_entries(query: &ListQuery) -> audit_log::BoxedQuery<'static, Pg> {
+    let mut q = audit_log::table.into_boxed();
+    if let Some(start) = query.starts_at() {
+        q = q.filter(audit_log::created_at.ge(start));
+    }
+    if let Some(end) = query.ends_before() {
+        q = q.filter(audit_log::created_at.lt(end));
+    }
+    q
+}
+
+pub async fn list_audit_log(
+    pool: web::Data<DbPool>,
+    query: web::Query<ListQuery>,
+) -> impl Responder {
+    let query = query.into_inner();
+    let Some((_, desc)) = query.sort(AUDIT_SORTS, "-created_at") else {
+        return pagination::invalid_sort(AUDIT_SORTS);
+    };
+    let mut conn = pool.get().expect("couldn't get db connection from pool");
+
+    let total = match filtered_entries(&query).count().get_result::<i64>(&mut conn) {
+        Ok(t) => t,
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    };
+
+    let q = filtered_entries(&query);
+    let ordered = if desc {
+        q.order((audit_log::created_at.desc(), audit_log::id.desc()))
+    } else {
+        q.order((audit_log::created_at.asc(), audit_log::id.asc()))
+    };
+
+    let results = ordered
+        .limit(query.per_page())
+        .offset(query.offset())
+        .load::<AuditEntry>(&mut conn);
+
+    match results {
+        Ok(list) => HttpResponse::Ok().json(Page::new(list, total, &query)),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
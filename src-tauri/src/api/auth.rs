@@ -1,11 +1,11 @@
 use actix_web::{web, HttpResponse, Responder};
-use jsonwebtoken::{encode, EncodingKey, Header};
-use serde::{Deserialize, Serialize};
+use serde::Deserialize;
 use bcrypt::verify;
 use chrono::{Utc, Duration};
-use crate::db::{DbPool, models::{Supplier, NewSupplier}, schema::suppliers};
+use crate::db::{DbPool, models::{Supplier, NewSupplier, User}, schema::{suppliers, users}};
 use diesel::prelude::*;
 use crate::email_service;
+use crate::api::security::{Claims, JwtKeys, Role};
 
 #[derive(Deserialize)]
 pub struct LoginInput {
@@ -13,18 +13,69 @@ pub struct LoginInput {
     pub password: String,
 }
 
-#[derive(Serialize)]
-struct Claims {
-    sub: String,
-    exp: usize,
+/// Self-registration of a supplier. Compliance flags are never taken from the client: new
+/// accounts wait for an admin to review and approve them.
+#[derive(Deserialize)]
+pub struct RegisterInput {
+    pub name: String,
+    pub contact: String,
+    pub email: String,
+    pub phone: String,
+    pub password: String,
+}
+
+fn session_expiration() -> usize {
+    Utc::now()
+        .checked_add_signed(Duration::hours(4))
+        .expect("valid timestamp")
+        .timestamp() as usize
 }
 
 pub async fn login(
     pool: web::Data<DbPool>,
+    keys: web::Data<JwtKeys>,
     item: web::Json<LoginInput>,
 ) -> impl Responder {
     let mut conn = pool.get().expect("couldn't get db connection from pool");
 
+    // Internal staff accounts take precedence over supplier accounts
+    let staff_result = users::table
+        .filter(users::email.eq(&item.email))
+        .first::<User>(&mut conn);
+
+    if let Ok(user) = staff_result {
+        if !verify(&item.password, &user.password_hash).unwrap_or(false) {
+            return HttpResponse::Unauthorized().body("Invalid credentials");
+        }
+        if !user.active {
+            return HttpResponse::Unauthorized().body("Cuenta desactivada.");
+        }
+        let user_role = match Role::parse(&user.role) {
+            Some(r) => r,
+            None => return HttpResponse::InternalServerError().body("Invalid role"),
+        };
+
+        let claims = Claims {
+            sub: user.email.clone(),
+            uid: user.id,
+            role: user_role,
+            exp: session_expiration(),
+        };
+
+        return match keys.sign(&claims) {
+            Ok(token) => HttpResponse::Ok().json(serde_json::json!({
+                "token": token,
+                "user": {
+                    "id": user.id,
+                    "name": user.name,
+                    "email": user.email,
+                    "role": user_role
+                }
+            })),
+            Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+        };
+    }
+
     use crate::db::schema::suppliers::dsl::*;
 
     let supplier_result = suppliers
@@ -39,27 +90,25 @@ pub async fn login(
                     return HttpResponse::Unauthorized().body("Cuenta pendiente de aprobación por el administrador.");
                 }
 
-                let expiration = Utc::now()
-                    .checked_add_signed(Duration::hours(4))
-                    .expect("valid timestamp")
-                    .timestamp();
-
                 let claims = Claims {
                     sub: supplier.email.clone(),
-                    exp: expiration as usize,
+                    uid: supplier.id,
+                    role: Role::Supplier,
+                    exp: session_expiration(),
                 };
 
-                // In a real app, use a secret from env
-                let key = b"secret"; 
-                let token = encode(&Header::default(), &claims, &EncodingKey::from_secret(key))
-                    .unwrap();
+                let token = match keys.sign(&claims) {
+                    Ok(t) => t,
+                    Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+                };
 
                 HttpResponse::Ok().json(serde_json::json!({ 
                     "token": token,
                     "user": {
                         "id": supplier.id,
                         "name": supplier.name,
-                        "email": supplier.email
+                        "email": supplier.email,
+                        "role": Role::Supplier
                     }
                 }))
             } else {
@@ -72,25 +121,29 @@ pub async fn login(
 
 pub async fn register(
     pool: web::Data<DbPool>,
-    item: web::Json<NewSupplier>,
+    item: web::Json<RegisterInput>,
 ) -> impl Responder {
     let mut conn = pool.get().expect("couldn't get db connection from pool");
-    
-    // Hash password
-    // Note: NewSupplier has password_hash field. The client might send plain password? 
-    // Usually client sends 'password'. My NewSupplier struct has 'password_hash'. 
-    // I should create a RegisterInput struct that takes password and hashes it.
-    // For simplicity, I'll assume NewSupplier acts as input but I'll hash the 'password_hash' field (treating it as plain text initially)
-    // or better, define a RegisterInput separate from NewSupplier.
-    
-    // I will write it as if I'm taking NewSupplier but modifying the hash.
-    // However, NewSupplier is Insertable.
-    // Let's assume the JSON matches NewSupplier but I need to hash 'password_hash' before insert.
-    // Actually simpler:
-    
-    let mut new_supplier = item.into_inner();
-    let hashed = bcrypt::hash(&new_supplier.password_hash, bcrypt::DEFAULT_COST).unwrap_or(new_supplier.password_hash.clone());
-    new_supplier.password_hash = hashed;
+    let input = item.into_inner();
+
+    let hashed = match bcrypt::hash(&input.password, bcrypt::DEFAULT_COST) {
+        Ok(h) => h,
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    };
+
+    let new_supplier = NewSupplier {
+        name: input.name,
+        contact: input.contact,
+        email: input.email,
+        phone: input.phone,
+        password_hash: hashed,
+        created_at: chrono::Local::now().naive_local(),
+        documents: String::new(),
+        active: false,
+        is_reviewed: false,
+        is_approved: false,
+        is_audited: false,
+    };
 
     let res = diesel::insert_into(suppliers::table)
         .values(&new_supplier)
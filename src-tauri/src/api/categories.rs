@@ -0,0 +1,347 @@
+use actix_web::{web, HttpResponse, Responder};
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use serde::Deserialize;
+use crate::db::{DbPool, models::{Category, NewCategory, Request, RequestCategory, SupplierCategory}};
+use crate::db::schema::{categories, request_categories, requests, supplier_categories, suppliers};
+use crate::db::status::RequestStatus;
+use crate::api::{audit, events::{Event, EventHub, EventKind}, security::AuthUser, validation::{self, Violation}};
+use crate::email_service;
+
+#[derive(Deserialize)]
+pub struct CategoryInput {
+    pub name: String,
+    #[serde(default)]
+    pub active: Option<bool>,
+}
+
+/// Category ids to link, replacing the current ones.
+#[derive(Deserialize)]
+pub struct CategoryIdsInput {
+    pub categories: Vec<i32>,
+}
+
+/// A category of one of the requests being listed.
+pub(crate) struct LinkedCategory {
+    pub request_id: i32,
+    pub category: Category,
+}
+
+/// Stable key of a category name: lowercase words joined by `-` ("Equipo de cómputo" -> "equipo-de-cómputo").
+pub fn slugify(name: &str) -> String {
+    name.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Value kept in `requests.tags` so the tag filter and search keep working.
+pub(crate) fn tags_of(cats: &[Category]) -> String {
+    cats.iter().map(|c| c.name.as_str()).collect::<Vec<_>>().join(",")
+}
+
+/// Loads the active categories with the given ids, or rejects the list when one is unknown or inactive.
+pub(crate) fn check_categories(conn: &mut PgConnection, ids: &[i32]) -> Result<Vec<Category>, HttpResponse> {
+    let mut wanted = ids.to_vec();
+    wanted.sort_unstable();
+    wanted.dedup();
+
+    let found = categories::table
+        .filter(categories::id.eq_any(&wanted))
+        .filter(categories::active.eq(true))
+        .order(categories::name.asc())
+        .load::<Category>(conn)
+        .map_err(|e| HttpResponse::InternalServerError().body(e.to_string()))?;
+
+    let missing: Vec<String> = wanted
+        .iter()
+        .filter(|id| !found.iter().any(|c| c.id == **id))
+        .map(|id| id.to_string())
+        .collect();
+    if !missing.is_empty() {
+        return Err(validation::rejected(vec![Violation::new(
+            "categories_known",
+            format!("Categorías inexistentes o inactivas: {}", missing.join(", ")),
+        )]));
+    }
+    Ok(found)
+}
+
+/// Active categories named in a comma-separated tag list, as sent by the ERP.
+pub(crate) fn matching_tags(conn: &mut PgConnection, tags: &str) -> QueryResult<Vec<Category>> {
+    let slugs: Vec<String> = tags.split(',').map(slugify).filter(|s| !s.is_empty()).collect();
+    categories::table
+        .filter(categories::slug.eq_any(slugs))
+        .filter(categories::active.eq(true))
+        .order(categories::name.asc())
+        .load::<Category>(conn)
+}
+
+/// Replaces the categories of a request.
+pub(crate) fn link_request(conn: &mut PgConnection, req_id: i32, cats: &[Category]) -> QueryResult<()> {
+    diesel::delete(request_categories::table.filter(request_categories::request_id.eq(req_id))).execute(conn)?;
+    let links: Vec<RequestCategory> = cats
+        .iter()
+        .map(|c| RequestCategory { request_id: req_id, category_id: c.id })
+        .collect();
+    if !links.is_empty() {
+        diesel::insert_into(request_categories::table).values(&links).execute(conn)?;
+    }
+    Ok(())
+}
+
+/// Categories of the given requests, ordered by name.
+pub(crate) fn load_linked(conn: &mut PgConnection, req_ids: &[i32]) -> QueryResult<Vec<LinkedCategory>> {
+    let rows = request_categories::table
+        .inner_join(categories::table)
+        .filter(request_categories::request_id.eq_any(req_ids))
+        .order(categories::name.asc())
+        .select((request_categories::request_id, categories::all_columns))
+        .load::<(i32, Category)>(conn)?;
+    Ok(rows.into_iter().map(|(request_id, category)| LinkedCategory { request_id, category }).collect())
+}
+
+/// Ids of the categories a supplier serves.
+pub(crate) fn subscriptions(conn: &mut PgConnection, supplier_id: i32) -> QueryResult<Vec<i32>> {
+    supplier_categories::table
+        .filter(supplier_categories::supplier_id.eq(supplier_id))
+        .select(supplier_categories::category_id)
+        .load::<i32>(conn)
+}
+
+/// Emails the active suppliers that serve any category of a newly published request.
+/// Requests without categories reach nobody; suppliers find them in the list. Invite-only
+/// requests only reach their invitees.
+pub(crate) fn notify_subscribers(pool: &DbPool, conn: &mut PgConnection, req: &Request) {
+    if req.status != RequestStatus::Published || req.invite_only {
+        return;
+    }
+
+    let linked = request_categories::table
+        .filter(request_categories::request_id.eq(req.id))
+        .select(request_categories::category_id);
+    let subscribed = supplier_categories::table
+        .filter(supplier_categories::category_id.eq_any(linked))
+        .select(supplier_categories::supplier_id);
+
+    let emails: Vec<String> = suppliers::table
+        .filter(suppliers::id.eq_any(subscribed))
+        .filter(suppliers::active.eq(true))
+        .select(suppliers::email)
+        .load(conn)
+        .unwrap_or_default();
+
+    for to in emails {
+        email_service::send_new_request_email(pool, &to, &req.title);
+    }
+}
+
+/// Active categories by name; staff also get the inactive ones.
+pub async fn list_categories(
+    pool: web::Data<DbPool>,
+    actor: AuthUser,
+) -> impl Responder {
+    let mut conn = pool.get().expect("couldn't get db connection from pool");
+
+    let mut q = categories::table.order(categories::name.asc()).into_boxed();
+    if !actor.is_staff() {
+        q = q.filter(categories::active.eq(true));
+    }
+
+    match q.load::<Category>(&mut conn) {
+        Ok(list) => HttpResponse::Ok().json(list),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+fn check_name(name: &str) -> Result<String, HttpResponse> {
+    let slug = slugify(name);
+    if slug.is_empty() {
+        return Err(validation::rejected(vec![Violation::new("name_required", "La categoría necesita un nombre")]));
+    }
+    Ok(slug)
+}
+
+fn duplicate_name() -> HttpResponse {
+    HttpResponse::Conflict().body("Ya existe una categoría con ese nombre")
+}
+
+pub async fn create_category(
+    pool: web::Data<DbPool>,
+    actor: AuthUser,
+    item: web::Json<CategoryInput>,
+) -> impl Responder {
+    let slug = match check_name(&item.name) {
+        Ok(s) => s,
+        Err(rejection) => return rejection,
+    };
+
+    let new_category = NewCategory {
+        slug,
+        name: item.name.trim().to_string(),
+        active: item.active.unwrap_or(true),
+    };
+
+    let mut conn = pool.get().expect("couldn't get db connection from pool");
+
+    let res = diesel::insert_into(categories::table)
+        .values(&new_category)
+        .get_result::<Category>(&mut conn);
+
+    match res {
+        Ok(c) => {
+            audit::record(&mut conn, &actor, "create_category", "category", Some(c.id));
+            HttpResponse::Ok().json(c)
+        },
+        Err(diesel::result::Error::DatabaseError(diesel::result::DatabaseErrorKind::UniqueViolation, _)) => duplicate_name(),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+/// Renames or (de)activates a category. Inactive categories keep their links but can't be
+/// assigned or subscribed to anymore.
+pub async fn update_category(
+    pool: web::Data<DbPool>,
+    actor: AuthUser,
+    path: web::Path<i32>,
+    item: web::Json<CategoryInput>,
+) -> impl Responder {
+    let cat_id = path.into_inner();
+    let slug = match check_name(&item.name) {
+        Ok(s) => s,
+        Err(rejection) => return rejection,
+    };
+
+    let mut conn = pool.get().expect("couldn't get db connection from pool");
+
+    let updated = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+        let current = categories::table.find(cat_id).for_update().first::<Category>(conn)?;
+        let renamed = diesel::update(categories::table.find(cat_id))
+            .set((
+                categories::slug.eq(slug),
+                categories::name.eq(item.name.trim()),
+                categories::active.eq(item.active.unwrap_or(current.active)),
+            ))
+            .get_result::<Category>(conn)?;
+
+        // Keep the denormalized tags of the linked requests in step with the new name
+        let linked: Vec<i32> = request_categories::table
+            .filter(request_categories::category_id.eq(cat_id))
+            .select(request_categories::request_id)
+            .load(conn)?;
+        let all_links = load_linked(conn, &linked)?;
+        for req_id in linked {
+            let names: Vec<&str> = all_links
+                .iter()
+                .filter(|l| l.request_id == req_id)
+                .map(|l| l.category.name.as_str())
+                .collect();
+            diesel::update(requests::table.find(req_id))
+                .set(requests::tags.eq(names.join(",")))
+                .execute(conn)?;
+        }
+        Ok(renamed)
+    });
+
+    match updated {
+        Ok(c) => {
+            audit::record(&mut conn, &actor, "update_category", "category", Some(c.id));
+            HttpResponse::Ok().json(c)
+        },
+        Err(diesel::result::Error::NotFound) => HttpResponse::NotFound().body("Category not found"),
+        Err(diesel::result::Error::DatabaseError(diesel::result::DatabaseErrorKind::UniqueViolation, _)) => duplicate_name(),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+/// Replaces the categories of a request and its tags with their names.
+pub async fn set_request_categories(
+    pool: web::Data<DbPool>,
+    events: web::Data<EventHub>,
+    actor: AuthUser,
+    path: web::Path<i32>,
+    item: web::Json<CategoryIdsInput>,
+) -> impl Responder {
+    let req_id = path.into_inner();
+    let mut conn = pool.get().expect("couldn't get db connection from pool");
+
+    let cats = match check_categories(&mut conn, &item.categories) {
+        Ok(c) => c,
+        Err(rejection) => return rejection,
+    };
+
+    let updated = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+        let req = diesel::update(requests::table.find(req_id))
+            .set(requests::tags.eq(tags_of(&cats)))
+            .get_result::<Request>(conn)?;
+        link_request(conn, req.id, &cats)?;
+        Ok(req)
+    });
+
+    match updated {
+        Ok(req) => {
+            audit::record(&mut conn, &actor, "set_request_categories", "request", Some(req.id));
+            events.publish(Event::request(EventKind::RequestUpdated, &req));
+            HttpResponse::Ok().json(cats)
+        },
+        Err(diesel::result::Error::NotFound) => HttpResponse::NotFound().body("Request not found"),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+fn subscribed_categories(conn: &mut PgConnection, supplier_id: i32) -> QueryResult<Vec<Category>> {
+    supplier_categories::table
+        .inner_join(categories::table)
+        .filter(supplier_categories::supplier_id.eq(supplier_id))
+        .order(categories::name.asc())
+        .select(categories::all_columns)
+        .load::<Category>(conn)
+}
+
+/// Categories the logged-in supplier serves.
+pub async fn my_categories(
+    pool: web::Data<DbPool>,
+    actor: AuthUser,
+) -> impl Responder {
+    let mut conn = pool.get().expect("couldn't get db connection from pool");
+
+    match subscribed_categories(&mut conn, actor.id) {
+        Ok(list) => HttpResponse::Ok().json(list),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+/// Replaces the categories the logged-in supplier serves. An empty list means "all of them".
+pub async fn set_my_categories(
+    pool: web::Data<DbPool>,
+    actor: AuthUser,
+    item: web::Json<CategoryIdsInput>,
+) -> impl Responder {
+    let mut conn = pool.get().expect("couldn't get db connection from pool");
+
+    let cats = match check_categories(&mut conn, &item.categories) {
+        Ok(c) => c,
+        Err(rejection) => return rejection,
+    };
+
+    let saved = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+        diesel::delete(supplier_categories::table.filter(supplier_categories::supplier_id.eq(actor.id))).execute(conn)?;
+        let links: Vec<SupplierCategory> = cats
+            .iter()
+            .map(|c| SupplierCategory { supplier_id: actor.id, category_id: c.id })
+            .collect();
+        if !links.is_empty() {
+            diesel::insert_into(supplier_categories::table).values(&links).execute(conn)?;
+        }
+        Ok(())
+    });
+
+    match saved {
+        Ok(()) => {
+            audit::record(&mut conn, &actor, "set_supplier_categories", "supplier", Some(actor.id));
+            HttpResponse::Ok().json(cats)
+        },
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
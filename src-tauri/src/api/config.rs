@@ -1,6 +1,9 @@
 use actix_web::{web, HttpResponse, Responder};
-use crate::db::{DbPool, models::{EmailConfig, UpdateEmailConfig}};
+use crate::db::{DbPool, models::{EmailConfig, TaxSettings, UpdateEmailConfig, UpdateTaxSettings}};
+use diesel::pg::PgConnection;
 use diesel::prelude::*;
+use rust_decimal::Decimal;
+use crate::api::{audit, security::AuthUser, validation::{self, Violation}};
 
 const PASSWORD_MASK: &str = "********";
 
@@ -23,8 +26,30 @@ pub async fn get_email_config(
     }
 }
 
+/// Branding needed by the login screen before there is a session.
+pub async fn get_public_config(
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let mut conn = pool.get().expect("couldn't get db connection from pool");
+    use crate::db::schema::email_config::dsl::*;
+
+    let branding = email_config
+        .find(1)
+        .select((ui_theme, login_image_url))
+        .first::<(String, String)>(&mut conn);
+
+    match branding {
+        Ok((theme, image)) => HttpResponse::Ok().json(serde_json::json!({
+            "ui_theme": theme,
+            "login_image_url": image
+        })),
+        Err(_) => HttpResponse::NotFound().body("Config not found"),
+    }
+}
+
 pub async fn save_email_config(
     pool: web::Data<DbPool>,
+    actor: AuthUser,
     item: web::Json<UpdateEmailConfig>,
 ) -> impl Responder {
     let mut conn = pool.get().expect("couldn't get db connection from pool");
@@ -53,6 +78,7 @@ pub async fn save_email_config(
 
     match result {
         Ok(mut c) => {
+            audit::record(&mut conn, &actor, "save_email_config", "email_config", Some(c.id));
             if !c.smtp_password.is_empty() {
                 c.smtp_password = PASSWORD_MASK.to_string();
             }
@@ -100,3 +126,101 @@ pub async fn test_email_config(
         }
     }
 }
+
+pub(crate) fn tax_settings(conn: &mut PgConnection) -> QueryResult<TaxSettings> {
+    use crate::db::schema::tax_settings::dsl::*;
+
+    tax_settings.find(1).first::<TaxSettings>(conn)
+}
+
+/// Taxes on an offer subtotal, rounded to cents.
+pub(crate) struct TaxBreakdown {
+    pub tax_amount: Decimal,
+    pub withholding_amount: Decimal,
+    pub total: Decimal,
+}
+
+pub(crate) fn apply_taxes(settings: &TaxSettings, subtotal: Decimal) -> TaxBreakdown {
+    let tax_amount = (subtotal * settings.iva_rate).round_dp(2);
+    let withholding_amount = (subtotal * settings.withholding_rate).round_dp(2);
+
+    TaxBreakdown {
+        tax_amount,
+        withholding_amount,
+        total: subtotal + tax_amount - withholding_amount,
+    }
+}
+
+pub async fn get_tax_config(
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let mut conn = pool.get().expect("couldn't get db connection from pool");
+
+    match tax_settings(&mut conn) {
+        Ok(t) => HttpResponse::Ok().json(t),
+        Err(_) => HttpResponse::NotFound().body("Config not found"),
+    }
+}
+
+/// New rates only apply to offers sent afterwards; existing offers keep the rates they were quoted with.
+pub async fn save_tax_config(
+    pool: web::Data<DbPool>,
+    actor: AuthUser,
+    item: web::Json<UpdateTaxSettings>,
+) -> impl Responder {
+    let mut violations = Vec::new();
+    for (rule, value) in [("iva_rate", item.iva_rate), ("withholding_rate", item.withholding_rate)] {
+        if value < Decimal::ZERO || value >= Decimal::ONE {
+            violations.push(Violation::new(rule, "La tasa debe expresarse como fracción entre 0 y 1 (ej. 0.16)"));
+        }
+    }
+    if !violations.is_empty() {
+        return validation::rejected(violations);
+    }
+
+    let mut conn = pool.get().expect("couldn't get db connection from pool");
+    use crate::db::schema::tax_settings::dsl::*;
+
+    let result = diesel::update(tax_settings.find(1))
+        .set((&item.into_inner(), updated_at.eq(chrono::Local::now().naive_local())))
+        .get_result::<TaxSettings>(&mut conn);
+
+    match result {
+        Ok(t) => {
+            audit::record(&mut conn, &actor, "save_tax_config", "tax_settings", Some(t.id));
+            HttpResponse::Ok().json(t)
+        },
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::test_support::dec;
+
+    fn settings(iva_rate: &str, withholding_rate: &str) -> TaxSettings {
+        TaxSettings {
+            id: 1,
+            iva_rate: dec(iva_rate),
+            withholding_rate: dec(withholding_rate),
+            updated_at: chrono::NaiveDate::from_ymd_opt(2026, 1, 1).unwrap().and_hms_opt(0, 0, 0).unwrap(),
+        }
+    }
+
+    #[test]
+    fn adds_iva_and_subtracts_withholding() {
+        let taxes = apply_taxes(&settings("0.16", "0.0125"), dec("1000"));
+        assert_eq!(taxes.tax_amount, dec("160.00"));
+        assert_eq!(taxes.withholding_amount, dec("12.50"));
+        assert_eq!(taxes.total, dec("1147.50"));
+    }
+
+    #[test]
+    fn taxes_are_rounded_to_cents() {
+        let taxes = apply_taxes(&settings("0.16", "0"), dec("10.03"));
+        assert_eq!(taxes.tax_amount, dec("1.60"));
+        assert_eq!(taxes.withholding_amount, dec("0"));
+        assert_eq!(taxes.total, dec("11.63"));
+    }
+}
@@ -0,0 +1,147 @@
+use actix_web::{web, HttpResponse, Responder};
+use chrono::NaiveDate;
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use rust_decimal::Decimal;
+use crate::db::{DbPool, models::{ExchangeRate, NewExchangeRate}, schema::exchange_rates};
+use crate::api::{audit, security::AuthUser, validation::{self, Violation}};
+
+/// Currency every exchange rate is expressed in.
+pub const BASE_CURRENCY: &str = "MXN";
+
+/// ISO 4217 style code: three uppercase letters.
+pub fn is_currency_code(code: &str) -> bool {
+    code.len() == 3 && code.chars().all(|c| c.is_ascii_uppercase())
+}
+
+/// The base currency, or any currency with at least one exchange rate on file.
+pub(crate) fn is_known(conn: &mut PgConnection, code: &str) -> QueryResult<bool> {
+    if code == BASE_CURRENCY {
+        return Ok(true);
+    }
+
+    let rates: i64 = exchange_rates::table
+        .filter(exchange_rates::currency.eq(code))
+        .count()
+        .get_result(conn)?;
+    Ok(rates > 0)
+}
+
+/// Rate in effect for `code` on `date`, i.e. the latest one not dated after it.
+pub(crate) fn rate_on(conn: &mut PgConnection, code: &str, date: NaiveDate) -> QueryResult<Option<Decimal>> {
+    if code == BASE_CURRENCY {
+        return Ok(Some(Decimal::ONE));
+    }
+
+    exchange_rates::table
+        .filter(exchange_rates::currency.eq(code))
+        .filter(exchange_rates::effective_date.le(date))
+        .order(exchange_rates::effective_date.desc())
+        .select(exchange_rates::rate)
+        .first::<Decimal>(conn)
+        .optional()
+}
+
+/// Converts `amount` with the rates in effect on `date`, rounded to cents.
+/// Returns `None` when either currency has no rate for that date.
+pub(crate) fn convert(
+    conn: &mut PgConnection,
+    amount: Decimal,
+    from: &str,
+    to: &str,
+    date: NaiveDate,
+) -> QueryResult<Option<Decimal>> {
+    if from == to {
+        return Ok(Some(amount));
+    }
+
+    let (Some(from_rate), Some(to_rate)) = (rate_on(conn, from, date)?, rate_on(conn, to, date)?) else {
+        return Ok(None);
+    };
+    Ok(Some(cross_convert(amount, from_rate, to_rate)))
+}
+
+/// `amount` in a currency worth `from_rate` base units, expressed in one worth `to_rate`.
+fn cross_convert(amount: Decimal, from_rate: Decimal, to_rate: Decimal) -> Decimal {
+    (amount * from_rate / to_rate).round_dp(2)
+}
+
+pub async fn list_rates(
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let mut conn = pool.get().expect("couldn't get db connection from pool");
+    use crate::db::schema::exchange_rates::dsl::*;
+
+    let results = exchange_rates
+        .order((currency.asc(), effective_date.desc()))
+        .load::<ExchangeRate>(&mut conn);
+
+    match results {
+        Ok(list) => HttpResponse::Ok().json(list),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+/// Creates the rate for a currency and date, or replaces it if one was already captured.
+pub async fn save_rate(
+    pool: web::Data<DbPool>,
+    actor: AuthUser,
+    item: web::Json<NewExchangeRate>,
+) -> impl Responder {
+    let mut input = item.into_inner();
+    input.currency = input.currency.trim().to_uppercase();
+
+    let mut violations = Vec::new();
+    if !is_currency_code(&input.currency) {
+        violations.push(Violation::new("currency_code", "La moneda debe ser un código de tres letras (ej. USD)"));
+    } else if input.currency == BASE_CURRENCY {
+        violations.push(Violation::new("currency_base", format!("{} es la moneda base; su tipo de cambio siempre es 1", BASE_CURRENCY)));
+    }
+    if input.rate <= Decimal::ZERO {
+        violations.push(Violation::new("rate_positive", "El tipo de cambio debe ser mayor a cero"));
+    }
+    if !violations.is_empty() {
+        return validation::rejected(violations);
+    }
+
+    let mut conn = pool.get().expect("couldn't get db connection from pool");
+
+    let saved = diesel::insert_into(exchange_rates::table)
+        .values(&input)
+        .on_conflict((exchange_rates::currency, exchange_rates::effective_date))
+        .do_update()
+        .set(exchange_rates::rate.eq(input.rate))
+        .get_result::<ExchangeRate>(&mut conn);
+
+    match saved {
+        Ok(r) => {
+            audit::record(&mut conn, &actor, "save_exchange_rate", "exchange_rate", Some(r.id));
+            HttpResponse::Ok().json(r)
+        },
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::test_support::dec;
+
+    #[test]
+    fn currency_codes_are_three_uppercase_letters() {
+        assert!(is_currency_code("USD"));
+        assert!(!is_currency_code("usd"));
+        assert!(!is_currency_code("US"));
+        assert!(!is_currency_code("US1"));
+    }
+
+    #[test]
+    fn converts_through_the_base_currency() {
+        // 100 USD at 17.25 MXN each
+        assert_eq!(cross_convert(dec("100"), dec("17.25"), Decimal::ONE), dec("1725.00"));
+        // 1725 MXN back to USD
+        assert_eq!(cross_convert(dec("1725"), Decimal::ONE, dec("17.25")), dec("100.00"));
+        // 100 EUR (18.90) to USD (17.25), rounded to cents
+        assert_eq!(cross_convert(dec("100"), dec("18.90"), dec("17.25")), dec("109.57"));
+    }
+}
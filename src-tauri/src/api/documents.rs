@@ -0,0 +1,441 @@
+use actix_web::{web, HttpResponse, Responder};
+use chrono::NaiveDate;
+use diesel::pg::{Pg, PgConnection};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::db::{DbPool, models::{NewSupplierDocument, SupplierDocument}};
+use crate::db::schema::{supplier_documents, suppliers};
+use crate::db::status::{DocumentType, ReviewStatus};
+use crate::api::{audit, events::{Event, EventHub, EventKind}, files, pagination::{self, ListQuery, Page}, security::AuthUser, validation::{self, Violation}};
+use crate::email_service;
+
+const DOCUMENT_SORTS: &[&str] = &["created_at", "expires_on"];
+
+#[derive(Deserialize)]
+pub struct DocumentInput {
+    pub doc_type: DocumentType,
+    /// Name returned by `POST /api/upload`
+    pub file: String,
+    pub issued_on: Option<NaiveDate>,
+    pub expires_on: Option<NaiveDate>,
+}
+
+#[derive(Deserialize)]
+pub struct ReviewInput {
+    pub status: ReviewStatus,
+    #[serde(default)]
+    pub notes: String,
+}
+
+/// A document with its display name. `current` marks the latest upload of its type; older
+/// ones are kept as history.
+#[derive(Serialize)]
+pub struct DocumentView {
+    #[serde(flatten)]
+    pub document: SupplierDocument,
+    pub label: &'static str,
+    pub current: bool,
+}
+
+/// Whether `doc` is the latest upload of its type for its supplier among `docs`.
+fn is_current(doc: &SupplierDocument, docs: &[SupplierDocument]) -> bool {
+    !docs
+        .iter()
+        .any(|d| d.supplier_id == doc.supplier_id && d.doc_type == doc.doc_type && d.id > doc.id)
+}
+
+fn document_views(docs: Vec<SupplierDocument>) -> Vec<DocumentView> {
+    let currents: Vec<bool> = docs.iter().map(|d| is_current(d, &docs)).collect();
+    docs.into_iter()
+        .zip(currents)
+        .map(|(document, current)| DocumentView { label: document.doc_type.label(), document, current })
+        .collect()
+}
+
+/// Whether a newer document of the same type was uploaded after `doc`.
+pub(crate) fn is_superseded(conn: &mut PgConnection, doc: &SupplierDocument) -> QueryResult<bool> {
+    let newer: i64 = supplier_documents::table
+        .filter(supplier_documents::supplier_id.eq(doc.supplier_id))
+        .filter(supplier_documents::doc_type.eq(doc.doc_type))
+        .filter(supplier_documents::id.gt(doc.id))
+        .count()
+        .get_result(conn)?;
+    Ok(newer > 0)
+}
+
+/// Why a required document keeps a supplier from bidding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Shortfall {
+    /// Never uploaded
+    Missing,
+    /// The current document was rejected in review
+    Rejected,
+    /// The current document expired
+    Lapsed,
+    /// The renewal of an expired document wasn't approved yet
+    RenewalPending,
+}
+
+impl Shortfall {
+    pub(crate) fn message(&self, doc_type: DocumentType) -> String {
+        let label = doc_type.label();
+        match self {
+            Shortfall::Missing => format!("Falta tu {}; súbelo para poder ofertar", label),
+            Shortfall::Rejected => format!("Tu {} fue rechazado; sube uno corregido para volver a ofertar", label),
+            Shortfall::Lapsed => format!("Tu {} venció; sube uno vigente para volver a ofertar", label),
+            Shortfall::RenewalPending => format!("Tu {} renovado aún no ha sido aprobado", label),
+        }
+    }
+}
+
+/// Required documents missing from `docs` (one supplier's history) or not usable: rejected,
+/// expired, or renewed after expiring but still pending review.
+fn shortfalls_in(docs: &[SupplierDocument]) -> Vec<(DocumentType, Shortfall)> {
+    DocumentType::ALL
+        .into_iter()
+        .filter(DocumentType::is_required)
+        .filter_map(|doc_type| {
+            let history: Vec<&SupplierDocument> = docs.iter().filter(|d| d.doc_type == doc_type).collect();
+            let Some(current) = history.iter().max_by_key(|d| d.id) else {
+                return Some((doc_type, Shortfall::Missing));
+            };
+            let shortfall = if current.lapsed_at.is_some() {
+                Shortfall::Lapsed
+            } else if current.review_status == ReviewStatus::Rejected {
+                Shortfall::Rejected
+            } else if current.review_status == ReviewStatus::Pending && history.iter().any(|d| d.lapsed_at.is_some()) {
+                Shortfall::RenewalPending
+            } else {
+                return None;
+            };
+            Some((doc_type, shortfall))
+        })
+        .collect()
+}
+
+/// Required documents that keep a supplier from bidding; see `shortfalls_in`.
+pub(crate) fn required_shortfalls(conn: &mut PgConnection, supplier_id: i32) -> QueryResult<Vec<(DocumentType, Shortfall)>> {
+    let docs = supplier_documents_of(conn, supplier_id)?;
+    Ok(shortfalls_in(&docs))
+}
+
+fn supplier_documents_of(conn: &mut PgConnection, supplier_id: i32) -> QueryResult<Vec<SupplierDocument>> {
+    supplier_documents::table
+        .filter(supplier_documents::supplier_id.eq(supplier_id))
+        .order((supplier_documents::doc_type.asc(), supplier_documents::id.desc()))
+        .load::<SupplierDocument>(conn)
+}
+
+fn documents_response(pool: &DbPool, supplier_id: i32) -> HttpResponse {
+    let mut conn = pool.get().expect("couldn't get db connection from pool");
+
+    match supplier_documents_of(&mut conn, supplier_id) {
+        Ok(list) => HttpResponse::Ok().json(document_views(list)),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+/// Documents of the logged-in supplier, current ones and history.
+pub async fn my_documents(
+    pool: web::Data<DbPool>,
+    actor: AuthUser,
+) -> impl Responder {
+    documents_response(&pool, actor.id)
+}
+
+pub async fn supplier_documents(
+    pool: web::Data<DbPool>,
+    actor: AuthUser,
+    path: web::Path<i32>,
+) -> impl Responder {
+    let supplier_id = path.into_inner();
+    if !actor.can_access_supplier(supplier_id) {
+        return HttpResponse::Forbidden().body("No tienes acceso a este proveedor");
+    }
+
+    documents_response(&pool, supplier_id)
+}
+
+/// `own_file` tells whether the supplier registering the document uploaded `input.file`.
+fn check_document(input: &DocumentInput, own_file: bool) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    let today = chrono::Local::now().date_naive();
+
+    if !own_file {
+        violations.push(Violation::new("file_uploaded", "Sube el archivo antes de registrar el documento"));
+    }
+    if input.issued_on.is_some_and(|d| d > today) {
+        violations.push(Violation::new("issued_on_past", "La fecha de emisión no puede ser futura"));
+    }
+    if input.expires_on.is_some_and(|d| d < today) {
+        violations.push(Violation::new("expires_on_future", "El documento ya está vencido"));
+    }
+    if input.doc_type.expires() && input.expires_on.is_none() {
+        violations.push(Violation::new("expires_on_required", format!("Indica la fecha de vencimiento de {}", input.doc_type.label())));
+    }
+    if let (Some(issued), Some(expires)) = (input.issued_on, input.expires_on) {
+        if expires < issued {
+            violations.push(Violation::new("expires_after_issued", "La fecha de vencimiento debe ser posterior a la de emisión"));
+        }
+    }
+    violations
+}
+
+/// Registers an uploaded file as a document of the logged-in supplier. It replaces the
+/// current document of the same type and waits for an admin to review it.
+pub async fn add_my_document(
+    pool: web::Data<DbPool>,
+    events: web::Data<EventHub>,
+    actor: AuthUser,
+    item: web::Json<DocumentInput>,
+) -> impl Responder {
+    let input = item.into_inner();
+    let mut conn = pool.get().expect("couldn't get db connection from pool");
+
+    let own_file = match files::uploaded_by_supplier(&mut conn, &input.file, actor.id) {
+        Ok(own) => own,
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    };
+    let violations = check_document(&input, own_file);
+    if !violations.is_empty() {
+        return validation::rejected(violations);
+    }
+
+    let new_document = NewSupplierDocument {
+        supplier_id: actor.id,
+        doc_type: input.doc_type,
+        file: input.file,
+        issued_on: input.issued_on,
+        expires_on: input.expires_on,
+    };
+
+    let inserted = diesel::insert_into(supplier_documents::table)
+        .values(&new_document)
+        .get_result::<SupplierDocument>(&mut conn);
+
+    match inserted {
+        Ok(doc) => {
+            audit::record(&mut conn, &actor, "add_document", "supplier_document", Some(doc.id));
+            events.publish(Event::supplier(EventKind::SupplierUpdated, actor.id));
+            HttpResponse::Ok().json(DocumentView { label: doc.doc_type.label(), document: doc, current: true })
+        },
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+/// Documents of every supplier, e.g. `status=pending` for the review queue; `from`/`to`
+/// filter on the upload date.
+fn filtered_documents(query: &ListQuery, status: Option<ReviewStatus>) -> supplier_documents::BoxedQuery<'static, Pg> {
+    let mut q = supplier_documents::table.into_boxed();
+    if let Some(st) = status {
+        q = q.filter(supplier_documents::review_status.eq(st));
+    }
+    if let Some(supplier_id) = query.supplier_id {
+        q = q.filter(supplier_documents::supplier_id.eq(supplier_id));
+    }
+    if let Some(start) = query.starts_at() {
+        q = q.filter(supplier_documents::created_at.ge(start));
+    }
+    if let Some(end) = query.ends_before() {
+        q = q.filter(supplier_documents::created_at.lt(end));
+    }
+    q
+}
+
+pub async fn list_documents(
+    pool: web::Data<DbPool>,
+    query: web::Query<ListQuery>,
+) -> impl Responder {
+    let query = query.into_inner();
+    let Some((sort, desc)) = query.sort(DOCUMENT_SORTS, "created_at") else {
+        return pagination::invalid_sort(DOCUMENT_SORTS);
+    };
+    let status = match query.status.as_deref() {
+        Some(s) => match ReviewStatus::parse(s) {
+            Some(st) => Some(st),
+            None => return pagination::invalid_status(s),
+        },
+        None => None,
+    };
+    let mut conn = pool.get().expect("couldn't get db connection from pool");
+
+    let total = match filtered_documents(&query, status).count().get_result::<i64>(&mut conn) {
+        Ok(t) => t,
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    };
+
+    let q = filtered_documents(&query, status);
+    let ordered = match (sort, desc) {
+        ("expires_on", false) => q.order(supplier_documents::expires_on.asc().nulls_last()),
+        ("expires_on", true) => q.order(supplier_documents::expires_on.desc().nulls_last()),
+        (_, true) => q.order(supplier_documents::created_at.desc()),
+        (_, false) => q.order(supplier_documents::created_at.asc()),
+    };
+
+    let results = ordered
+        .then_order_by(supplier_documents::id.asc())
+        .limit(query.per_page())
+        .offset(query.offset())
+        .load::<SupplierDocument>(&mut conn);
+
+    let list = match results {
+        Ok(list) => list,
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    };
+
+    // `current` has to look at the whole history of the listed suppliers, not only this page
+    let supplier_ids: Vec<i32> = list.iter().map(|d| d.supplier_id).collect();
+    let history = match supplier_documents::table
+        .filter(supplier_documents::supplier_id.eq_any(&supplier_ids))
+        .load::<SupplierDocument>(&mut conn)
+    {
+        Ok(h) => h,
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    };
+
+    let views: Vec<DocumentView> = list
+        .into_iter()
+        .map(|document| {
+            let current = is_current(&document, &history);
+            DocumentView { label: document.doc_type.label(), document, current }
+        })
+        .collect();
+
+    HttpResponse::Ok().json(Page::new(views, total, &query))
+}
+
+/// Approves or rejects a document; a rejection needs notes telling the supplier what to fix.
+pub async fn review_document(
+    pool: web::Data<DbPool>,
+    events: web::Data<EventHub>,
+    actor: AuthUser,
+    path: web::Path<i32>,
+    item: web::Json<ReviewInput>,
+) -> impl Responder {
+    let doc_id = path.into_inner();
+
+    let mut violations = Vec::new();
+    if item.status == ReviewStatus::Pending {
+        violations.push(Violation::new("review_status", "La revisión debe aprobar o rechazar el documento"));
+    }
+    if item.status == ReviewStatus::Rejected && item.notes.trim().is_empty() {
+        violations.push(Violation::new("review_notes_required", "Indica por qué se rechaza el documento"));
+    }
+    if !violations.is_empty() {
+        return validation::rejected(violations);
+    }
+
+    let mut conn = pool.get().expect("couldn't get db connection from pool");
+
+    let updated = diesel::update(supplier_documents::table.find(doc_id))
+        .set((
+            supplier_documents::review_status.eq(item.status),
+            supplier_documents::review_notes.eq(item.notes.trim()),
+            supplier_documents::reviewed_by.eq(actor.id),
+            supplier_documents::reviewed_at.eq(chrono::Local::now().naive_local()),
+        ))
+        .get_result::<SupplierDocument>(&mut conn);
+
+    match updated {
+        Ok(doc) => {
+            let action = if doc.review_status == ReviewStatus::Approved { "approve_document" } else { "reject_document" };
+            audit::record(&mut conn, &actor, action, "supplier_document", Some(doc.id));
+            events.publish(Event::supplier(EventKind::SupplierUpdated, doc.supplier_id));
+
+            let supplier_email: String = suppliers::table
+                .find(doc.supplier_id)
+                .select(suppliers::email)
+                .first(&mut conn)
+                .unwrap_or_default();
+            email_service::send_document_reviewed_email(
+                &pool,
+                &supplier_email,
+                doc.doc_type.label(),
+                doc.review_status == ReviewStatus::Approved,
+                &doc.review_notes,
+            );
+
+            let history = supplier_documents_of(&mut conn, doc.supplier_id).unwrap_or_default();
+            let current = is_current(&doc, &history);
+            HttpResponse::Ok().json(DocumentView { label: doc.doc_type.label(), document: doc, current })
+        },
+        Err(diesel::result::Error::NotFound) => HttpResponse::NotFound().body("Document not found"),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn doc(id: i32, doc_type: DocumentType, review_status: ReviewStatus, lapsed: bool) -> SupplierDocument {
+        let uploaded = chrono::NaiveDate::from_ymd_opt(2026, 1, 1).unwrap().and_hms_opt(0, 0, 0).unwrap();
+        SupplierDocument {
+            id,
+            supplier_id: 1,
+            doc_type,
+            file: format!("{}.pdf", id),
+            issued_on: None,
+            expires_on: None,
+            review_status,
+            review_notes: String::new(),
+            reviewed_by: None,
+            reviewed_at: None,
+            created_at: uploaded,
+            reminder_days: None,
+            lapsed_at: lapsed.then_some(uploaded),
+        }
+    }
+
+    fn approved_set() -> Vec<SupplierDocument> {
+        vec![
+            doc(1, DocumentType::Rfc, ReviewStatus::Approved, false),
+            doc(2, DocumentType::ConstanciaSituacionFiscal, ReviewStatus::Approved, false),
+            doc(3, DocumentType::OpinionCumplimiento, ReviewStatus::Approved, false),
+        ]
+    }
+
+    #[test]
+    fn approved_required_documents_have_no_shortfalls() {
+        assert!(shortfalls_in(&approved_set()).is_empty());
+    }
+
+    #[test]
+    fn never_uploaded_required_types_are_missing() {
+        let docs = vec![doc(1, DocumentType::Rfc, ReviewStatus::Approved, false), doc(2, DocumentType::CertificadoIso, ReviewStatus::Approved, false)];
+        assert_eq!(
+            shortfalls_in(&docs),
+            vec![
+                (DocumentType::ConstanciaSituacionFiscal, Shortfall::Missing),
+                (DocumentType::OpinionCumplimiento, Shortfall::Missing),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejected_current_document_is_a_shortfall() {
+        let mut docs = approved_set();
+        docs.push(doc(4, DocumentType::Rfc, ReviewStatus::Rejected, false));
+        assert_eq!(shortfalls_in(&docs), vec![(DocumentType::Rfc, Shortfall::Rejected)]);
+    }
+
+    #[test]
+    fn lapsed_document_blocks_until_its_renewal_is_approved() {
+        let mut docs = approved_set();
+        docs[2] = doc(3, DocumentType::OpinionCumplimiento, ReviewStatus::Approved, true);
+        assert_eq!(shortfalls_in(&docs), vec![(DocumentType::OpinionCumplimiento, Shortfall::Lapsed)]);
+
+        docs.push(doc(4, DocumentType::OpinionCumplimiento, ReviewStatus::Pending, false));
+        assert_eq!(shortfalls_in(&docs), vec![(DocumentType::OpinionCumplimiento, Shortfall::RenewalPending)]);
+
+        docs[3].review_status = ReviewStatus::Approved;
+        assert!(shortfalls_in(&docs).is_empty());
+    }
+
+    #[test]
+    fn first_upload_pending_review_is_not_a_shortfall() {
+        let mut docs = approved_set();
+        docs.push(doc(4, DocumentType::Rfc, ReviewStatus::Pending, false));
+        assert!(shortfalls_in(&docs).is_empty());
+    }
+}
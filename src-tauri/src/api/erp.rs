@@ -1,9 +1,14 @@
 use actix_web::{web, HttpResponse, Responder, HttpRequest};
 use serde::{Deserialize, Serialize};
-use crate::db::models::{NewRequest};
-use crate::db::schema::requests;
+use crate::db::models::{NewRequest, Request};
+use crate::db::status::{BiddingMode, RequestStatus};
+use crate::api::categories;
+use crate::api::events::{Event, EventHub, EventKind};
+use crate::api::currency::{is_known as currency_known, BASE_CURRENCY};
+use crate::api::requests::{check_lines, insert_lines, record_version, RequestLineInput};
 use diesel::prelude::*;
 use chrono::NaiveDateTime;
+use rust_decimal::Decimal;
 
 #[derive(Deserialize, Debug)]
 pub struct ErpRequestItem {
@@ -14,6 +19,13 @@ pub struct ErpRequestItem {
     pub units: String,
     pub deadline: Option<String>, // ISO string from JSON
     pub tags: Option<String>,
+    pub bidding_mode: Option<BiddingMode>,
+    pub currency: Option<String>,
+    /// Date the goods are needed by, as YYYY-MM-DD
+    pub required_date: Option<String>,
+    /// Bill of materials; when omitted the request is a single line built from the header
+    #[serde(default)]
+    pub lines: Vec<RequestLineInput>,
 }
 
 use crate::db::DbPool;
@@ -27,6 +39,7 @@ pub struct ImportResponse {
 
 pub async fn import_requests(
     pool: web::Data<DbPool>,
+    events: web::Data<EventHub>,
     req: HttpRequest, 
     items: web::Json<Vec<ErpRequestItem>>
 ) -> impl Responder {
@@ -36,15 +49,15 @@ pub async fn import_requests(
         None => return HttpResponse::Unauthorized().body("Missing X-API-KEY"),
     };
 
-    // TODO: Move to config/env
-    if api_key != "secret-erp-key" {
+    let expected_key = std::env::var("ERP_API_KEY").unwrap_or_else(|_| "secret-erp-key".to_string());
+    if api_key != expected_key {
         return HttpResponse::Unauthorized().body("Invalid API Key");
     }
 
     let mut connection = pool.get().expect("couldn't get db connection from pool");
     let mut count = 0;
 
-    for item in items.iter() {
+    for item in items.into_inner() {
         // Parse deadline or default to now + 7 days
         let deadline_dt = if let Some(d) = &item.deadline {
              NaiveDateTime::parse_from_str(d, "%Y-%m-%dT%H:%M:%S")
@@ -60,8 +73,19 @@ pub async fn import_requests(
             quantity: item.quantity,
             units: item.units.clone(),
             tags: item.tags.clone().unwrap_or_default(),
-            status: "open".to_string(),
+            status: RequestStatus::Published,
             origin_erp: item.external_id.clone(),
+            bidding_mode: item.bidding_mode.unwrap_or(BiddingMode::Open),
+            currency: item.currency.clone().unwrap_or_else(|| BASE_CURRENCY.to_string()),
+            required_date: item
+                .required_date
+                .as_deref()
+                .and_then(|d| chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d").ok()),
+            auction_min_decrement: Decimal::ZERO,
+            auction_extension_minutes: 5,
+            invite_only: false,
+            auction_rounds: 1,
+            auction_round_minutes: 10,
         };
 
         // Upsert logic (simplified: check if external_id exists, else insert)
@@ -77,11 +101,35 @@ pub async fn import_requests(
             .unwrap_or(0);
 
         if existing == 0 {
-             diesel::insert_into(requests)
-                .values(&new_req)
-                .execute(&mut connection)
-                .unwrap_or(0);
-             count += 1;
+             let mut violations = Vec::new();
+             check_lines(&mut violations, &item.lines);
+             if !violations.is_empty() {
+                 println!("Skipping ERP request {}: invalid lines", item.external_id);
+                 continue;
+             }
+             if !currency_known(&mut connection, &new_req.currency).unwrap_or(false) {
+                 println!("Skipping ERP request {}: unknown currency {}", item.external_id, new_req.currency);
+                 continue;
+             }
+
+             // Tags that name a known category link the request to it, so its subscribers hear about it
+             let cats = categories::matching_tags(&mut connection, &new_req.tags).unwrap_or_default();
+
+             let inserted = connection.transaction::<_, diesel::result::Error, _>(|conn| {
+                 let req = diesel::insert_into(requests)
+                    .values(&new_req)
+                    .get_result::<Request>(conn)?;
+                 insert_lines(conn, &req, item.lines)?;
+                 categories::link_request(conn, req.id, &cats)?;
+                 record_version(conn, &req, "Importación ERP", None)?;
+                 Ok(req)
+             });
+
+             if let Ok(created) = inserted {
+                 events.publish(Event::request(EventKind::RequestCreated, &created));
+                 categories::notify_subscribers(&pool, &mut connection, &created);
+                 count += 1;
+             }
         }
     }
 
@@ -0,0 +1,391 @@
+use actix_web::{web, HttpResponse, Responder};
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use crate::db::{DbPool, models::{NewOfferEvaluation, OfferEvaluation, Request, ScoringWeights, Supplier, UpdateScoringWeights}};
+use crate::db::schema::{offer_evaluations, request_lines, requests, scoring_weights, suppliers};
+use crate::db::status::OfferStatus;
+use crate::api::{audit, offers::{bids_sealed, normalized_offers, sealed_response}, security::AuthUser, validation::{self, Violation}};
+
+/// Score of one criterion, from 0 (worst) to 100 (best).
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ScoreBreakdown {
+    pub price: f64,
+    pub delivery: f64,
+    pub compliance: f64,
+    pub experience: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ScoredOffer {
+    pub rank: usize,
+    pub offer_id: i32,
+    pub supplier_id: i32,
+    pub supplier_name: String,
+    /// Total with taxes in the request currency; `None` when it could not be converted
+    pub normalized_total: Option<Decimal>,
+    pub delivery_days: Option<f64>,
+    /// Promised delivery falls after the date the request needs the goods
+    #[serde(default)]
+    pub late: bool,
+    /// Quotes every line of the request
+    #[serde(default)]
+    pub complete: bool,
+    pub needs_reconfirmation: bool,
+    pub breakdown: ScoreBreakdown,
+    /// Weighted average of the breakdown
+    pub score: f64,
+}
+
+/// An evaluation as returned by the API, with its stored JSON expanded.
+#[derive(Serialize)]
+pub struct EvaluationView {
+    pub id: i32,
+    pub request_id: i32,
+    pub currency: String,
+    pub weights: UpdateScoringWeights,
+    pub ranking: Vec<ScoredOffer>,
+    pub created_by: i32,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+#[derive(Deserialize)]
+pub struct EvaluationInput {
+    /// Weights for this evaluation only; the configured defaults are used when omitted
+    pub weights: Option<UpdateScoringWeights>,
+}
+
+fn check_weights(w: &UpdateScoringWeights) -> Vec<Violation> {
+    let values = [w.price, w.delivery, w.compliance, w.experience];
+    let mut violations = Vec::new();
+
+    if values.iter().any(|v| !v.is_finite() || *v < 0.0) {
+        violations.push(Violation::new("weights_positive", "Los pesos no pueden ser negativos"));
+    } else if values.iter().sum::<f64>() <= 0.0 {
+        violations.push(Violation::new("weights_sum", "Al menos un criterio debe tener peso"));
+    }
+    violations
+}
+
+fn default_weights(conn: &mut PgConnection) -> QueryResult<UpdateScoringWeights> {
+    let w = scoring_weights::table.find(1).first::<ScoringWeights>(conn)?;
+    Ok(UpdateScoringWeights {
+        price: w.price,
+        delivery: w.delivery,
+        compliance: w.compliance,
+        experience: w.experience,
+    })
+}
+
+/// Best-effort reading of the free-text delivery times of offers sent before delivery
+/// was structured, such as "10 días", "2 semanas" or "1 mes".
+fn delivery_days(text: &str) -> Option<f64> {
+    let lower = text.to_lowercase();
+    let number: String = lower
+        .chars()
+        .skip_while(|c| !c.is_ascii_digit())
+        .take_while(|c| c.is_ascii_digit() || *c == '.')
+        .collect();
+    let amount: f64 = number.parse().ok()?;
+
+    let factor = if lower.contains("semana") {
+        7.0
+    } else if lower.contains("mes") {
+        30.0
+    } else {
+        1.0
+    };
+    Some(amount * factor)
+}
+
+/// Lowest value gets 100, the rest proportionally less.
+fn relative_score(value: Option<f64>, best: Option<f64>) -> f64 {
+    match (value, best) {
+        (Some(v), Some(b)) if v > 0.0 => (b / v * 100.0).min(100.0),
+        (Some(_), Some(_)) => 100.0,
+        _ => 0.0,
+    }
+}
+
+/// Price score over the lines of a request: each line is scored against the cheapest quote
+/// for it and lines the offer doesn't quote score 0, so partial offers can't look cheapest.
+fn price_score(quoted: &[(i32, Option<f64>)], best: &[(i32, Option<f64>)]) -> f64 {
+    if best.is_empty() {
+        return 0.0;
+    }
+    let sum: f64 = best
+        .iter()
+        .map(|(line, best_total)| {
+            let own = quoted.iter().find(|(l, _)| l == line).and_then(|(_, t)| *t);
+            relative_score(own, *best_total)
+        })
+        .sum();
+    sum / best.len() as f64
+}
+
+/// Scores the live offers of `request` and returns them ranked, best first.
+fn rank_offers(
+    conn: &mut PgConnection,
+    request: &Request,
+    weights: &UpdateScoringWeights,
+) -> QueryResult<Vec<ScoredOffer>> {
+    let today = chrono::Local::now().date_naive();
+    let compared: Vec<_> = normalized_offers(conn, request, today)?
+        .into_iter()
+        .filter(|c| c.offer.status != OfferStatus::Rejected)
+        .collect();
+
+    let supplier_ids: Vec<i32> = compared.iter().map(|c| c.offer.supplier_id).collect();
+    let bidders = suppliers::table
+        .filter(suppliers::id.eq_any(&supplier_ids))
+        .load::<Supplier>(conn)?;
+
+    let line_ids: Vec<i32> = request_lines::table
+        .filter(request_lines::request_id.eq(request.id))
+        .select(request_lines::id)
+        .load(conn)?;
+    let totals: Vec<Vec<(i32, Option<f64>)>> = compared
+        .iter()
+        .map(|c| c.line_totals.iter().map(|(line, t)| (*line, t.and_then(|t| t.to_f64()))).collect())
+        .collect();
+    let best_lines: Vec<(i32, Option<f64>)> = line_ids
+        .iter()
+        .map(|line| {
+            let best = totals
+                .iter()
+                .flatten()
+                .filter(|(l, _)| l == line)
+                .filter_map(|(_, t)| *t)
+                .reduce(f64::min);
+            (*line, best)
+        })
+        .collect();
+    let days: Vec<Option<f64>> = compared
+        .iter()
+        .map(|c| c.offer.delivery_days.map(f64::from).or_else(|| delivery_days(&c.offer.delivery_time)))
+        .collect();
+    let best_days = days.iter().flatten().cloned().reduce(f64::min);
+    let most_wins = bidders.iter().map(|s| s.earnings_count).max().unwrap_or(0);
+    let weight_sum = weights.price + weights.delivery + weights.compliance + weights.experience;
+
+    let mut ranking: Vec<ScoredOffer> = compared
+        .into_iter()
+        .zip(totals.into_iter().zip(days))
+        .map(|(c, (line_totals, delivery))| {
+            let supplier = bidders.iter().find(|s| s.id == c.offer.supplier_id);
+            let compliance = supplier
+                .map(|s| [s.is_reviewed, s.is_approved, s.is_audited].iter().filter(|f| **f).count() as f64 / 3.0 * 100.0)
+                .unwrap_or(0.0);
+            let experience = match supplier {
+                Some(s) if most_wins > 0 => s.earnings_count as f64 / most_wins as f64 * 100.0,
+                _ => 0.0,
+            };
+
+            let breakdown = ScoreBreakdown {
+                price: price_score(&line_totals, &best_lines),
+                delivery: relative_score(delivery, best_days),
+                compliance,
+                experience,
+            };
+            let score = (breakdown.price * weights.price
+                + breakdown.delivery * weights.delivery
+                + breakdown.compliance * weights.compliance
+                + breakdown.experience * weights.experience)
+                / weight_sum;
+
+            ScoredOffer {
+                rank: 0,
+                offer_id: c.offer.id,
+                supplier_id: c.offer.supplier_id,
+                supplier_name: supplier.map(|s| s.name.clone()).unwrap_or_default(),
+                normalized_total: c.normalized_total,
+                delivery_days: delivery,
+                late: c.late,
+                complete: c.complete,
+                needs_reconfirmation: c.offer.needs_reconfirmation,
+                breakdown,
+                score: (score * 100.0).round() / 100.0,
+            }
+        })
+        .collect();
+
+    ranking.sort_by(|a, b| b.score.total_cmp(&a.score));
+    for (i, scored) in ranking.iter_mut().enumerate() {
+        scored.rank = i + 1;
+    }
+    Ok(ranking)
+}
+
+fn to_view(evaluation: OfferEvaluation, currency: String) -> EvaluationView {
+    EvaluationView {
+        id: evaluation.id,
+        request_id: evaluation.request_id,
+        currency,
+        weights: serde_json::from_str(&evaluation.weights).unwrap_or_default(),
+        ranking: serde_json::from_str(&evaluation.ranking).unwrap_or_default(),
+        created_by: evaluation.created_by,
+        created_at: evaluation.created_at,
+    }
+}
+
+/// Request a stored evaluation belongs to, if it exists.
+pub(crate) fn evaluation_request(conn: &mut PgConnection, eval_id: i32) -> QueryResult<Option<i32>> {
+    offer_evaluations::table
+        .find(eval_id)
+        .select(offer_evaluations::request_id)
+        .first::<i32>(conn)
+        .optional()
+}
+
+/// Ranks the offers of a request and stores the result as an evaluation snapshot.
+pub async fn evaluate_offers(
+    pool: web::Data<DbPool>,
+    actor: AuthUser,
+    path: web::Path<i32>,
+    item: web::Json<EvaluationInput>,
+) -> impl Responder {
+    let req_id = path.into_inner();
+    let mut conn = pool.get().expect("couldn't get db connection from pool");
+
+    let request = match requests::table.find(req_id).first::<Request>(&mut conn) {
+        Ok(r) => r,
+        Err(diesel::result::Error::NotFound) => return HttpResponse::NotFound().body("Request not found"),
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    };
+
+    if bids_sealed(&request) {
+        return sealed_response(&request);
+    }
+
+    let weights = match item.into_inner().weights {
+        Some(w) => w,
+        None => match default_weights(&mut conn) {
+            Ok(w) => w,
+            Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+        },
+    };
+    let violations = check_weights(&weights);
+    if !violations.is_empty() {
+        return validation::rejected(violations);
+    }
+
+    let ranking = match rank_offers(&mut conn, &request, &weights) {
+        Ok(r) => r,
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    };
+
+    let snapshot = NewOfferEvaluation {
+        request_id: req_id,
+        weights: serde_json::to_string(&weights).unwrap_or_default(),
+        ranking: serde_json::to_string(&ranking).unwrap_or_default(),
+        created_by: actor.id,
+    };
+
+    let saved = diesel::insert_into(offer_evaluations::table)
+        .values(&snapshot)
+        .get_result::<OfferEvaluation>(&mut conn);
+
+    match saved {
+        Ok(evaluation) => {
+            audit::record(&mut conn, &actor, "evaluate_offers", "request", Some(req_id));
+            HttpResponse::Ok().json(to_view(evaluation, request.currency))
+        },
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+pub async fn list_evaluations(
+    pool: web::Data<DbPool>,
+    path: web::Path<i32>,
+) -> impl Responder {
+    let req_id = path.into_inner();
+    let mut conn = pool.get().expect("couldn't get db connection from pool");
+
+    let currency = match requests::table.find(req_id).select(requests::currency).first::<String>(&mut conn) {
+        Ok(c) => c,
+        Err(diesel::result::Error::NotFound) => return HttpResponse::NotFound().body("Request not found"),
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    };
+
+    let results = offer_evaluations::table
+        .filter(offer_evaluations::request_id.eq(req_id))
+        .order(offer_evaluations::created_at.desc())
+        .load::<OfferEvaluation>(&mut conn);
+
+    match results {
+        Ok(list) => {
+            let views: Vec<EvaluationView> = list.into_iter().map(|e| to_view(e, currency.clone())).collect();
+            HttpResponse::Ok().json(views)
+        },
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+pub async fn get_scoring_weights(
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let mut conn = pool.get().expect("couldn't get db connection from pool");
+
+    match default_weights(&mut conn) {
+        Ok(w) => HttpResponse::Ok().json(w),
+        Err(_) => HttpResponse::NotFound().body("Config not found"),
+    }
+}
+
+pub async fn save_scoring_weights(
+    pool: web::Data<DbPool>,
+    actor: AuthUser,
+    item: web::Json<UpdateScoringWeights>,
+) -> impl Responder {
+    let violations = check_weights(&item);
+    if !violations.is_empty() {
+        return validation::rejected(violations);
+    }
+
+    let mut conn = pool.get().expect("couldn't get db connection from pool");
+
+    let result = diesel::update(scoring_weights::table.find(1))
+        .set(&item.into_inner())
+        .get_result::<ScoringWeights>(&mut conn);
+
+    match result {
+        Ok(w) => {
+            audit::record(&mut conn, &actor, "save_scoring_weights", "scoring_weights", Some(w.id));
+            HttpResponse::Ok().json(w)
+        },
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_free_text_delivery_times() {
+        assert_eq!(delivery_days("10 días"), Some(10.0));
+        assert_eq!(delivery_days("Entrega en 2 semanas"), Some(14.0));
+        assert_eq!(delivery_days("1 mes"), Some(30.0));
+        assert_eq!(delivery_days("1.5 meses"), Some(45.0));
+        assert_eq!(delivery_days("inmediata"), None);
+    }
+
+    #[test]
+    fn best_value_scores_100_and_the_rest_proportionally() {
+        assert_eq!(relative_score(Some(50.0), Some(50.0)), 100.0);
+        assert_eq!(relative_score(Some(100.0), Some(50.0)), 50.0);
+        assert_eq!(relative_score(Some(0.0), Some(0.0)), 100.0);
+        assert_eq!(relative_score(None, Some(50.0)), 0.0);
+        assert_eq!(relative_score(Some(50.0), None), 0.0);
+    }
+
+    #[test]
+    fn unquoted_lines_score_zero_in_the_price_score() {
+        let best = [(1, Some(100.0)), (2, Some(50.0))];
+        assert_eq!(price_score(&[(1, Some(100.0)), (2, Some(100.0))], &best), 75.0);
+        assert_eq!(price_score(&[(1, Some(100.0))], &best), 50.0);
+        assert_eq!(price_score(&[(1, Some(100.0))], &[]), 0.0);
+    }
+}
@@ -0,0 +1,240 @@
+use std::time::Duration;
+use actix_web::{http::header, web, HttpResponse, Responder};
+use serde::Serialize;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::time::Instant;
+use crate::db::{DbPool, models::{Offer, Request, RequestInvitation, RequestQuestion}};
+use crate::db::status::{AnswerVisibility, RequestStatus};
+use crate::api::security::{self, AuthUser, Role};
+
+/// Events a slow subscriber may fall behind before it is asked to resync.
+const CHANNEL_CAPACITY: usize = 256;
+/// Comment sent on idle streams so proxies don't drop the connection.
+const KEEP_ALIVE: Duration = Duration::from_secs(15);
+/// How often an open stream checks that the caller's account still has access.
+const ACCOUNT_CHECK: Duration = Duration::from_secs(60);
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    RequestCreated,
+    RequestUpdated,
+    OfferCreated,
+    OfferUpdated,
+    OfferWithdrawn,
+    OfferWon,
+    OfferRejected,
+    SupplierApproved,
+    SupplierUpdated,
+    InvitationSent,
+    InvitationAnswered,
+    QuestionAsked,
+    QuestionAnswered,
+}
+
+impl EventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EventKind::RequestCreated => "request_created",
+            EventKind::RequestUpdated => "request_updated",
+            EventKind::OfferCreated => "offer_created",
+            EventKind::OfferUpdated => "offer_updated",
+            EventKind::OfferWithdrawn => "offer_withdrawn",
+            EventKind::OfferWon => "offer_won",
+            EventKind::OfferRejected => "offer_rejected",
+            EventKind::SupplierApproved => "supplier_approved",
+            EventKind::SupplierUpdated => "supplier_updated",
+            EventKind::InvitationSent => "invitation_sent",
+            EventKind::InvitationAnswered => "invitation_answered",
+            EventKind::QuestionAsked => "question_asked",
+            EventKind::QuestionAnswered => "question_answered",
+        }
+    }
+}
+
+/// Who may receive an event.
+#[derive(Clone, Copy, Debug)]
+enum Audience {
+    Everyone,
+    Staff,
+    /// Staff plus the one supplier the event is about
+    StaffAndSupplier(i32),
+}
+
+/// A change pushed to connected clients. It only carries ids and the new status;
+/// clients fetch the details through the regular endpoints, which apply their own checks.
+#[derive(Serialize, Clone, Debug)]
+pub struct Event {
+    pub kind: EventKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offer_id: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub supplier_id: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<&'static str>,
+    #[serde(skip)]
+    audience: Audience,
+}
+
+impl Event {
+    /// Drafts are only announced to staff, like they are only listed to staff. Invite-only
+    /// requests are too; their invitees hear about them through invitation events.
+    pub fn request(kind: EventKind, req: &Request) -> Self {
+        let audience = Self::request_audience(req);
+        Event {
+            kind,
+            request_id: Some(req.id),
+            offer_id: None,
+            supplier_id: None,
+            status: Some(req.status.as_str()),
+            audience,
+        }
+    }
+
+    /// Offer events never reach other suppliers.
+    pub fn offer(kind: EventKind, offer: &Offer) -> Self {
+        Event {
+            kind,
+            request_id: Some(offer.request_id),
+            offer_id: Some(offer.id),
+            supplier_id: Some(offer.supplier_id),
+            status: Some(offer.status.as_str()),
+            audience: Audience::StaffAndSupplier(offer.supplier_id),
+        }
+    }
+
+    pub fn supplier(kind: EventKind, supplier_id: i32) -> Self {
+        Event {
+            kind,
+            request_id: None,
+            offer_id: None,
+            supplier_id: Some(supplier_id),
+            status: None,
+            audience: Audience::StaffAndSupplier(supplier_id),
+        }
+    }
+
+    pub fn invitation(kind: EventKind, invitation: &RequestInvitation) -> Self {
+        Event {
+            kind,
+            request_id: Some(invitation.request_id),
+            offer_id: None,
+            supplier_id: Some(invitation.supplier_id),
+            status: Some(invitation.status.as_str()),
+            audience: Audience::StaffAndSupplier(invitation.supplier_id),
+        }
+    }
+
+    /// New questions go to staff. Answers reach the asker, or everyone who may see the request
+    /// when public, in which case the asker is left out of the event.
+    pub fn question(kind: EventKind, question: &RequestQuestion, req: &Request) -> Self {
+        let (audience, supplier_id) = match (kind, question.visibility, question.supplier_id) {
+            (EventKind::QuestionAnswered, Some(AnswerVisibility::Public), _) => (Self::request_audience(req), None),
+            (EventKind::QuestionAnswered, _, Some(asker)) => (Audience::StaffAndSupplier(asker), Some(asker)),
+            (_, _, asker) => (Audience::Staff, asker),
+        };
+        Event {
+            kind,
+            request_id: Some(req.id),
+            offer_id: None,
+            supplier_id,
+            status: question.visibility.map(|v| v.as_str()),
+            audience,
+        }
+    }
+
+    fn request_audience(req: &Request) -> Audience {
+        if req.status == RequestStatus::Draft || req.invite_only { Audience::Staff } else { Audience::Everyone }
+    }
+
+    fn visible_to(&self, user: &AuthUser) -> bool {
+        match self.audience {
+            Audience::Everyone => true,
+            Audience::Staff => user.is_staff(),
+            Audience::StaffAndSupplier(id) => user.is_staff() || (user.role == Role::Supplier && user.id == id),
+        }
+    }
+
+    fn to_frame(&self) -> web::Bytes {
+        let data = serde_json::to_string(self).unwrap_or_default();
+        web::Bytes::from(format!("event: {}\ndata: {}\n\n", self.kind.as_str(), data))
+    }
+}
+
+/// In-process fan-out of events to every open stream. Cloning shares the same channel.
+#[derive(Clone)]
+pub struct EventHub {
+    sender: broadcast::Sender<Event>,
+}
+
+impl Default for EventHub {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventHub {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(CHANNEL_CAPACITY);
+        EventHub { sender }
+    }
+
+    /// Sends an event to the current subscribers; with nobody listening it is dropped.
+    pub fn publish(&self, event: Event) {
+        let _ = self.sender.send(event);
+    }
+}
+
+/// Server-sent events stream with the changes the caller is allowed to see. The stream is
+/// closed once the caller's account is deactivated or changes role; the client then has to
+/// authenticate again to reconnect.
+pub async fn stream_events(
+    pool: web::Data<DbPool>,
+    events: web::Data<EventHub>,
+    actor: AuthUser,
+) -> impl Responder {
+    let receiver = events.sender.subscribe();
+    let next_check = Instant::now() + ACCOUNT_CHECK;
+
+    let stream = futures_util::stream::unfold((receiver, actor, next_check), move |(mut receiver, actor, mut next_check)| {
+        let pool = pool.clone();
+        async move {
+            loop {
+                if Instant::now() >= next_check {
+                    if !still_authorized(pool.clone(), actor.clone()).await {
+                        return None;
+                    }
+                    next_check = Instant::now() + ACCOUNT_CHECK;
+                }
+
+                let frame = match tokio::time::timeout(KEEP_ALIVE, receiver.recv()).await {
+                    Err(_) => web::Bytes::from_static(b": ping\n\n"),
+                    Ok(Ok(event)) if event.visible_to(&actor) => event.to_frame(),
+                    Ok(Ok(_)) => continue,
+                    // Events were dropped for this client; it should reload instead of trusting its state
+                    Ok(Err(RecvError::Lagged(_))) => web::Bytes::from_static(b"event: resync\ndata: {}\n\n"),
+                    Ok(Err(RecvError::Closed)) => return None,
+                };
+                return Some((Ok::<_, actix_web::Error>(frame), (receiver, actor, next_check)));
+            }
+        }
+    });
+
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header((header::CACHE_CONTROL, "no-cache"))
+        .streaming(stream)
+}
+
+/// Re-checks the account behind a stream off the async runtime. When the database can't be
+/// reached the stream stays open; it is checked again on the next round.
+async fn still_authorized(pool: web::Data<DbPool>, actor: AuthUser) -> bool {
+    web::block(move || match pool.get() {
+        Ok(mut conn) => security::still_authorized(&mut conn, &actor),
+        Err(_) => true,
+    })
+    .await
+    .unwrap_or(true)
+}
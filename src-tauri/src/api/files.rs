@@ -1,54 +1,84 @@
+use actix_files::NamedFile;
 use actix_multipart::Multipart;
-use actix_web::{web, Error, HttpResponse, Responder};
+use actix_web::{web, Error, HttpRequest, HttpResponse};
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
 use futures_util::{StreamExt, TryStreamExt};
 use std::io::Write;
 use uuid::Uuid;
+use crate::db::{DbPool, models::NewUpload};
+use crate::db::schema::uploads;
+use crate::api::security::{AuthUser, Role};
 
-pub async fn upload_file(mut payload: Multipart) -> Result<HttpResponse, Error> {
+/// Folder the uploaded files are kept in.
+fn upload_dir() -> &'static str {
+    // Improved path detection: check local uploads first, then src-tauri/uploads
+    if std::path::Path::new("uploads").exists() {
+        "uploads"
+    } else if std::path::Path::new("src-tauri/uploads").exists() {
+        "src-tauri/uploads"
+    } else {
+        // If neither exists, decide based on where we are
+        if std::path::Path::new("src-tauri").exists() {
+            "src-tauri/uploads"
+        } else {
+            "uploads"
+        }
+    }
+}
+
+/// Whether `name` can only be a file name returned by `upload_file`, not a path.
+fn is_plain_name(name: &str) -> bool {
+    !name.is_empty() && !name.contains(['/', '\\']) && !name.starts_with('.')
+}
+
+pub async fn upload_file(
+    pool: web::Data<DbPool>,
+    actor: AuthUser,
+    mut payload: Multipart,
+) -> Result<HttpResponse, Error> {
     let mut filename = String::new();
-    
+    let mut conn = pool.get().expect("couldn't get db connection from pool");
+
     // Iterate over multipart stream
     while let Ok(Some(mut field)) = payload.try_next().await {
         let content_disposition = field.content_disposition();
         let field_name = content_disposition.map(|cd| cd.get_name().unwrap_or("")).unwrap_or("");
-        
+
         if field_name == "file" {
             let original_filename = field.content_disposition()
                 .and_then(|cd| cd.get_filename().map(|s| s.to_string()))
                 .unwrap_or_else(|| "file".to_string());
-                
-            let extension = original_filename.split('.').last().unwrap_or("bin");
+
+            let extension = original_filename.split('.').next_back().unwrap_or("bin");
             let new_filename = format!("{}.{}", Uuid::new_v4(), extension);
             filename = new_filename.clone();
-            
-            // Improved path detection: check local uploads first, then src-tauri/uploads
-            let upload_dir = if std::path::Path::new("uploads").exists() {
-                "uploads"
-            } else if std::path::Path::new("src-tauri/uploads").exists() {
-                "src-tauri/uploads"
-            } else {
-                // If neither exists, decide based on where we are
-                if std::path::Path::new("src-tauri").exists() {
-                    "src-tauri/uploads"
-                } else {
-                    "uploads"
-                }
-            };
 
+            let upload_dir = upload_dir();
             if !std::path::Path::new(upload_dir).exists() {
                 std::fs::create_dir_all(upload_dir)?;
             }
 
             let filepath = format!("{}/{}", upload_dir, new_filename);
-            
+
             // File::create is blocking, in a real production app use spawn_blocking
             let mut f = std::fs::File::create(filepath)?;
-            
+
             // Field in turn is stream of *Bytes* object
             while let Some(chunk) = field.next().await {
                 let data = chunk?;
                 f.write_all(&data)?;
             }
+
+            let is_supplier = actor.role == Role::Supplier;
+            let upload = NewUpload {
+                file: new_filename,
+                supplier_id: is_supplier.then_some(actor.id),
+                user_id: (!is_supplier).then_some(actor.id),
+            };
+            if let Err(e) = diesel::insert_into(uploads::table).values(&upload).execute(&mut conn) {
+                return Ok(HttpResponse::InternalServerError().body(e.to_string()));
+            }
         }
     }
 
@@ -58,3 +88,70 @@ pub async fn upload_file(mut payload: Multipart) -> Result<HttpResponse, Error>
 
     Ok(HttpResponse::Ok().json(serde_json::json!({ "file": filename })))
 }
+
+/// Whether `supplier_id` uploaded the file `name` through `upload_file`.
+pub(crate) fn uploaded_by_supplier(conn: &mut PgConnection, name: &str, supplier_id: i32) -> QueryResult<bool> {
+    let uploads: i64 = uploads::table
+        .filter(uploads::file.eq(name))
+        .filter(uploads::supplier_id.eq(supplier_id))
+        .count()
+        .get_result(conn)?;
+    Ok(uploads > 0)
+}
+
+/// Who uploaded the file `name`: `Some(Some(supplier))`, `Some(None)` for staff, or `None`
+/// when no such file was uploaded.
+fn find_upload(conn: &mut PgConnection, name: &str) -> QueryResult<Option<Option<i32>>> {
+    if !is_plain_name(name) {
+        return Ok(None);
+    }
+    uploads::table
+        .filter(uploads::file.eq(name))
+        .select(uploads::supplier_id)
+        .first::<Option<i32>>(conn)
+        .optional()
+}
+
+fn send_file(req: &HttpRequest, name: &str) -> HttpResponse {
+    match NamedFile::open(std::path::Path::new(upload_dir()).join(name)) {
+        Ok(file) => file.into_response(req),
+        Err(_) => HttpResponse::NotFound().body("Archivo no encontrado"),
+    }
+}
+
+/// Files uploaded by staff, such as the login image, for anyone. Supplier files are only
+/// served by `supplier_file`.
+pub async fn public_file(
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    path: web::Path<String>,
+) -> HttpResponse {
+    let name = path.into_inner();
+    let mut conn = pool.get().expect("couldn't get db connection from pool");
+
+    match find_upload(&mut conn, &name) {
+        Ok(Some(None)) => send_file(&req, &name),
+        Ok(_) => HttpResponse::NotFound().body("Archivo no encontrado"),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+/// Any uploaded file for a logged-in caller; a supplier only gets its own files.
+pub async fn supplier_file(
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    actor: AuthUser,
+    path: web::Path<String>,
+) -> HttpResponse {
+    let name = path.into_inner();
+    let mut conn = pool.get().expect("couldn't get db connection from pool");
+
+    match find_upload(&mut conn, &name) {
+        Ok(Some(Some(supplier_id))) if !actor.can_access_supplier(supplier_id) => {
+            HttpResponse::Forbidden().body("No tienes acceso a este archivo")
+        },
+        Ok(Some(_)) => send_file(&req, &name),
+        Ok(None) => HttpResponse::NotFound().body("Archivo no encontrado"),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
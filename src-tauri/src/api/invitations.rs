@@ -0,0 +1,351 @@
+use std::env;
+use actix_web::{web, HttpResponse, Responder};
+use chrono::NaiveDateTime;
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::db::{DbPool, models::{NewRequestInvitation, Request, RequestInvitation}};
+use crate::db::schema::{offers, request_invitations, requests, suppliers};
+use crate::db::status::{InvitationStatus, OfferStatus, RequestStatus};
+use crate::api::{audit, events::{Event, EventHub, EventKind}, security::AuthUser, validation::{self, Violation}};
+use crate::email_service;
+
+#[derive(Deserialize)]
+pub struct InviteInput {
+    pub suppliers: Vec<i32>,
+}
+
+/// An invitation with the supplier it was sent to, as listed to staff.
+#[derive(Serialize)]
+pub struct InvitationView {
+    #[serde(flatten)]
+    pub invitation: RequestInvitation,
+    pub supplier_name: String,
+    pub supplier_email: String,
+}
+
+/// An invitation with the request it is for, as listed to the invited supplier.
+#[derive(Serialize)]
+pub struct ReceivedInvitation {
+    #[serde(flatten)]
+    pub invitation: RequestInvitation,
+    pub title: String,
+    pub deadline: NaiveDateTime,
+    pub request_status: RequestStatus,
+    pub link: String,
+}
+
+/// Address of the request in the portal, sent in the invitation email.
+fn portal_link(req_id: i32) -> String {
+    let base = env::var("PORTAL_URL").unwrap_or_else(|_| "http://localhost:1420".to_string());
+    format!("{}/dashboard?solicitud={}", base.trim_end_matches('/'), req_id)
+}
+
+fn invitation_for(conn: &mut PgConnection, req_id: i32, supplier_id: i32) -> QueryResult<Option<RequestInvitation>> {
+    request_invitations::table
+        .filter(request_invitations::request_id.eq(req_id))
+        .filter(request_invitations::supplier_id.eq(supplier_id))
+        .first::<RequestInvitation>(conn)
+        .optional()
+}
+
+/// Whether a supplier may see a request: public ones always, invite-only ones when it was
+/// invited and didn't decline.
+pub(crate) fn can_see(conn: &mut PgConnection, req: &Request, supplier_id: i32) -> QueryResult<bool> {
+    if !req.invite_only {
+        return Ok(true);
+    }
+    Ok(invitation_for(conn, req.id, supplier_id)?.is_some_and(|i| i.status != InvitationStatus::Declined))
+}
+
+/// Bidding on an invite-only request needs an invitation that wasn't declined.
+pub(crate) fn check_invited(conn: &mut PgConnection, violations: &mut Vec<Violation>, req: &Request, supplier_id: i32) {
+    if !can_see(conn, req, supplier_id).unwrap_or(false) {
+        violations.push(Violation::new("invitation_required", "Esta solicitud es solo por invitación"));
+    }
+}
+
+/// Bidding answers a pending invitation.
+pub(crate) fn accept_on_bid(conn: &mut PgConnection, req_id: i32, supplier_id: i32) -> QueryResult<()> {
+    diesel::update(
+        request_invitations::table
+            .filter(request_invitations::request_id.eq(req_id))
+            .filter(request_invitations::supplier_id.eq(supplier_id))
+            .filter(request_invitations::status.eq(InvitationStatus::Pending)),
+    )
+    .set((
+        request_invitations::status.eq(InvitationStatus::Accepted),
+        request_invitations::responded_at.eq(chrono::Local::now().naive_local()),
+    ))
+    .execute(conn)?;
+    Ok(())
+}
+
+/// Tells the invitees of a published request that haven't heard of it yet, by email and
+/// event. Invitations to a draft wait until it is published, so suppliers never learn about
+/// unpublished requests.
+pub(crate) fn notify_invitees(pool: &DbPool, events: &EventHub, conn: &mut PgConnection, req: &Request) {
+    if !req.invite_only || req.status != RequestStatus::Published {
+        return;
+    }
+
+    let pending = request_invitations::table
+        .inner_join(suppliers::table)
+        .filter(request_invitations::request_id.eq(req.id))
+        .filter(request_invitations::notified_at.is_null())
+        .filter(request_invitations::status.ne(InvitationStatus::Declined))
+        .select((request_invitations::all_columns, suppliers::email))
+        .load::<(RequestInvitation, String)>(conn)
+        .unwrap_or_default();
+
+    let link = portal_link(req.id);
+    for (invitation, to) in pending {
+        events.publish(Event::invitation(EventKind::InvitationSent, &invitation));
+        email_service::send_invitation_email(pool, &to, &req.title, req.deadline, &link);
+        let _ = diesel::update(request_invitations::table.find(invitation.id))
+            .set(request_invitations::notified_at.eq(chrono::Local::now().naive_local()))
+            .execute(conn);
+    }
+}
+
+fn invitation_views(conn: &mut PgConnection, req_id: i32) -> QueryResult<Vec<InvitationView>> {
+    let rows = request_invitations::table
+        .inner_join(suppliers::table)
+        .filter(request_invitations::request_id.eq(req_id))
+        .order(suppliers::name.asc())
+        .select((request_invitations::all_columns, suppliers::name, suppliers::email))
+        .load::<(RequestInvitation, String, String)>(conn)?;
+    Ok(rows
+        .into_iter()
+        .map(|(invitation, supplier_name, supplier_email)| InvitationView { invitation, supplier_name, supplier_email })
+        .collect())
+}
+
+fn load_request(conn: &mut PgConnection, req_id: i32) -> Result<Request, HttpResponse> {
+    match requests::table.find(req_id).first::<Request>(conn) {
+        Ok(r) => Ok(r),
+        Err(diesel::result::Error::NotFound) => Err(HttpResponse::NotFound().body("Request not found")),
+        Err(e) => Err(HttpResponse::InternalServerError().body(e.to_string())),
+    }
+}
+
+/// Invites approved suppliers to an invite-only request. Suppliers already invited are left
+/// as they are; the new ones are emailed now, or when the request is published if it's a draft.
+pub async fn invite_suppliers(
+    pool: web::Data<DbPool>,
+    events: web::Data<EventHub>,
+    actor: AuthUser,
+    path: web::Path<i32>,
+    item: web::Json<InviteInput>,
+) -> impl Responder {
+    let req_id = path.into_inner();
+    let mut conn = pool.get().expect("couldn't get db connection from pool");
+
+    let request = match load_request(&mut conn, req_id) {
+        Ok(r) => r,
+        Err(response) => return response,
+    };
+    if !request.invite_only {
+        return HttpResponse::Conflict().body("La solicitud es pública; no necesita invitaciones");
+    }
+    if request.status != RequestStatus::Draft && !request.status.is_bidding() {
+        return HttpResponse::Conflict().body("La solicitud ya no acepta invitaciones");
+    }
+
+    let mut wanted = item.suppliers.clone();
+    wanted.sort_unstable();
+    wanted.dedup();
+
+    // Same criterion as the offer gate: active and approved in compliance
+    let approved: Vec<i32> = match suppliers::table
+        .filter(suppliers::id.eq_any(&wanted))
+        .filter(suppliers::active.eq(true))
+        .filter(suppliers::is_approved.eq(true))
+        .select(suppliers::id)
+        .load(&mut conn)
+    {
+        Ok(ids) => ids,
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    };
+    if wanted.is_empty() {
+        return validation::rejected(vec![Violation::new("suppliers_required", "Indica a qué proveedores invitar")]);
+    }
+    let unknown: Vec<String> = wanted.iter().filter(|id| !approved.contains(id)).map(|id| id.to_string()).collect();
+    if !unknown.is_empty() {
+        return validation::rejected(vec![Violation::new(
+            "suppliers_approved",
+            format!("Solo se puede invitar a proveedores aprobados: {}", unknown.join(", ")),
+        )]);
+    }
+
+    let new_invitations: Vec<NewRequestInvitation> = approved
+        .iter()
+        .map(|supplier_id| NewRequestInvitation { request_id: req_id, supplier_id: *supplier_id, invited_by: Some(actor.id) })
+        .collect();
+
+    let inserted = diesel::insert_into(request_invitations::table)
+        .values(&new_invitations)
+        .on_conflict_do_nothing()
+        .get_results::<RequestInvitation>(&mut conn);
+
+    let inserted = match inserted {
+        Ok(list) => list,
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    };
+
+    for invitation in &inserted {
+        audit::record(&mut conn, &actor, "invite_supplier", "invitation", Some(invitation.id));
+    }
+    notify_invitees(&pool, &events, &mut conn, &request);
+
+    match invitation_views(&mut conn, req_id) {
+        Ok(list) => HttpResponse::Ok().json(list),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+/// Invitations of a request and their answers.
+pub async fn list_invitations(
+    pool: web::Data<DbPool>,
+    path: web::Path<i32>,
+) -> impl Responder {
+    let req_id = path.into_inner();
+    let mut conn = pool.get().expect("couldn't get db connection from pool");
+
+    match invitation_views(&mut conn, req_id) {
+        Ok(list) => HttpResponse::Ok().json(list),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+fn has_live_offer(conn: &mut PgConnection, req_id: i32, supplier_id: i32) -> QueryResult<bool> {
+    let count: i64 = offers::table
+        .filter(offers::request_id.eq(req_id))
+        .filter(offers::supplier_id.eq(supplier_id))
+        .filter(offers::status.ne(OfferStatus::Withdrawn))
+        .count()
+        .get_result(conn)?;
+    Ok(count > 0)
+}
+
+/// Withdraws an invitation. Not possible once the supplier has an offer on the request.
+pub async fn revoke_invitation(
+    pool: web::Data<DbPool>,
+    actor: AuthUser,
+    path: web::Path<(i32, i32)>,
+) -> impl Responder {
+    let (req_id, supplier_id) = path.into_inner();
+    let mut conn = pool.get().expect("couldn't get db connection from pool");
+
+    match has_live_offer(&mut conn, req_id, supplier_id) {
+        Ok(false) => {},
+        Ok(true) => return HttpResponse::Conflict().body("El proveedor ya envió una oferta; debe retirarla antes"),
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    }
+
+    let deleted = diesel::delete(
+        request_invitations::table
+            .filter(request_invitations::request_id.eq(req_id))
+            .filter(request_invitations::supplier_id.eq(supplier_id)),
+    )
+    .get_result::<RequestInvitation>(&mut conn);
+
+    match deleted {
+        Ok(invitation) => {
+            audit::record(&mut conn, &actor, "revoke_invitation", "invitation", Some(invitation.id));
+            HttpResponse::Ok().json("Invitación retirada")
+        },
+        Err(diesel::result::Error::NotFound) => HttpResponse::NotFound().body("Invitation not found"),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+/// Invitations of the logged-in supplier, newest first. Invitations to drafts stay hidden
+/// until the request is published.
+pub async fn my_invitations(
+    pool: web::Data<DbPool>,
+    actor: AuthUser,
+) -> impl Responder {
+    let mut conn = pool.get().expect("couldn't get db connection from pool");
+
+    let rows = request_invitations::table
+        .inner_join(requests::table)
+        .filter(request_invitations::supplier_id.eq(actor.id))
+        .filter(requests::status.ne(RequestStatus::Draft))
+        .order(request_invitations::created_at.desc())
+        .select((request_invitations::all_columns, requests::title, requests::deadline, requests::status))
+        .load::<(RequestInvitation, String, NaiveDateTime, RequestStatus)>(&mut conn);
+
+    match rows {
+        Ok(list) => {
+            let received: Vec<ReceivedInvitation> = list
+                .into_iter()
+                .map(|(invitation, title, deadline, request_status)| {
+                    let link = portal_link(invitation.request_id);
+                    ReceivedInvitation { invitation, title, deadline, request_status, link }
+                })
+                .collect();
+            HttpResponse::Ok().json(received)
+        },
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+fn answer_invitation(pool: &DbPool, events: &EventHub, actor: &AuthUser, req_id: i32, answer: InvitationStatus) -> HttpResponse {
+    let mut conn = pool.get().expect("couldn't get db connection from pool");
+
+    let request = match load_request(&mut conn, req_id) {
+        Ok(r) => r,
+        Err(response) => return response,
+    };
+    let invitation = match invitation_for(&mut conn, req_id, actor.id) {
+        Ok(Some(i)) if request.status != RequestStatus::Draft => i,
+        Ok(_) => return HttpResponse::NotFound().body("Invitation not found"),
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    };
+    if !request.status.is_bidding() {
+        return HttpResponse::Conflict().body("La solicitud ya no acepta ofertas");
+    }
+    if answer == InvitationStatus::Declined {
+        match has_live_offer(&mut conn, req_id, actor.id) {
+            Ok(false) => {},
+            Ok(true) => return HttpResponse::Conflict().body("Ya enviaste una oferta; retírala antes de rechazar la invitación"),
+            Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+        }
+    }
+
+    let updated = diesel::update(request_invitations::table.find(invitation.id))
+        .set((
+            request_invitations::status.eq(answer),
+            request_invitations::responded_at.eq(chrono::Local::now().naive_local()),
+        ))
+        .get_result::<RequestInvitation>(&mut conn);
+
+    match updated {
+        Ok(inv) => {
+            let action = if answer == InvitationStatus::Accepted { "accept_invitation" } else { "decline_invitation" };
+            audit::record(&mut conn, actor, action, "invitation", Some(inv.id));
+            events.publish(Event::invitation(EventKind::InvitationAnswered, &inv));
+            HttpResponse::Ok().json(inv)
+        },
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+pub async fn accept_invitation(
+    pool: web::Data<DbPool>,
+    events: web::Data<EventHub>,
+    actor: AuthUser,
+    path: web::Path<i32>,
+) -> impl Responder {
+    answer_invitation(&pool, &events, &actor, path.into_inner(), InvitationStatus::Accepted)
+}
+
+pub async fn decline_invitation(
+    pool: web::Data<DbPool>,
+    events: web::Data<EventHub>,
+    actor: AuthUser,
+    path: web::Path<i32>,
+) -> impl Responder {
+    answer_invitation(&pool, &events, &actor, path.into_inner(), InvitationStatus::Declined)
+}
@@ -1,4 +1,4 @@
-use actix_web::web;
+use actix_web::{guard, middleware::{from_fn, Logger}, web};
 
 pub mod auth;
 pub mod requests;
@@ -8,40 +8,171 @@ pub mod config;
 pub mod erp;
 pub mod suppliers;
 pub mod files;
+pub mod security;
+pub mod users;
+pub mod audit;
+pub mod validation;
+pub mod currency;
+pub mod evaluation;
+pub mod auction;
+pub mod events;
+pub mod pagination;
+pub mod search;
+pub mod categories;
+pub mod invitations;
+pub mod questions;
+pub mod documents;
+#[cfg(test)]
+mod test_support;
 
-pub fn config(cfg: &mut web::ServiceConfig) {
-    let uploads_path = if std::path::Path::new("uploads").exists() {
-        "uploads"
-    } else if std::path::Path::new("src-tauri/uploads").exists() {
-        "src-tauri/uploads"
-    } else {
-        "uploads"
-    };
-
-    cfg.service(actix_files::Files::new("/api/uploads", uploads_path).show_files_listing());
+/// Access log in the default format, except that the request line leaves out the query
+/// string: the event stream carries the session token there.
+pub fn access_log() -> Logger {
+    Logger::new("%a \"%{r}xi\" %s %b \"%{Referer}i\" \"%{User-Agent}i\" %T")
+        .custom_request_replace("r", |req| format!("{} {} {:?}", req.method(), req.path(), req.version()))
+}
 
+pub fn config(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/api")
             .route("/login", web::post().to(auth::login))
+            // Staff uploads such as the login image; supplier files go through /archivos
+            .route("/uploads/{file}", web::get().to(files::public_file))
             .route("/register", web::post().to(auth::register))
-            .route("/solicitudes", web::post().to(requests::create_request))
-            .route("/solicitudes", web::get().to(requests::list_requests))
-            .route("/ofertas", web::post().to(offers::create_offer))
-            .route("/ofertas/{id}", web::get().to(offers::list_offers))
-            .route("/ganadora/{id}", web::put().to(offers::mark_winner))
-            .route("/admin/suppliers", web::get().to(admin::list_pending_suppliers))
-            .route("/admin/suppliers/approved", web::get().to(admin::list_approved_suppliers))
-            .route("/admin/approve/{id}", web::put().to(admin::approve_supplier))
-            .route("/admin/reject/{id}", web::delete().to(admin::reject_supplier))
-            .route("/admin/compliance/{id}", web::put().to(admin::update_compliance))
-            .route("/admin/ofertas", web::get().to(offers::list_all_offers))
-            .route("/admin/config/email", web::get().to(config::get_email_config))
-            .route("/admin/config/email", web::post().to(config::save_email_config))
-            .route("/admin/config/test", web::post().to(config::test_email_config))
-            .route("/admin/reset", web::delete().to(admin::reset_database))
-            .route("/suppliers/{id}", web::get().to(suppliers::get_supplier))
-            .route("/suppliers/{id}/docs", web::put().to(suppliers::update_docs))
-            .route("/upload", web::post().to(files::upload_file))
+            .route("/config/public", web::get().to(config::get_public_config))
+            // ERP integration authenticates with its own X-API-KEY header
             .route("/erp/import", web::post().to(erp::import_requests))
+            .service(
+                web::scope("/admin")
+                    .wrap(from_fn(security::staff_only))
+                    .route("/suppliers", web::get().to(admin::list_pending_suppliers))
+                    .route("/suppliers/approved", web::get().to(admin::list_approved_suppliers))
+                    .route("/ofertas", web::get().to(offers::list_all_offers))
+                    .route("/buscar/proveedores", web::get().to(search::search_suppliers))
+                    .route("/documentos", web::get().to(documents::list_documents))
+                    .service(
+                        web::scope("")
+                            .wrap(from_fn(security::admin_only))
+                            .route("/approve/{id}", web::put().to(admin::approve_supplier))
+                            .route("/reject/{id}", web::delete().to(admin::reject_supplier))
+                            .route("/compliance/{id}", web::put().to(admin::update_compliance))
+                            .route("/config/email", web::get().to(config::get_email_config))
+                            .route("/config/email", web::post().to(config::save_email_config))
+                            .route("/config/test", web::post().to(config::test_email_config))
+                            .route("/config/impuestos", web::get().to(config::get_tax_config))
+                            .route("/config/impuestos", web::put().to(config::save_tax_config))
+                            .route("/config/evaluacion", web::get().to(evaluation::get_scoring_weights))
+                            .route("/config/evaluacion", web::put().to(evaluation::save_scoring_weights))
+                            .route("/reset", web::delete().to(admin::reset_database))
+                            .route("/users", web::get().to(users::list_users))
+                            .route("/users", web::post().to(users::create_user))
+                            .route("/users/{id}/active", web::put().to(users::set_user_active))
+                            .route("/audit", web::get().to(audit::list_audit_log))
+                            .route("/tipos-cambio", web::post().to(currency::save_rate))
+                            .route("/categorias", web::post().to(categories::create_category))
+                            .route("/categorias/{id}", web::put().to(categories::update_category))
+                            .route("/documentos/{id}/revision", web::put().to(documents::review_document))
+                    )
+            )
+            .service(
+                web::resource("/solicitudes")
+                    .guard(guard::Post())
+                    .wrap(from_fn(security::staff_only))
+                    .to(requests::create_request)
+            )
+            .service(
+                web::resource("/ofertas")
+                    .wrap(from_fn(security::supplier_only))
+                    .route(web::post().to(offers::create_offer))
+            )
+            .service(
+                web::resource("/ofertas/{id}/confirmar")
+                    .wrap(from_fn(security::supplier_only))
+                    .route(web::put().to(offers::reconfirm_offer))
+            )
+            .service(
+                web::resource("/ofertas/{id}/retirar")
+                    .wrap(from_fn(security::supplier_only))
+                    .route(web::put().to(offers::withdraw_offer))
+            )
+            .service(
+                web::resource("/ofertas/{id}")
+                    .guard(guard::Put())
+                    .wrap(from_fn(security::supplier_only))
+                    .to(offers::revise_offer)
+            )
+            .service(
+                web::scope("/solicitudes/{id}")
+                    .wrap(from_fn(security::authenticated))
+                    .service(web::resource("").guard(guard::Get()).to(requests::get_request))
+                    .route("/versiones", web::get().to(requests::list_versions))
+                    .route("/subasta", web::get().to(auction::auction_status))
+                    .route("/preguntas", web::get().to(questions::list_questions))
+                    .service(
+                        web::resource("/preguntas")
+                            .guard(guard::Post())
+                            .wrap(from_fn(security::supplier_only))
+                            .to(questions::ask_question)
+                    )
+                    .service(
+                        web::scope("")
+                            .wrap(from_fn(security::staff_only))
+                            .route("", web::put().to(requests::update_request))
+                            .route("", web::delete().to(requests::cancel_request))
+                            .route("/publicar", web::put().to(requests::publish_request))
+                            .route("/cerrar", web::put().to(requests::close_request))
+                            .route("/cancelar", web::put().to(requests::cancel_request))
+                            .route("/enmiendas", web::post().to(requests::amend_request))
+                            .route("/comparativo", web::get().to(offers::compare_offers))
+                            .route("/evaluaciones", web::get().to(evaluation::list_evaluations))
+                            .route("/evaluaciones", web::post().to(evaluation::evaluate_offers))
+                            .route("/subasta/adjudicar", web::put().to(auction::award_auction))
+                            .route("/categorias", web::put().to(categories::set_request_categories))
+                            .route("/invitaciones", web::get().to(invitations::list_invitations))
+                            .route("/invitaciones", web::post().to(invitations::invite_suppliers))
+                            .route("/invitaciones/{supplier_id}", web::delete().to(invitations::revoke_invitation))
+                            .route("/preguntas/{question_id}/respuesta", web::put().to(questions::answer_question))
+                    )
+            )
+            .service(
+                web::resource("/ganadora/partida/{id}")
+                    .wrap(from_fn(security::staff_only))
+                    .route(web::put().to(offers::mark_line_winner))
+            )
+            .service(
+                web::resource("/ganadora/{id}")
+                    .wrap(from_fn(security::staff_only))
+                    .route(web::put().to(offers::mark_winner))
+            )
+            .service(
+                web::scope("/me")
+                    .wrap(from_fn(security::supplier_only))
+                    .route("", web::get().to(suppliers::my_profile))
+                    .route("/docs", web::put().to(suppliers::update_my_docs))
+                    .route("/ofertas", web::get().to(offers::my_offers))
+                    .route("/categorias", web::get().to(categories::my_categories))
+                    .route("/categorias", web::put().to(categories::set_my_categories))
+                    .route("/invitaciones", web::get().to(invitations::my_invitations))
+                    .route("/documentos", web::get().to(documents::my_documents))
+                    .route("/documentos", web::post().to(documents::add_my_document))
+                    .route("/invitaciones/{id}/aceptar", web::put().to(invitations::accept_invitation))
+                    .route("/invitaciones/{id}/rechazar", web::put().to(invitations::decline_invitation))
+            )
+            .service(
+                web::scope("")
+                    .wrap(from_fn(security::authenticated))
+                    .route("/solicitudes", web::get().to(requests::list_requests))
+                    .route("/buscar/solicitudes", web::get().to(search::search_requests))
+                    .route("/tipos-cambio", web::get().to(currency::list_rates))
+                    .route("/categorias", web::get().to(categories::list_categories))
+                    .route("/eventos", web::get().to(events::stream_events))
+                    .route("/ofertas/{id}", web::get().to(offers::list_offers))
+                    .route("/ofertas/{id}/revisiones", web::get().to(offers::list_revisions))
+                    .route("/suppliers/{id}", web::get().to(suppliers::get_supplier))
+                    .route("/suppliers/{id}/docs", web::put().to(suppliers::update_docs))
+                    .route("/suppliers/{id}/documentos", web::get().to(documents::supplier_documents))
+                    .route("/upload", web::post().to(files::upload_file))
+                    .route("/archivos/{file}", web::get().to(files::supplier_file))
+            )
     );
 }
@@ -1,36 +1,786 @@
 use actix_web::{web, HttpResponse, Responder};
-use crate::db::{DbPool, models::{NewOffer, Offer}, schema::offers};
+use chrono::NaiveDate;
+use crate::db::{DbPool, models::{NewOffer, NewOfferLine, NewOfferRevision, Offer, OfferLine, OfferRevision, Request, RequestLine}, schema::{offer_lines, offer_revisions, offers, request_lines, requests}};
+use diesel::pg::{Pg, PgConnection};
 use diesel::prelude::*;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
 use crate::email_service;
 use crate::db::schema::suppliers;
+use crate::db::status::{BiddingMode, OfferStatus, RequestStatus};
+use crate::api::{audit, auction, config, currency, documents, evaluation, events::{Event, EventHub, EventKind}, invitations, pagination::{self, ListQuery, Page}, security::AuthUser, validation::{self, Violation}};
+
+const MAX_OFFER_PRICE: i64 = 1_000_000_000;
+const MAX_DELIVERY_DAYS: i32 = 3650;
+
+/// Sealed requests keep bids hidden from buyers until the deadline passes or the request is closed.
+pub fn bids_sealed(req: &Request) -> bool {
+    req.bidding_mode == BiddingMode::Sealed
+        && req.status.is_bidding()
+        && req.deadline > chrono::Local::now().naive_local()
+}
+
+pub(crate) fn sealed_response(req: &Request) -> HttpResponse {
+    HttpResponse::Forbidden().json(serde_json::json!({
+        "error": "sealed_bids",
+        "message": "Las ofertas están selladas hasta el cierre de la solicitud",
+        "deadline": req.deadline
+    }))
+}
+
+/// Price and delivery quoted for one line of a multi-line request. Either the unit price
+/// or the line total (`price`) may be sent; the other one is derived from the quantity.
+#[derive(Deserialize)]
+pub struct OfferLineInput {
+    pub request_line_id: i32,
+    #[serde(default)]
+    pub price: Decimal,
+    pub unit_price: Option<Decimal>,
+    pub delivery_days: Option<i32>,
+    pub promised_date: Option<NaiveDate>,
+    /// Optional free-text note on the delivery
+    #[serde(default)]
+    pub delivery_time: String,
+}
+
+/// Offer payload sent by suppliers. The bidding supplier is always taken from the session.
+///
+/// Single-line requests may be quoted with `price` and a delivery promise alone; multi-line
+/// requests are quoted per line and the offer price is the sum of the lines. Prices are
+/// in `currency`, which defaults to the currency of the request, and exclude taxes.
+/// Delivery is promised either as `delivery_days` from today or as a `promised_date`.
+#[derive(Deserialize)]
+pub struct OfferInput {
+    pub request_id: i32,
+    #[serde(default)]
+    pub price: Decimal,
+    pub unit_price: Option<Decimal>,
+    pub currency: Option<String>,
+    pub delivery_days: Option<i32>,
+    pub promised_date: Option<NaiveDate>,
+    #[serde(default)]
+    pub delivery_time: String,
+    pub conditions: String,
+    pub attachments: String,
+    pub photo: Option<String>,
+    #[serde(default)]
+    pub lines: Vec<OfferLineInput>,
+}
+
+/// An offer with the lines it quotes.
+#[derive(Serialize)]
+pub struct OfferDetail {
+    #[serde(flatten)]
+    pub offer: Offer,
+    pub lines: Vec<OfferLine>,
+    /// Promised delivery falls after the date the request needs the goods
+    pub late: bool,
+}
+
+/// Date the goods are needed by: the request's required date, or its deadline when not set.
+pub fn required_by(req: &Request) -> NaiveDate {
+    req.required_date.unwrap_or(req.deadline.date())
+}
+
+pub fn is_late(offer: &Offer, req: &Request) -> bool {
+    offer.promised_date.is_some_and(|d| d > required_by(req))
+}
+
+/// A delivery promise with both representations resolved.
+#[derive(Clone, Copy)]
+struct Delivery {
+    days: i32,
+    date: NaiveDate,
+}
+
+/// Turns the days or date sent by the supplier into a `Delivery`, recording a violation
+/// when neither or both are present or the value is out of range.
+fn resolve_delivery(
+    violations: &mut Vec<Violation>,
+    days: Option<i32>,
+    date: Option<NaiveDate>,
+    label: &str,
+) -> Option<Delivery> {
+    let today = chrono::Local::now().date_naive();
+
+    match (days, date) {
+        (Some(_), Some(_)) => {
+            violations.push(Violation::new("delivery_single", format!("Indica los días de entrega o la fecha prometida{}, no ambos", label)));
+            None
+        },
+        (Some(d), None) if (0..=MAX_DELIVERY_DAYS).contains(&d) => {
+            Some(Delivery { days: d, date: today + chrono::Duration::days(d as i64) })
+        },
+        (Some(_), None) => {
+            violations.push(Violation::new("delivery_days_range", format!("Los días de entrega{} deben estar entre 0 y {}", label, MAX_DELIVERY_DAYS)));
+            None
+        },
+        (None, Some(dt)) if dt >= today && (dt - today).num_days() <= MAX_DELIVERY_DAYS as i64 => {
+            Some(Delivery { days: (dt - today).num_days() as i32, date: dt })
+        },
+        (None, Some(_)) => {
+            violations.push(Violation::new("promised_date_range", format!("La fecha prometida{} debe ser de hoy en adelante", label)));
+            None
+        },
+        (None, None) => {
+            violations.push(Violation::new("delivery_required", format!("Indica los días de entrega o la fecha prometida{}", label)));
+            None
+        },
+    }
+}
+
+fn describe_delivery(note: &str, delivery: Delivery) -> String {
+    if note.trim().is_empty() {
+        format!("{} días", delivery.days)
+    } else {
+        note.to_string()
+    }
+}
+
+/// A quoted line with both its unit price and its total resolved.
+struct PricedLine {
+    request_line_id: i32,
+    unit_price: Decimal,
+    price: Decimal,
+    delivery: Delivery,
+    delivery_time: String,
+}
+
+/// Derives (unit price, line total) from whichever of the two the supplier sent.
+fn price_line(quantity: i32, unit_price: Option<Decimal>, total: Decimal) -> (Decimal, Decimal) {
+    let quantity = Decimal::from(quantity.max(1));
+    match unit_price {
+        Some(unit) => (unit, (unit * quantity).round_dp(2)),
+        None => ((total / quantity).round_dp(4), total.round_dp(2)),
+    }
+}
+
+fn with_lines(conn: &mut PgConnection, list: Vec<Offer>) -> QueryResult<Vec<OfferDetail>> {
+    let ids: Vec<i32> = list.iter().map(|o| o.id).collect();
+    let all_lines = offer_lines::table
+        .filter(offer_lines::offer_id.eq_any(&ids))
+        .order(offer_lines::id.asc())
+        .load::<OfferLine>(conn)?;
+
+    let request_ids: Vec<i32> = list.iter().map(|o| o.request_id).collect();
+    let quoted_requests = requests::table
+        .filter(requests::id.eq_any(&request_ids))
+        .load::<Request>(conn)?;
+
+    Ok(list
+        .into_iter()
+        .map(|offer| {
+            let lines = all_lines.iter().filter(|l| l.offer_id == offer.id).cloned().collect();
+            let late = quoted_requests
+                .iter()
+                .find(|r| r.id == offer.request_id)
+                .is_some_and(|r| is_late(&offer, r));
+            OfferDetail { offer, lines, late }
+        })
+        .collect())
+}
+
+fn check_price(violations: &mut Vec<Violation>, price: Decimal, label: &str) {
+    if price <= Decimal::ZERO {
+        violations.push(Violation::new("price_positive", format!("El precio{} debe ser mayor a cero", label)));
+    } else if price > Decimal::from(MAX_OFFER_PRICE) {
+        violations.push(Violation::new("price_range", format!("El precio{} excede el máximo permitido", label)));
+    }
+}
+
+/// Checks the lines an offer quotes against the lines of the request. Leaving `quoted` empty
+/// quotes the only line of a single-line request.
+fn check_quoted_lines(violations: &mut Vec<Violation>, lines: &[RequestLine], quoted: &[OfferLineInput]) {
+    if lines.is_empty() {
+        violations.push(Violation::new("request_lines", "La solicitud no tiene partidas que cotizar"));
+        return;
+    }
+
+    if quoted.is_empty() {
+        if lines.len() > 1 {
+            violations.push(Violation::new("lines_required", "La solicitud tiene varias partidas; cotiza cada una por separado"));
+        }
+        return;
+    }
+
+    let mut seen = Vec::new();
+    for line in quoted {
+        if !lines.iter().any(|l| l.id == line.request_line_id) {
+            violations.push(Violation::new("line_exists", format!("La partida {} no pertenece a la solicitud", line.request_line_id)));
+        } else if seen.contains(&line.request_line_id) {
+            violations.push(Violation::new("line_unique", format!("La partida {} se cotizó más de una vez", line.request_line_id)));
+        }
+        seen.push(line.request_line_id);
+    }
+}
+
+/// Checks every business rule for a new bid, or for a new revision of offer `revising`;
+/// on success returns the request being bid on and the priced lines of the offer.
+fn validate_offer(
+    conn: &mut PgConnection,
+    supplier_id: i32,
+    input: &OfferInput,
+    revising: Option<i32>,
+) -> Result<(Request, Vec<PricedLine>), Vec<Violation>> {
+    let mut violations = Vec::new();
+
+    let mut offer_delivery = None;
+    let mut line_deliveries = Vec::with_capacity(input.lines.len());
+    if input.lines.is_empty() {
+        check_price(&mut violations, input.unit_price.unwrap_or(input.price), "");
+        offer_delivery = resolve_delivery(&mut violations, input.delivery_days, input.promised_date, "");
+    } else {
+        for line in &input.lines {
+            let label = format!(" de la partida {}", line.request_line_id);
+            check_price(&mut violations, line.unit_price.unwrap_or(line.price), &label);
+            line_deliveries.push(resolve_delivery(&mut violations, line.delivery_days, line.promised_date, &label));
+        }
+    }
+
+    if let Some(code) = &input.currency {
+        let known = currency::is_currency_code(code) && currency::is_known(conn, code).unwrap_or(false);
+        if !known {
+            violations.push(Violation::new("currency_known", format!("La moneda {} no tiene tipo de cambio registrado", code)));
+        }
+    }
+
+    let supplier_state = suppliers::table
+        .find(supplier_id)
+        .select((suppliers::active, suppliers::is_approved))
+        .first::<(bool, bool)>(conn);
+
+    // Approval alone isn't enough: the required documents must be on file and in good standing
+    match supplier_state {
+        Ok((false, _)) => violations.push(Violation::new("supplier_active", "Tu cuenta no está activa")),
+        Ok((true, approved)) => match documents::required_shortfalls(conn, supplier_id) {
+            Ok(shortfalls) => match shortfalls.first() {
+                Some((doc_type, shortfall)) => violations.push(Violation::new("documents_current", shortfall.message(*doc_type))),
+                None if !approved => violations.push(Violation::new("supplier_approved", "Tu cuenta aún no ha sido aprobada en cumplimiento")),
+                None => {},
+            },
+            Err(e) => violations.push(Violation::new("documents_current", format!("No se pudieron revisar tus documentos: {}", e))),
+        },
+        Err(_) => violations.push(Violation::new("supplier_exists", "Proveedor no encontrado")),
+    }
+
+    let request = match requests::table.find(input.request_id).first::<Request>(conn) {
+        Ok(req) => {
+            if !req.status.is_bidding() {
+                violations.push(Violation::new("request_open", format!("La solicitud no está abierta (estado: {})", req.status.as_str())));
+            }
+            if req.deadline < chrono::Local::now().naive_local() {
+                violations.push(Violation::new("deadline_open", "La fecha límite de la solicitud ya pasó"));
+            }
+            invitations::check_invited(conn, &mut violations, &req, supplier_id);
+            Some(req)
+        },
+        Err(_) => {
+            violations.push(Violation::new("request_exists", "La solicitud no existe"));
+            None
+        },
+    };
+
+    // Withdrawn offers don't count, so a supplier may bid again after withdrawing
+    let mut other_bids = offers::table
+        .filter(offers::request_id.eq(input.request_id))
+        .filter(offers::supplier_id.eq(supplier_id))
+        .filter(offers::status.ne(OfferStatus::Withdrawn))
+        .into_boxed();
+    if let Some(own) = revising {
+        other_bids = other_bids.filter(offers::id.ne(own));
+    }
+    let previous_bids: i64 = other_bids.count().get_result(conn).unwrap_or(0);
+
+    if previous_bids > 0 {
+        violations.push(Violation::new("single_bid", "Ya enviaste una oferta para esta solicitud"));
+    }
+
+    let lines = match &request {
+        Some(req) => request_lines::table
+            .filter(request_lines::request_id.eq(req.id))
+            .order(request_lines::line_no.asc())
+            .load::<RequestLine>(conn)
+            .unwrap_or_default(),
+        None => Vec::new(),
+    };
+
+    if let Some(req) = &request {
+        check_quoted_lines(&mut violations, &lines, &input.lines);
+        // Checked again under lock when the offer is stored
+        if won_lines(conn, req.id).unwrap_or(0) > 0 {
+            violations.push(Violation::new("lines_awarded", "Ya se adjudicaron partidas de esta solicitud; no se aceptan ofertas nuevas ni cambios"));
+        }
+    }
+
+    let priced: Vec<PricedLine> = if input.lines.is_empty() {
+        lines
+            .first()
+            .zip(offer_delivery)
+            .map(|(l, delivery)| {
+                let (unit_price, price) = price_line(l.quantity, input.unit_price, input.price);
+                let delivery_time = describe_delivery(&input.delivery_time, delivery);
+                PricedLine { request_line_id: l.id, unit_price, price, delivery, delivery_time }
+            })
+            .into_iter()
+            .collect()
+    } else {
+        input
+            .lines
+            .iter()
+            .zip(line_deliveries)
+            .filter_map(|(quoted, delivery)| {
+                let line = lines.iter().find(|l| l.id == quoted.request_line_id)?;
+                let delivery = delivery?;
+                let (unit_price, price) = price_line(line.quantity, quoted.unit_price, quoted.price);
+                let delivery_time = describe_delivery(&quoted.delivery_time, delivery);
+                Some(PricedLine { request_line_id: line.id, unit_price, price, delivery, delivery_time })
+            })
+            .collect()
+    };
+
+    let subtotal: Decimal = priced.iter().map(|p| p.price).sum();
+    if subtotal > Decimal::from(MAX_OFFER_PRICE) {
+        violations.push(Violation::new("price_range", "El precio total excede el máximo permitido"));
+    }
+
+    if let Some(req) = request.as_ref().filter(|r| auction::is_auction(r)) {
+        let code = input.currency.as_deref().unwrap_or(&req.currency);
+        auction::check_bid(conn, &mut violations, req, supplier_id, code, subtotal);
+    }
+
+    match request {
+        Some(req) if violations.is_empty() && !priced.is_empty() => Ok((req, priced)),
+        _ => Err(violations),
+    }
+}
+
+/// Builds the stored values of an offer from a validated payload, applying current taxes.
+fn build_offer(
+    conn: &mut PgConnection,
+    supplier_id: i32,
+    input: OfferInput,
+    request: &Request,
+    priced: &[PricedLine],
+) -> QueryResult<NewOffer> {
+    let taxes = config::tax_settings(conn)?;
+    let subtotal: Decimal = priced.iter().map(|p| p.price).sum();
+    let breakdown = config::apply_taxes(&taxes, subtotal);
+
+    // The offer as a whole is delivered when its slowest line is; validation guarantees a line
+    let delivery = priced
+        .iter()
+        .map(|p| p.delivery)
+        .max_by_key(|d| d.date)
+        .ok_or(diesel::result::Error::NotFound)?;
+
+    Ok(NewOffer {
+        supplier_id,
+        request_id: request.id,
+        price: subtotal,
+        delivery_time: describe_delivery(&input.delivery_time, delivery),
+        conditions: input.conditions,
+        attachments: input.attachments,
+        photo: input.photo,
+        status: OfferStatus::Sent,
+        request_version: request.version,
+        currency: input.currency.unwrap_or_else(|| request.currency.clone()),
+        tax_rate: taxes.iva_rate,
+        tax_amount: breakdown.tax_amount,
+        withholding_rate: taxes.withholding_rate,
+        withholding_amount: breakdown.withholding_amount,
+        total: breakdown.total,
+        delivery_days: Some(delivery.days),
+        promised_date: Some(delivery.date),
+    })
+}
+
+fn insert_lines(conn: &mut PgConnection, offer_id: i32, priced: Vec<PricedLine>) -> QueryResult<Vec<OfferLine>> {
+    let new_lines: Vec<NewOfferLine> = priced
+        .into_iter()
+        .map(|line| NewOfferLine {
+            offer_id,
+            request_line_id: line.request_line_id,
+            price: line.price,
+            delivery_time: line.delivery_time,
+            status: OfferStatus::Sent,
+            unit_price: line.unit_price,
+            delivery_days: Some(line.delivery.days),
+            promised_date: Some(line.delivery.date),
+        })
+        .collect();
+
+    diesel::insert_into(offer_lines::table)
+        .values(&new_lines)
+        .get_results::<OfferLine>(conn)
+}
+
+/// Appends the current state of an offer to its immutable history.
+fn record_revision(conn: &mut PgConnection, offer: &Offer, lines: &[OfferLine]) -> QueryResult<()> {
+    let snapshot = NewOfferRevision {
+        offer_id: offer.id,
+        revision: offer.revision,
+        status: offer.status,
+        price: offer.price,
+        currency: offer.currency.clone(),
+        tax_amount: offer.tax_amount,
+        withholding_amount: offer.withholding_amount,
+        total: offer.total,
+        delivery_days: offer.delivery_days,
+        promised_date: offer.promised_date,
+        delivery_time: offer.delivery_time.clone(),
+        conditions: offer.conditions.clone(),
+        attachments: offer.attachments.clone(),
+        photo: offer.photo.clone(),
+        lines: serde_json::to_string(lines).unwrap_or_else(|_| "[]".to_string()),
+    };
+
+    diesel::insert_into(offer_revisions::table)
+        .values(&snapshot)
+        .execute(conn)
+        .map(|_| ())
+}
 
 pub async fn create_offer(
     pool: web::Data<DbPool>,
-    item: web::Json<NewOffer>,
+    events: web::Data<EventHub>,
+    actor: AuthUser,
+    item: web::Json<OfferInput>,
 ) -> impl Responder {
     let mut conn = pool.get().expect("couldn't get db connection from pool");
+    let input = item.into_inner();
+
+    let (request, priced) = match validate_offer(&mut conn, actor.id, &input, None) {
+        Ok(validated) => validated,
+        Err(violations) => return validation::rejected(violations),
+    };
+
+    let new_offer = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+        // Validation ran unlocked; the request may have closed or had lines awarded since
+        let locked = match lock_open_request(conn, request.id)? {
+            Ok(locked) => locked,
+            Err(response) => return Ok(Err(response)),
+        };
+        if let Err(response) = check_same_version(&request, &locked) {
+            return Ok(Err(response));
+        }
+        let extended = match place_bid(conn, &locked, actor.id, &priced)? {
+            Ok(extended) => extended,
+            Err(response) => return Ok(Err(response)),
+        };
+
+        let values = build_offer(conn, actor.id, input, &locked, &priced)?;
+        let offer = diesel::insert_into(offers::table)
+            .values(&values)
+            .get_result::<Offer>(conn)?;
+
+        let lines = insert_lines(conn, offer.id, priced)?;
+        record_revision(conn, &offer, &lines)?;
+        invitations::accept_on_bid(conn, locked.id, actor.id)?;
 
-    let new_offer = diesel::insert_into(offers::table)
-        .values(&item.into_inner())
-        .get_result::<Offer>(&mut conn);
+        let late = is_late(&offer, &locked);
+        Ok(Ok((OfferDetail { offer, lines, late }, extended)))
+    });
 
     match new_offer {
-        Ok(offer) => HttpResponse::Ok().json(offer),
+        Ok(Ok((detail, extended))) => {
+            announce_bid(&mut conn, &events, &actor, &detail.offer, EventKind::OfferCreated, extended);
+            HttpResponse::Ok().json(detail)
+        },
+        Ok(Err(response)) => response,
+        // Another submission of the same supplier got in between validation and insert
+        Err(diesel::result::Error::DatabaseError(diesel::result::DatabaseErrorKind::UniqueViolation, info))
+            if info.constraint_name() == Some("offers_single_bid_idx") =>
+        {
+            validation::rejected(vec![Violation::new("single_bid", "Ya enviaste una oferta para esta solicitud")])
+        },
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+/// In an auction, locks the request for an incoming bid. Fails when the auction closed in
+/// the meantime or another bid of the same supplier got in first that this one no longer
+/// improves on, and returns the
+/// request when the bid extended its deadline.
+fn place_bid(conn: &mut PgConnection, request: &Request, supplier_id: i32, priced: &[PricedLine]) -> QueryResult<Result<Option<Request>, HttpResponse>> {
+    if !auction::is_auction(request) {
+        return Ok(Ok(None));
+    }
+
+    let Some(window) = auction::lock_for_bid(conn, request.id)? else {
+        return Ok(Err(validation::rejected(vec![Violation::new("deadline_open", "La subasta ya cerró")])));
+    };
+    let subtotal: Decimal = priced.iter().map(|p| p.price).sum();
+    if let Some(violation) = auction::check_own_bid(conn, &window.request, supplier_id, subtotal)? {
+        return Ok(Err(validation::rejected(vec![violation])));
+    }
+
+    Ok(Ok(window.extended.then_some(window.request)))
+}
+
+fn announce_bid(
+    conn: &mut PgConnection,
+    events: &EventHub,
+    actor: &AuthUser,
+    offer: &Offer,
+    kind: EventKind,
+    extended: Option<Request>,
+) {
+    events.publish(Event::offer(kind, offer));
+    if let Some(req) = extended {
+        audit::record(conn, actor, "extend_auction", "request", Some(req.id));
+        events.publish(Event::request(EventKind::RequestUpdated, &req));
+    }
+}
+
+/// An offer is priced against the request as validation read it, unlocked. When an amendment
+/// got in before the lock, its lines may no longer match the request; the offer is stored
+/// against the version of the locked row, so it has to be sent again.
+fn check_same_version(validated: &Request, locked: &Request) -> Result<(), HttpResponse> {
+    if validated.version == locked.version {
+        return Ok(());
+    }
+    Err(validation::rejected(vec![Violation::new(
+        "request_version",
+        format!("La solicitud cambió a la versión {} mientras se enviaba la oferta; revísala y vuelve a enviarla", locked.version),
+    )]))
+}
+
+/// Locks a request that takes new bids: open, before its deadline and without lines awarded
+/// yet. A new bid on a request with a winner would never be considered.
+fn lock_open_request(conn: &mut PgConnection, req_id: i32) -> QueryResult<Result<Request, HttpResponse>> {
+    let req = requests::table.find(req_id).for_update().first::<Request>(conn)?;
+    if !req.status.is_bidding() || req.deadline < chrono::Local::now().naive_local() {
+        return Ok(Err(validation::rejected(vec![
+            Violation::new("request_open", "La solicitud ya no acepta ofertas"),
+        ])));
+    }
+    if won_lines(conn, req.id)? > 0 {
+        return Ok(Err(validation::rejected(vec![
+            Violation::new("lines_awarded", "Ya se adjudicaron partidas de esta solicitud; no se aceptan más ofertas"),
+        ])));
+    }
+
+    Ok(Ok(req))
+}
+
+/// Locks an offer of the caller that can still change: sent, on a request still taking bids
+/// and without lines awarded yet. Once a line has a winner, revising or withdrawing would undo
+/// the award.
+fn lock_open_offer(conn: &mut PgConnection, actor: &AuthUser, off_id: i32) -> QueryResult<Result<(Offer, Request), HttpResponse>> {
+    let req_id = offers::table.find(off_id).select(offers::request_id).first::<i32>(conn)?;
+    // Same order as the awards: request first, then the offer
+    let req = requests::table.find(req_id).for_update().first::<Request>(conn)?;
+    let offer = offers::table.find(off_id).for_update().first::<Offer>(conn)?;
+    if offer.supplier_id != actor.id {
+        return Ok(Err(HttpResponse::Forbidden().body("No tienes acceso a esta oferta")));
+    }
+
+    if !req.status.is_bidding() || req.deadline < chrono::Local::now().naive_local() || offer.status != OfferStatus::Sent {
+        return Ok(Err(validation::rejected(vec![
+            Violation::new("request_open", "La solicitud ya no acepta cambios en las ofertas"),
+        ])));
+    }
+    if won_lines(conn, req.id)? > 0 {
+        return Ok(Err(validation::rejected(vec![
+            Violation::new("lines_awarded", "Ya se adjudicaron partidas de esta solicitud; las ofertas no pueden cambiar"),
+        ])));
+    }
+
+    Ok(Ok((offer, req)))
+}
+
+/// Replaces the terms of an offer with a new revision; every previous revision is kept.
+/// Revising also accepts the latest version of the request terms.
+pub async fn revise_offer(
+    pool: web::Data<DbPool>,
+    events: web::Data<EventHub>,
+    actor: AuthUser,
+    path: web::Path<i32>,
+    item: web::Json<OfferInput>,
+) -> impl Responder {
+    let off_id = path.into_inner();
+    let input = item.into_inner();
+    let mut conn = pool.get().expect("couldn't get db connection from pool");
+
+    let (validated, priced) = match validate_offer(&mut conn, actor.id, &input, Some(off_id)) {
+        Ok(validated) => validated,
+        Err(violations) => return validation::rejected(violations),
+    };
+
+    let result = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+        let (current, request) = match lock_open_offer(conn, &actor, off_id)? {
+            Ok(locked) => locked,
+            Err(response) => return Ok(Err(response)),
+        };
+        if current.request_id != input.request_id {
+            return Ok(Err(validation::rejected(vec![
+                Violation::new("request_mismatch", "La oferta pertenece a otra solicitud"),
+            ])));
+        }
+        if let Err(response) = check_same_version(&validated, &request) {
+            return Ok(Err(response));
+        }
+
+        let extended = match place_bid(conn, &request, actor.id, &priced)? {
+            Ok(extended) => extended,
+            Err(response) => return Ok(Err(response)),
+        };
+
+        let values = build_offer(conn, actor.id, input, &request, &priced)?;
+        let offer = diesel::update(offers::table.find(off_id))
+            .set((
+                &values,
+                offers::revision.eq(current.revision + 1),
+                offers::updated_at.eq(chrono::Local::now().naive_local()),
+                offers::needs_reconfirmation.eq(false),
+            ))
+            .get_result::<Offer>(conn)?;
+
+        diesel::delete(offer_lines::table.filter(offer_lines::offer_id.eq(off_id))).execute(conn)?;
+        let lines = insert_lines(conn, off_id, priced)?;
+        record_revision(conn, &offer, &lines)?;
+
+        let late = is_late(&offer, &request);
+        Ok(Ok((OfferDetail { offer, lines, late }, extended)))
+    });
+
+    match result {
+        Ok(Ok((detail, extended))) => {
+            announce_bid(&mut conn, &events, &actor, &detail.offer, EventKind::OfferUpdated, extended);
+            HttpResponse::Ok().json(detail)
+        },
+        Ok(Err(response)) => response,
+        Err(diesel::result::Error::NotFound) => HttpResponse::NotFound().body("Offer not found"),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+/// Withdraws an offer while the request is still open. The withdrawal is kept as the last revision.
+pub async fn withdraw_offer(
+    pool: web::Data<DbPool>,
+    events: web::Data<EventHub>,
+    actor: AuthUser,
+    path: web::Path<i32>,
+) -> impl Responder {
+    let off_id = path.into_inner();
+    let mut conn = pool.get().expect("couldn't get db connection from pool");
+
+    let result = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+        let (current, _) = match lock_open_offer(conn, &actor, off_id)? {
+            Ok(locked) => locked,
+            Err(response) => return Ok(Err(response)),
+        };
+
+        let offer = diesel::update(offers::table.find(off_id))
+            .set((
+                offers::status.eq(OfferStatus::Withdrawn),
+                offers::revision.eq(current.revision + 1),
+                offers::updated_at.eq(chrono::Local::now().naive_local()),
+            ))
+            .get_result::<Offer>(conn)?;
+
+        let lines = diesel::update(offer_lines::table.filter(offer_lines::offer_id.eq(off_id)))
+            .set(offer_lines::status.eq(OfferStatus::Withdrawn))
+            .get_results::<OfferLine>(conn)?;
+        record_revision(conn, &offer, &lines)?;
+
+        Ok(Ok(offer))
+    });
+
+    match result {
+        Ok(Ok(offer)) => {
+            events.publish(Event::offer(EventKind::OfferWithdrawn, &offer));
+            HttpResponse::Ok().json(offer)
+        },
+        Ok(Err(response)) => response,
+        Err(diesel::result::Error::NotFound) => HttpResponse::NotFound().body("Offer not found"),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+/// A stored revision with its lines expanded.
+#[derive(Serialize)]
+pub struct RevisionView {
+    #[serde(flatten)]
+    pub revision: OfferRevision,
+    pub lines: Vec<OfferLine>,
+}
+
+/// Full revision history of an offer, oldest first.
+pub async fn list_revisions(
+    pool: web::Data<DbPool>,
+    actor: AuthUser,
+    path: web::Path<i32>,
+) -> impl Responder {
+    let off_id = path.into_inner();
+    let mut conn = pool.get().expect("couldn't get db connection from pool");
+
+    let offer = match offers::table.find(off_id).first::<Offer>(&mut conn) {
+        Ok(o) => o,
+        Err(diesel::result::Error::NotFound) => return HttpResponse::NotFound().body("Offer not found"),
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    };
+
+    if !actor.is_staff() {
+        if offer.supplier_id != actor.id {
+            return HttpResponse::Forbidden().body("No tienes acceso a esta oferta");
+        }
+    } else {
+        match requests::table.find(offer.request_id).first::<Request>(&mut conn) {
+            Ok(req) if bids_sealed(&req) => return sealed_response(&req),
+            Ok(_) => {},
+            Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+        }
+    }
+
+    let results = offer_revisions::table
+        .filter(offer_revisions::offer_id.eq(off_id))
+        .order(offer_revisions::revision.asc())
+        .load::<OfferRevision>(&mut conn);
+
+    match results {
+        Ok(list) => {
+            let views: Vec<RevisionView> = list
+                .into_iter()
+                .map(|revision| {
+                    let lines = serde_json::from_str(&revision.lines).unwrap_or_default();
+                    RevisionView { revision, lines }
+                })
+                .collect();
+            HttpResponse::Ok().json(views)
+        },
         Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
     }
 }
 
 pub async fn list_offers(
     pool: web::Data<DbPool>,
+    actor: AuthUser,
     path: web::Path<i32>,
 ) -> impl Responder {
     let req_id = path.into_inner();
     let mut conn = pool.get().expect("couldn't get db connection from pool");
-    use crate::db::schema::offers::dsl::*;
 
-    let results = offers
-        .filter(request_id.eq(req_id))
-        .load::<Offer>(&mut conn);
+    let request = match requests::table.find(req_id).first::<Request>(&mut conn) {
+        Ok(r) => r,
+        Err(diesel::result::Error::NotFound) => return HttpResponse::NotFound().body("Request not found"),
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    };
+
+    let mut query = offers::table
+        .filter(offers::request_id.eq(req_id))
+        .into_boxed();
+
+    if !actor.is_staff() {
+        // Suppliers never see competitors' bids
+        query = query.filter(offers::supplier_id.eq(actor.id));
+    } else if bids_sealed(&request) {
+        return sealed_response(&request);
+    }
+
+    let results = query
+        .load::<Offer>(&mut conn)
+        .and_then(|list| with_lines(&mut conn, list));
 
     match results {
         Ok(list) => HttpResponse::Ok().json(list),
@@ -38,67 +788,822 @@ pub async fn list_offers(
     }
 }
 
+const OFFER_SORTS: &[&str] = &["created_at", "updated_at", "price", "total"];
+
+/// Offers matching the list filters. `status`, `request_id` and `from`/`to` (on the date the
+/// offer was sent) apply to every offer list; `supplier_id` only to the staff list.
+fn filtered_offers(query: &ListQuery, status: Option<OfferStatus>) -> offers::BoxedQuery<'static, Pg> {
+    let mut q = offers::table.into_boxed();
+    if let Some(st) = status {
+        q = q.filter(offers::status.eq(st));
+    }
+    if let Some(req_id) = query.request_id {
+        q = q.filter(offers::request_id.eq(req_id));
+    }
+    if let Some(start) = query.starts_at() {
+        q = q.filter(offers::created_at.ge(start));
+    }
+    if let Some(end) = query.ends_before() {
+        q = q.filter(offers::created_at.lt(end));
+    }
+    q
+}
+
+fn sorted_offers(q: offers::BoxedQuery<'static, Pg>, sort: &str, desc: bool) -> offers::BoxedQuery<'static, Pg> {
+    let q = match (sort, desc) {
+        ("updated_at", false) => q.order(offers::updated_at.asc()),
+        ("updated_at", true) => q.order(offers::updated_at.desc()),
+        ("price", false) => q.order(offers::price.asc()),
+        ("price", true) => q.order(offers::price.desc()),
+        ("total", false) => q.order(offers::total.asc()),
+        ("total", true) => q.order(offers::total.desc()),
+        (_, false) => q.order(offers::created_at.asc()),
+        (_, true) => q.order(offers::created_at.desc()),
+    };
+    q.then_order_by(offers::id.asc())
+}
+
+/// Parses the `status` filter of an offer list.
+fn status_filter(query: &ListQuery) -> Result<Option<OfferStatus>, HttpResponse> {
+    match query.status.as_deref() {
+        None => Ok(None),
+        Some(s) => OfferStatus::parse(s).map(Some).ok_or_else(|| pagination::invalid_status(s)),
+    }
+}
+
+/// Loads one page of the filtered offers, with the lines of each offer.
+fn offer_page(
+    conn: &mut PgConnection,
+    filtered: impl Fn() -> offers::BoxedQuery<'static, Pg>,
+    query: &ListQuery,
+    sort: &str,
+    desc: bool,
+) -> QueryResult<Page<OfferDetail>> {
+    let total = filtered().count().get_result::<i64>(conn)?;
+    let list = sorted_offers(filtered(), sort, desc)
+        .limit(query.per_page())
+        .offset(query.offset())
+        .load::<Offer>(conn)?;
+    Ok(Page::new(with_lines(conn, list)?, total, query))
+}
+
+pub async fn my_offers(
+    pool: web::Data<DbPool>,
+    actor: AuthUser,
+    query: web::Query<ListQuery>,
+) -> impl Responder {
+    let query = query.into_inner();
+    let Some((sort, desc)) = query.sort(OFFER_SORTS, "-created_at") else {
+        return pagination::invalid_sort(OFFER_SORTS);
+    };
+    let status = match status_filter(&query) {
+        Ok(s) => s,
+        Err(response) => return response,
+    };
+
+    let mut conn = pool.get().expect("couldn't get db connection from pool");
+    let own = || filtered_offers(&query, status).filter(offers::supplier_id.eq(actor.id));
+
+    match offer_page(&mut conn, own, &query, sort, desc) {
+        Ok(page) => HttpResponse::Ok().json(page),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+/// Supplier accepts the latest version of the request terms for an offer flagged by an amendment.
+/// Like any other change to the offer it needs the request still open, and it is kept as a
+/// new revision.
+pub async fn reconfirm_offer(
+    pool: web::Data<DbPool>,
+    events: web::Data<EventHub>,
+    actor: AuthUser,
+    path: web::Path<i32>,
+) -> impl Responder {
+    let off_id = path.into_inner();
+    let mut conn = pool.get().expect("couldn't get db connection from pool");
+
+    let result = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+        let (current, req) = match lock_open_offer(conn, &actor, off_id)? {
+            Ok(locked) => locked,
+            Err(response) => return Ok(Err(response)),
+        };
+
+        let offer = diesel::update(offers::table.find(off_id))
+            .set((
+                offers::request_version.eq(req.version),
+                offers::needs_reconfirmation.eq(false),
+                offers::revision.eq(current.revision + 1),
+                offers::updated_at.eq(chrono::Local::now().naive_local()),
+            ))
+            .get_result::<Offer>(conn)?;
+
+        let lines = offer_lines::table
+            .filter(offer_lines::offer_id.eq(off_id))
+            .order(offer_lines::id.asc())
+            .load::<OfferLine>(conn)?;
+        record_revision(conn, &offer, &lines)?;
+
+        Ok(Ok(offer))
+    });
+
+    match result {
+        Ok(Ok(offer)) => {
+            events.publish(Event::offer(EventKind::OfferUpdated, &offer));
+            HttpResponse::Ok().json(offer)
+        },
+        Ok(Err(response)) => response,
+        Err(diesel::result::Error::NotFound) => HttpResponse::NotFound().body("Offer not found"),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
 pub async fn list_all_offers(
     pool: web::Data<DbPool>,
+    query: web::Query<ListQuery>,
 ) -> impl Responder {
+    let query = query.into_inner();
+    let Some((sort, desc)) = query.sort(OFFER_SORTS, "-created_at") else {
+        return pagination::invalid_sort(OFFER_SORTS);
+    };
+    let status = match status_filter(&query) {
+        Ok(s) => s,
+        Err(response) => return response,
+    };
+
     let mut conn = pool.get().expect("couldn't get db connection from pool");
-    use crate::db::schema::offers::dsl::*;
+    let now = chrono::Local::now().naive_local();
 
-    let results = offers
-        .load::<Offer>(&mut conn);
+    let visible = || {
+        // Leave out bids on sealed requests that are still accepting offers
+        let open_requests = requests::table
+            .filter(
+                requests::bidding_mode.ne(BiddingMode::Sealed)
+                    .or(requests::deadline.le(now))
+                    .or(requests::status.ne(RequestStatus::Published))
+            )
+            .select(requests::id);
 
-    match results {
-        Ok(list) => HttpResponse::Ok().json(list),
+        let mut q = filtered_offers(&query, status).filter(offers::request_id.eq_any(open_requests));
+        if let Some(supplier) = query.supplier_id {
+            q = q.filter(offers::supplier_id.eq(supplier));
+        }
+        q
+    };
+
+    match offer_page(&mut conn, visible, &query, sort, desc) {
+        Ok(page) => HttpResponse::Ok().json(page),
         Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
     }
 }
 
+#[derive(Deserialize)]
+pub struct ComparisonQuery {
+    /// Date whose exchange rates are used; defaults to today
+    pub fecha: Option<chrono::NaiveDate>,
+}
+
+/// An offer with its price expressed in the currency of the request.
+#[derive(Serialize)]
+pub struct ComparedOffer {
+    #[serde(flatten)]
+    pub offer: Offer,
+    /// `None` when there is no exchange rate for the offer currency on the comparison date
+    pub normalized_price: Option<Decimal>,
+    /// Same conversion applied to the total with taxes
+    pub normalized_total: Option<Decimal>,
+    pub late: bool,
+    /// Quotes every line of the request; incomplete offers can only win line by line
+    pub complete: bool,
+    /// Normalized total of each quoted request line, taxes prorated by line price
+    #[serde(skip)]
+    pub line_totals: Vec<(i32, Option<Decimal>)>,
+}
+
+/// Offers of a request normalized to the request currency, cheapest first.
+pub async fn compare_offers(
+    pool: web::Data<DbPool>,
+    path: web::Path<i32>,
+    query: web::Query<ComparisonQuery>,
+) -> impl Responder {
+    let req_id = path.into_inner();
+    let date = query.fecha.unwrap_or_else(|| chrono::Local::now().date_naive());
+    let mut conn = pool.get().expect("couldn't get db connection from pool");
+
+    let request = match requests::table.find(req_id).first::<Request>(&mut conn) {
+        Ok(r) => r,
+        Err(diesel::result::Error::NotFound) => return HttpResponse::NotFound().body("Request not found"),
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    };
+
+    if bids_sealed(&request) {
+        return sealed_response(&request);
+    }
+
+    let compared = match normalized_offers(&mut conn, &request, date) {
+        Ok(list) => list,
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    };
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "request_id": request.id,
+        "currency": request.currency,
+        "date": date,
+        "offers": compared
+    }))
+}
+
+/// Offers of `request` converted with the rates in effect on `date`. Offers quoting every
+/// line come first, since a partial offer always looks cheaper; each group is sorted by
+/// normalized price and offers that cannot be converted go last.
+pub(crate) fn normalized_offers(
+    conn: &mut PgConnection,
+    request: &Request,
+    date: chrono::NaiveDate,
+) -> QueryResult<Vec<ComparedOffer>> {
+    let list = offers::table
+        .filter(offers::request_id.eq(request.id))
+        .filter(offers::status.ne(OfferStatus::Withdrawn))
+        .order(offers::created_at.asc())
+        .load::<Offer>(conn)?;
+
+    let total_lines: i64 = request_lines::table
+        .filter(request_lines::request_id.eq(request.id))
+        .count()
+        .get_result(conn)?;
+    let offer_ids: Vec<i32> = list.iter().map(|o| o.id).collect();
+    let quoted = offer_lines::table
+        .filter(offer_lines::offer_id.eq_any(&offer_ids))
+        .load::<OfferLine>(conn)?;
+
+    let mut compared = Vec::with_capacity(list.len());
+    for offer in list {
+        let normalized_price = currency::convert(conn, offer.price, &offer.currency, &request.currency, date)?;
+        let normalized_total = currency::convert(conn, offer.total, &offer.currency, &request.currency, date)?;
+        let late = is_late(&offer, request);
+        let line_totals: Vec<(i32, Option<Decimal>)> = quoted
+            .iter()
+            .filter(|l| l.offer_id == offer.id)
+            .map(|l| (l.request_line_id, normalized_total.map(|t| prorate(t, l.price, offer.price))))
+            .collect();
+        let complete = line_totals.len() as i64 >= total_lines;
+        compared.push(ComparedOffer { offer, normalized_price, normalized_total, late, complete, line_totals });
+    }
+
+    compared.sort_by(|a, b| {
+        b.complete.cmp(&a.complete).then_with(|| match (a.normalized_price, b.normalized_price) {
+            (Some(x), Some(y)) => x.cmp(&y),
+            (Some(_), None) => std::cmp::Ordering::Less,
+            (None, Some(_)) => std::cmp::Ordering::Greater,
+            (None, None) => std::cmp::Ordering::Equal,
+        })
+    });
+    Ok(compared)
+}
+
+/// Share of `total` that corresponds to a line priced `line_price` out of `price`.
+fn prorate(total: Decimal, line_price: Decimal, price: Decimal) -> Decimal {
+    if price.is_zero() {
+        Decimal::ZERO
+    } else {
+        total * line_price / price
+    }
+}
+
+pub(crate) enum AwardError {
+    NotFound,
+    Sealed(Box<Request>),
+    AlreadyAwarded,
+    NeedsReconfirmation,
+    /// The offer does not quote every line, so it can only win line by line
+    IncompleteOffer,
+    /// Some lines were already awarded individually
+    SplitAward,
+    AuctionOpen(Box<Request>),
+    InvalidTransition(RequestStatus),
+    /// The evaluation given as the basis of the award belongs to another request
+    UnknownEvaluation,
+    Db(diesel::result::Error),
+}
+
+/// Lines of a request as seen by an award of a whole offer.
+struct LineCounts {
+    total: i64,
+    /// Lines the offer quotes
+    quoted: i64,
+    /// Lines already awarded on their own
+    won: i64,
+}
+
+/// Why an offer can't win the whole request, from the state read under the request lock;
+/// `None` when it can.
+fn offer_award_conflict(
+    request: RequestStatus,
+    offer: OfferStatus,
+    needs_reconfirmation: bool,
+    lines: LineCounts,
+) -> Option<AwardError> {
+    if request == RequestStatus::Awarded {
+        return Some(AwardError::AlreadyAwarded);
+    }
+    if needs_reconfirmation {
+        return Some(AwardError::NeedsReconfirmation);
+    }
+    if !request.can_transition_to(RequestStatus::Awarded) || !offer.can_transition_to(OfferStatus::Won) {
+        return Some(AwardError::InvalidTransition(request));
+    }
+    if lines.quoted < lines.total {
+        return Some(AwardError::IncompleteOffer);
+    }
+    if lines.won > 0 {
+        return Some(AwardError::SplitAward);
+    }
+    None
+}
+
+/// Why one offer line can't win its request line; `None` when it can. A line quoted by a
+/// rival that already won was rejected along with the award, so it no longer can.
+fn line_award_conflict(
+    request: RequestStatus,
+    offer: OfferStatus,
+    needs_reconfirmation: bool,
+    line: OfferStatus,
+) -> Option<AwardError> {
+    if request == RequestStatus::Awarded {
+        return Some(AwardError::AlreadyAwarded);
+    }
+    if needs_reconfirmation {
+        return Some(AwardError::NeedsReconfirmation);
+    }
+    if !request.can_transition_to(RequestStatus::Awarded) || offer != OfferStatus::Sent || !line.can_transition_to(OfferStatus::Won) {
+        return Some(AwardError::InvalidTransition(request));
+    }
+    None
+}
+
+impl From<diesel::result::Error> for AwardError {
+    fn from(e: diesel::result::Error) -> Self {
+        match e {
+            diesel::result::Error::NotFound => AwardError::NotFound,
+            other => AwardError::Db(other),
+        }
+    }
+}
+
+pub(crate) struct Award {
+    pub winner: Offer,
+    pub rejected: Vec<Offer>,
+    pub newly_awarded: bool,
+}
+
+/// Checks that the evaluation a buyer based the award on belongs to the request.
+fn check_evaluation(conn: &mut PgConnection, req_id: i32, evaluation_id: Option<i32>) -> Result<(), AwardError> {
+    let Some(eval_id) = evaluation_id else { return Ok(()) };
+    match evaluation::evaluation_request(conn, eval_id)? {
+        Some(id) if id == req_id => Ok(()),
+        _ => Err(AwardError::UnknownEvaluation),
+    }
+}
+
+/// Marks `off_id` as the winner, rejects every other offer on the request and moves the
+/// request to "awarded", all in one transaction. Awarding the current winner again is a no-op.
+/// `evaluation_id` is the evaluation the buyer reviewed, if any; it is kept on the request.
+pub(crate) fn award_offer(conn: &mut PgConnection, off_id: i32, evaluation_id: Option<i32>) -> Result<Award, AwardError> {
+    conn.transaction::<_, AwardError, _>(|conn| {
+        let offer = offers::table.find(off_id).first::<Offer>(conn)?;
+
+        // Row lock on the request serializes concurrent awards
+        let req = requests::table
+            .find(offer.request_id)
+            .for_update()
+            .first::<Request>(conn)?;
+
+        if bids_sealed(&req) {
+            return Err(AwardError::Sealed(Box::new(req)));
+        }
+        if auction::auction_live(&req) {
+            return Err(AwardError::AuctionOpen(Box::new(req)));
+        }
+
+        // Re-read under the lock in case another award just finished
+        let offer = offers::table.find(off_id).first::<Offer>(conn)?;
+        if offer.status == OfferStatus::Won {
+            return Ok(Award { winner: offer, rejected: Vec::new(), newly_awarded: false });
+        }
+        let lines = LineCounts {
+            total: request_lines::table
+                .filter(request_lines::request_id.eq(req.id))
+                .count()
+                .get_result(conn)?,
+            quoted: offer_lines::table
+                .filter(offer_lines::offer_id.eq(off_id))
+                .count()
+                .get_result(conn)?,
+            won: won_lines(conn, req.id)?,
+        };
+        if let Some(conflict) = offer_award_conflict(req.status, offer.status, offer.needs_reconfirmation, lines) {
+            return Err(conflict);
+        }
+        check_evaluation(conn, req.id, evaluation_id)?;
+
+        let winner = diesel::update(offers::table.find(off_id))
+            .set(offers::status.eq(OfferStatus::Won))
+            .get_result::<Offer>(conn)?;
+
+        let rejected = diesel::update(
+            offers::table
+                .filter(offers::request_id.eq(req.id))
+                .filter(offers::id.ne(off_id))
+                .filter(offers::status.eq(OfferStatus::Sent)),
+        )
+        .set(offers::status.eq(OfferStatus::Rejected))
+        .get_results::<Offer>(conn)?;
+
+        diesel::update(offer_lines::table.filter(offer_lines::offer_id.eq(off_id)))
+            .set(offer_lines::status.eq(OfferStatus::Won))
+            .execute(conn)?;
+
+        let rejected_ids: Vec<i32> = rejected.iter().map(|o| o.id).collect();
+        diesel::update(offer_lines::table.filter(offer_lines::offer_id.eq_any(&rejected_ids)))
+            .set(offer_lines::status.eq(OfferStatus::Rejected))
+            .execute(conn)?;
+
+        diesel::update(requests::table.find(req.id))
+            .set((
+                requests::status.eq(RequestStatus::Awarded),
+                requests::awarded_evaluation_id.eq(evaluation_id),
+            ))
+            .execute(conn)?;
+
+        diesel::update(suppliers::table.find(winner.supplier_id))
+            .set(suppliers::earnings_count.eq(suppliers::earnings_count + 1))
+            .execute(conn)?;
+
+        Ok(Award { winner, rejected, newly_awarded: true })
+    })
+}
+
+fn won_lines(conn: &mut PgConnection, req_id: i32) -> QueryResult<i64> {
+    offer_lines::table
+        .inner_join(request_lines::table)
+        .filter(request_lines::request_id.eq(req_id))
+        .filter(offer_lines::status.eq(OfferStatus::Won))
+        .count()
+        .get_result(conn)
+}
+
+pub(crate) struct LineAward {
+    pub line: OfferLine,
+    /// Offers that won and lost the request, once its last line has been awarded
+    pub completed: Option<(Vec<Offer>, Vec<Offer>)>,
+    pub newly_awarded: bool,
+}
+
+/// Awards a single line of a request to `line_id` and rejects the competing quotes for that
+/// line. When every line of the request has a winner, the request is moved to "awarded":
+/// offers that won at least one line become winners and the rest are rejected.
+pub(crate) fn award_line(conn: &mut PgConnection, line_id: i32, evaluation_id: Option<i32>) -> Result<LineAward, AwardError> {
+    conn.transaction::<_, AwardError, _>(|conn| {
+        let line = offer_lines::table.find(line_id).first::<OfferLine>(conn)?;
+        let offer = offers::table.find(line.offer_id).first::<Offer>(conn)?;
+
+        let req = requests::table
+            .find(offer.request_id)
+            .for_update()
+            .first::<Request>(conn)?;
+
+        if bids_sealed(&req) {
+            return Err(AwardError::Sealed(Box::new(req)));
+        }
+        if auction::auction_live(&req) {
+            return Err(AwardError::AuctionOpen(Box::new(req)));
+        }
+
+        let line = offer_lines::table.find(line_id).first::<OfferLine>(conn)?;
+        if line.status == OfferStatus::Won {
+            return Ok(LineAward { line, completed: None, newly_awarded: false });
+        }
+        if let Some(conflict) = line_award_conflict(req.status, offer.status, offer.needs_reconfirmation, line.status) {
+            return Err(conflict);
+        }
+        check_evaluation(conn, req.id, evaluation_id)?;
+
+        let line = diesel::update(offer_lines::table.find(line_id))
+            .set(offer_lines::status.eq(OfferStatus::Won))
+            .get_result::<OfferLine>(conn)?;
+
+        diesel::update(
+            offer_lines::table
+                .filter(offer_lines::request_line_id.eq(line.request_line_id))
+                .filter(offer_lines::id.ne(line_id))
+                .filter(offer_lines::status.eq(OfferStatus::Sent)),
+        )
+        .set(offer_lines::status.eq(OfferStatus::Rejected))
+        .execute(conn)?;
+
+        let total_lines: i64 = request_lines::table
+            .filter(request_lines::request_id.eq(req.id))
+            .count()
+            .get_result(conn)?;
+        if won_lines(conn, req.id)? < total_lines {
+            return Ok(LineAward { line, completed: None, newly_awarded: true });
+        }
+
+        let winner_ids: Vec<i32> = offer_lines::table
+            .inner_join(request_lines::table)
+            .filter(request_lines::request_id.eq(req.id))
+            .filter(offer_lines::status.eq(OfferStatus::Won))
+            .select(offer_lines::offer_id)
+            .distinct()
+            .load(conn)?;
+
+        let winners = diesel::update(offers::table.filter(offers::id.eq_any(&winner_ids)))
+            .set(offers::status.eq(OfferStatus::Won))
+            .get_results::<Offer>(conn)?;
+
+        let rejected = diesel::update(
+            offers::table
+                .filter(offers::request_id.eq(req.id))
+                .filter(offers::status.eq(OfferStatus::Sent)),
+        )
+        .set(offers::status.eq(OfferStatus::Rejected))
+        .get_results::<Offer>(conn)?;
+
+        // Lines the winners quoted but lost are already rejected line by line
+        let rejected_ids: Vec<i32> = rejected.iter().map(|o| o.id).collect();
+        diesel::update(
+            offer_lines::table
+                .filter(offer_lines::offer_id.eq_any(&rejected_ids))
+                .filter(offer_lines::status.eq(OfferStatus::Sent)),
+        )
+        .set(offer_lines::status.eq(OfferStatus::Rejected))
+        .execute(conn)?;
+
+        diesel::update(requests::table.find(req.id))
+            .set((
+                requests::status.eq(RequestStatus::Awarded),
+                requests::awarded_evaluation_id.eq(evaluation_id),
+            ))
+            .execute(conn)?;
+
+        for winner in &winners {
+            diesel::update(suppliers::table.find(winner.supplier_id))
+                .set(suppliers::earnings_count.eq(suppliers::earnings_count + 1))
+                .execute(conn)?;
+        }
+
+        Ok(LineAward { line, completed: Some((winners, rejected)), newly_awarded: true })
+    })
+}
+
+/// Emails the winner and every supplier whose offer was rejected by the award.
+pub(crate) fn notify_award(pool: &DbPool, events: &EventHub, conn: &mut PgConnection, award: &Award) {
+    notify_results(pool, events, conn, std::slice::from_ref(&award.winner), &award.rejected);
+}
+
+fn notify_results(pool: &DbPool, events: &EventHub, conn: &mut PgConnection, winners: &[Offer], rejected: &[Offer]) {
+    if let Some(req_id) = winners.first().map(|w| w.request_id) {
+        if let Ok(req) = requests::table.find(req_id).first::<Request>(conn) {
+            events.publish(Event::request(EventKind::RequestUpdated, &req));
+        }
+    }
+    for offer in winners {
+        events.publish(Event::offer(EventKind::OfferWon, offer));
+    }
+    for offer in rejected {
+        events.publish(Event::offer(EventKind::OfferRejected, offer));
+    }
+
+    for winner in winners {
+        let winner_email: String = suppliers::table
+            .find(winner.supplier_id)
+            .select(suppliers::email)
+            .first(conn)
+            .unwrap_or_default();
+
+        email_service::send_winner_email(pool, &winner_email);
+    }
+
+    for other_offer in rejected {
+        // Inefficient N+1 query but simple for MVP
+        let other_email: String = suppliers::table
+            .find(other_offer.supplier_id)
+            .select(suppliers::email)
+            .first(conn)
+            .unwrap_or_default();
+
+        email_service::send_rejected_email(pool, &other_email);
+    }
+}
+
+#[derive(Deserialize)]
+pub struct AwardQuery {
+    /// Evaluation the buyer reviewed before deciding; stored on the request once it is awarded
+    pub evaluation_id: Option<i32>,
+}
+
 pub async fn mark_winner(
     pool: web::Data<DbPool>,
+    events: web::Data<EventHub>,
+    actor: AuthUser,
     path: web::Path<i32>,
+    query: web::Query<AwardQuery>,
 ) -> impl Responder {
     let off_id = path.into_inner();
     let mut conn = pool.get().expect("couldn't get db connection from pool");
-    use crate::db::schema::offers::dsl::*;
-
-    let updated = diesel::update(offers.find(off_id))
-        .set(status.eq("ganadora"))
-        .get_result::<Offer>(&mut conn);
-
-    match updated {
-        Ok(offer) => {
-            // Send Winner Email
-            // Need to join to get supplier email (or simple query)
-            let winner_email: String = suppliers::table
-                .find(offer.supplier_id)
-                .select(suppliers::email)
-                .first(&mut conn)
-                .unwrap_or_default();
-                
-            email_service::send_winner_email(&pool, &winner_email);
-            
-            // Mark others as rejected (optional but requested "notify rejected")
-            // and send emails
-            let request_id_val = offer.request_id;
-            let others: Vec<Offer> = offers
-                .filter(request_id.eq(request_id_val))
-                .filter(id.ne(offer.id))
-                .load::<Offer>(&mut conn)
-                .unwrap_or_default();
-                
-            for other_offer in others {
-                 // Inefficient N+1 query but simple for MVP
-                 let other_email: String = suppliers::table
-                    .find(other_offer.supplier_id)
-                    .select(suppliers::email)
-                    .first(&mut conn)
-                    .unwrap_or_default();
-                    
-                 email_service::send_rejected_email(&pool, &other_email);
-            }
 
-            HttpResponse::Ok().json(offer)
+    let award = match award_offer(&mut conn, off_id, query.evaluation_id) {
+        Ok(a) => a,
+        Err(e) => return award_error_response(e),
+    };
+
+    if award.newly_awarded {
+        audit::record(&mut conn, &actor, "mark_winner", "offer", Some(award.winner.id));
+        notify_award(&pool, &events, &mut conn, &award);
+    }
+
+    HttpResponse::Ok().json(award.winner)
+}
+
+pub(crate) fn award_error_response(error: AwardError) -> HttpResponse {
+    match error {
+        AwardError::NotFound => HttpResponse::NotFound().body("Offer not found"),
+        AwardError::Sealed(req) => sealed_response(&req),
+        AwardError::AuctionOpen(req) => auction::open_response(&req),
+        AwardError::AlreadyAwarded => {
+            HttpResponse::Conflict().body("La solicitud ya fue adjudicada a otra oferta")
         },
-        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+        AwardError::NeedsReconfirmation => {
+            HttpResponse::Conflict().body("La oferta corresponde a una versión anterior de la solicitud y no ha sido reconfirmada")
+        },
+        AwardError::IncompleteOffer => {
+            HttpResponse::Conflict().body("La oferta no cotiza todas las partidas; adjudícala por partida")
+        },
+        AwardError::SplitAward => {
+            HttpResponse::Conflict().body("La solicitud ya se está adjudicando por partida")
+        },
+        AwardError::InvalidTransition(current) => HttpResponse::Conflict().json(serde_json::json!({
+            "error": "invalid_transition",
+            "from": current,
+            "to": RequestStatus::Awarded
+        })),
+        AwardError::UnknownEvaluation => validation::rejected(vec![
+            Violation::new("evaluation_request", "La evaluación no corresponde a esta solicitud"),
+        ]),
+        AwardError::Db(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+/// Awards one line of a multi-line request to the given offer line.
+pub async fn mark_line_winner(
+    pool: web::Data<DbPool>,
+    events: web::Data<EventHub>,
+    actor: AuthUser,
+    path: web::Path<i32>,
+    query: web::Query<AwardQuery>,
+) -> impl Responder {
+    let line_id = path.into_inner();
+    let mut conn = pool.get().expect("couldn't get db connection from pool");
+
+    let award = match award_line(&mut conn, line_id, query.evaluation_id) {
+        Ok(a) => a,
+        Err(e) => return award_error_response(e),
+    };
+
+    if award.newly_awarded {
+        audit::record(&mut conn, &actor, "mark_line_winner", "offer_line", Some(award.line.id));
+    }
+    if let Some((winners, rejected)) = &award.completed {
+        notify_results(&pool, &events, &mut conn, winners, rejected);
+    }
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "line": award.line,
+        "request_awarded": award.completed.is_some()
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::test_support::{dec, rules};
+
+    #[test]
+    fn line_total_follows_the_unit_price() {
+        assert_eq!(price_line(3, Some(dec("12.505")), dec("0")), (dec("12.505"), dec("37.52")));
+    }
+
+    #[test]
+    fn unit_price_is_derived_from_the_line_total() {
+        assert_eq!(price_line(3, None, dec("100")), (dec("33.3333"), dec("100")));
+        // A zero quantity counts as one unit
+        assert_eq!(price_line(0, None, dec("80.5")), (dec("80.5"), dec("80.5")));
+    }
+
+    fn request_line(id: i32) -> RequestLine {
+        RequestLine { id, request_id: 1, line_no: id, description: format!("Partida {}", id), quantity: 1, units: "pza".to_string() }
+    }
+
+    fn quote(request_line_id: i32) -> OfferLineInput {
+        OfferLineInput {
+            request_line_id,
+            price: dec("100"),
+            unit_price: None,
+            delivery_days: Some(5),
+            promised_date: None,
+            delivery_time: String::new(),
+        }
+    }
+
+    fn quoted_line_rules(lines: &[RequestLine], quoted: &[OfferLineInput]) -> Vec<&'static str> {
+        let mut violations = Vec::new();
+        check_quoted_lines(&mut violations, lines, quoted);
+        rules(&violations)
+    }
+
+    #[test]
+    fn request_without_lines_cannot_be_quoted() {
+        assert_eq!(quoted_line_rules(&[], &[]), ["request_lines"]);
+        assert_eq!(quoted_line_rules(&[], &[quote(1)]), ["request_lines"]);
+    }
+
+    #[test]
+    fn single_line_request_takes_a_header_quote() {
+        assert!(quoted_line_rules(&[request_line(1)], &[]).is_empty());
+        assert_eq!(quoted_line_rules(&[request_line(1), request_line(2)], &[]), ["lines_required"]);
+    }
+
+    #[test]
+    fn quoted_lines_belong_to_the_request_once() {
+        let lines = [request_line(1), request_line(2)];
+        assert!(quoted_line_rules(&lines, &[quote(1), quote(2)]).is_empty());
+        // Partial offers are fine; they can only win line by line
+        assert!(quoted_line_rules(&lines, &[quote(2)]).is_empty());
+        assert_eq!(quoted_line_rules(&lines, &[quote(1), quote(3), quote(1)]), ["line_exists", "line_unique"]);
+    }
+
+    #[test]
+    fn prices_must_be_positive_and_bounded() {
+        let mut violations = Vec::new();
+        check_price(&mut violations, dec("0.01"), "");
+        check_price(&mut violations, Decimal::from(MAX_OFFER_PRICE), "");
+        assert!(violations.is_empty());
+
+        check_price(&mut violations, dec("0"), "");
+        check_price(&mut violations, dec("-5"), "");
+        check_price(&mut violations, Decimal::from(MAX_OFFER_PRICE) + dec("0.01"), "");
+        assert_eq!(rules(&violations), ["price_positive", "price_positive", "price_range"]);
+    }
+
+    #[test]
+    fn delivery_takes_days_or_a_date() {
+        let today = chrono::Local::now().date_naive();
+        let mut violations = Vec::new();
+        let by_days = resolve_delivery(&mut violations, Some(3), None, "");
+        assert_eq!(by_days.map(|d| d.date), Some(today + chrono::Duration::days(3)));
+        let by_date = resolve_delivery(&mut violations, None, Some(today + chrono::Duration::days(10)), "");
+        assert_eq!(by_date.map(|d| d.days), Some(10));
+        assert!(violations.is_empty());
+
+        assert!(resolve_delivery(&mut violations, Some(3), Some(today), "").is_none());
+        assert!(resolve_delivery(&mut violations, None, None, "").is_none());
+        assert!(resolve_delivery(&mut violations, Some(MAX_DELIVERY_DAYS + 1), None, "").is_none());
+        assert!(resolve_delivery(&mut violations, None, Some(today - chrono::Duration::days(1)), "").is_none());
+        assert_eq!(rules(&violations), ["delivery_single", "delivery_required", "delivery_days_range", "promised_date_range"]);
+    }
+
+    fn counts(total: i64, quoted: i64, won: i64) -> LineCounts {
+        LineCounts { total, quoted, won }
+    }
+
+    #[test]
+    fn whole_offer_award_conflicts() {
+        use OfferStatus::{Rejected, Sent};
+        use RequestStatus::{Awarded, Draft, InEvaluation, Published};
+
+        assert!(offer_award_conflict(InEvaluation, Sent, false, counts(2, 2, 0)).is_none());
+        assert!(offer_award_conflict(Published, Sent, false, counts(1, 1, 0)).is_none());
+
+        assert!(matches!(offer_award_conflict(Awarded, Sent, false, counts(2, 2, 0)), Some(AwardError::AlreadyAwarded)));
+        assert!(matches!(offer_award_conflict(InEvaluation, Sent, true, counts(2, 2, 0)), Some(AwardError::NeedsReconfirmation)));
+        assert!(matches!(offer_award_conflict(Draft, Sent, false, counts(2, 2, 0)), Some(AwardError::InvalidTransition(Draft))));
+        assert!(matches!(offer_award_conflict(InEvaluation, Rejected, false, counts(2, 2, 0)), Some(AwardError::InvalidTransition(_))));
+        assert!(matches!(offer_award_conflict(InEvaluation, Sent, false, counts(3, 2, 0)), Some(AwardError::IncompleteOffer)));
+        // Once a line has its own winner the request can only be finished line by line
+        assert!(matches!(offer_award_conflict(InEvaluation, Sent, false, counts(2, 2, 1)), Some(AwardError::SplitAward)));
+    }
+
+    #[test]
+    fn line_award_conflicts() {
+        use OfferStatus::{Rejected, Sent, Withdrawn};
+        use RequestStatus::{Awarded, Expired, InEvaluation};
+
+        assert!(line_award_conflict(InEvaluation, Sent, false, Sent).is_none());
+
+        assert!(matches!(line_award_conflict(Awarded, Sent, false, Sent), Some(AwardError::AlreadyAwarded)));
+        assert!(matches!(line_award_conflict(InEvaluation, Sent, true, Sent), Some(AwardError::NeedsReconfirmation)));
+        // A rival already won this request line, which rejected the other quotes for it
+        assert!(matches!(line_award_conflict(InEvaluation, Sent, false, Rejected), Some(AwardError::InvalidTransition(_))));
+        assert!(matches!(line_award_conflict(InEvaluation, Withdrawn, false, Withdrawn), Some(AwardError::InvalidTransition(_))));
+        assert!(matches!(line_award_conflict(Expired, Sent, false, Sent), Some(AwardError::InvalidTransition(Expired))));
     }
 }
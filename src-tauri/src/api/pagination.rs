@@ -0,0 +1,131 @@
+use actix_web::HttpResponse;
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use crate::api::validation::{self, Violation};
+
+pub const DEFAULT_PER_PAGE: i64 = 50;
+pub const MAX_PER_PAGE: i64 = 200;
+
+/// Query string shared by the list endpoints. `sort` names a field, with a leading `-`
+/// for descending order; each endpoint ignores the filters that don't apply to it.
+#[derive(Deserialize, Debug, Default)]
+pub struct ListQuery {
+    pub page: Option<i64>,
+    pub per_page: Option<i64>,
+    pub sort: Option<String>,
+    pub status: Option<String>,
+    pub tag: Option<String>,
+    /// First day of the date range, inclusive
+    pub from: Option<NaiveDate>,
+    /// Last day of the date range, inclusive
+    pub to: Option<NaiveDate>,
+    pub supplier_id: Option<i32>,
+    pub request_id: Option<i32>,
+    pub category: Option<i32>,
+    /// Suppliers: list every request instead of only the ones in their categories
+    pub all: Option<bool>,
+}
+
+impl ListQuery {
+    /// Current page, starting at 1.
+    pub fn page(&self) -> i64 {
+        self.page.unwrap_or(1).max(1)
+    }
+
+    pub fn per_page(&self) -> i64 {
+        self.per_page.unwrap_or(DEFAULT_PER_PAGE).clamp(1, MAX_PER_PAGE)
+    }
+
+    pub fn offset(&self) -> i64 {
+        (self.page() - 1) * self.per_page()
+    }
+
+    /// Field to sort by and whether it is descending; `None` when the field isn't in `allowed`.
+    pub fn sort<'a>(&'a self, allowed: &[&str], default: &'a str) -> Option<(&'a str, bool)> {
+        let raw = self.sort.as_deref().unwrap_or(default);
+        let (field, desc) = match raw.strip_prefix('-') {
+            Some(field) => (field, true),
+            None => (raw, false),
+        };
+        allowed.contains(&field).then_some((field, desc))
+    }
+
+    pub fn starts_at(&self) -> Option<NaiveDateTime> {
+        self.from.and_then(|d| d.and_hms_opt(0, 0, 0))
+    }
+
+    /// Start of the day after `to`, so the whole last day is included.
+    pub fn ends_before(&self) -> Option<NaiveDateTime> {
+        self.to.and_then(|d| d.succ_opt()).and_then(|d| d.and_hms_opt(0, 0, 0))
+    }
+}
+
+/// Envelope returned by every list endpoint.
+#[derive(Serialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub total: i64,
+    pub page: i64,
+    pub per_page: i64,
+}
+
+impl<T: Serialize> Page<T> {
+    pub fn new(items: Vec<T>, total: i64, query: &ListQuery) -> Self {
+        Page { items, total, page: query.page(), per_page: query.per_page() }
+    }
+}
+
+pub fn invalid_sort(allowed: &[&str]) -> HttpResponse {
+    validation::rejected(vec![Violation::new(
+        "sort",
+        format!("Solo se puede ordenar por: {}", allowed.join(", ")),
+    )])
+}
+
+pub fn invalid_status(value: &str) -> HttpResponse {
+    validation::rejected(vec![Violation::new("status", format!("Estado desconocido: {}", value))])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SORTS: &[&str] = &["deadline", "id", "title"];
+
+    fn sorted_by(sort: Option<&str>) -> ListQuery {
+        ListQuery { sort: sort.map(str::to_string), ..Default::default() }
+    }
+
+    #[test]
+    fn sort_reads_field_and_direction() {
+        assert_eq!(sorted_by(None).sort(SORTS, "deadline"), Some(("deadline", false)));
+        assert_eq!(sorted_by(Some("title")).sort(SORTS, "deadline"), Some(("title", false)));
+        assert_eq!(sorted_by(Some("-id")).sort(SORTS, "deadline"), Some(("id", true)));
+    }
+
+    #[test]
+    fn sort_rejects_unknown_fields() {
+        assert_eq!(sorted_by(Some("price")).sort(SORTS, "deadline"), None);
+        assert_eq!(sorted_by(Some("--id")).sort(SORTS, "deadline"), None);
+    }
+
+    #[test]
+    fn date_range_covers_whole_days() {
+        let query = ListQuery {
+            from: NaiveDate::from_ymd_opt(2026, 2, 1),
+            to: NaiveDate::from_ymd_opt(2026, 2, 28),
+            ..Default::default()
+        };
+        assert_eq!(query.starts_at(), NaiveDate::from_ymd_opt(2026, 2, 1).and_then(|d| d.and_hms_opt(0, 0, 0)));
+        assert_eq!(query.ends_before(), NaiveDate::from_ymd_opt(2026, 3, 1).and_then(|d| d.and_hms_opt(0, 0, 0)));
+        assert_eq!(ListQuery::default().ends_before(), None);
+    }
+
+    #[test]
+    fn paging_is_clamped() {
+        let query = ListQuery { page: Some(0), per_page: Some(1000), ..Default::default() };
+        assert_eq!((query.page(), query.per_page(), query.offset()), (1, MAX_PER_PAGE, 0));
+        let query = ListQuery { page: Some(3), per_page: Some(20), ..Default::default() };
+        assert_eq!(query.offset(), 40);
+    }
+}
@@ -0,0 +1,327 @@
+use actix_web::{web, HttpResponse, Responder};
+use chrono::NaiveDateTime;
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::db::{DbPool, models::{NewRequestQuestion, Request, RequestQuestion}};
+use crate::db::schema::{offers, request_invitations, request_questions, requests, suppliers};
+use crate::db::status::{AnswerVisibility, InvitationStatus, OfferStatus, RequestStatus};
+use crate::api::{audit, events::{Event, EventHub, EventKind}, invitations, security::AuthUser, validation::{self, Violation}};
+use crate::{email_service, scheduler};
+
+const MAX_TEXT_LEN: usize = 2000;
+
+#[derive(Deserialize)]
+pub struct QuestionInput {
+    pub question: String,
+}
+
+/// Answer of a buyer. A public answer may rephrase the question (`public_question`) so it
+/// doesn't give away who asked; the original wording is kept for staff.
+#[derive(Deserialize)]
+pub struct AnswerInput {
+    pub answer: String,
+    pub visibility: AnswerVisibility,
+    pub public_question: Option<String>,
+}
+
+/// A question as shown to the caller. Suppliers never learn who asked someone else's question.
+#[derive(Serialize)]
+pub struct QuestionView {
+    pub id: i32,
+    pub request_id: i32,
+    pub question: String,
+    pub answer: Option<String>,
+    pub visibility: Option<AnswerVisibility>,
+    pub created_at: NaiveDateTime,
+    pub answered_at: Option<NaiveDateTime>,
+    /// Asked by the calling supplier
+    pub own: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub supplier_id: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub supplier_name: Option<String>,
+    /// Original wording, when a public answer rephrased it (staff only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub original_question: Option<String>,
+}
+
+impl QuestionView {
+    fn for_staff(q: RequestQuestion, supplier_name: Option<String>) -> Self {
+        let (question, original_question) = match q.public_question {
+            Some(public) => (public, Some(q.question)),
+            None => (q.question, None),
+        };
+        QuestionView {
+            id: q.id,
+            request_id: q.request_id,
+            question,
+            answer: q.answer,
+            visibility: q.visibility,
+            created_at: q.created_at,
+            answered_at: q.answered_at,
+            own: false,
+            supplier_id: q.supplier_id,
+            supplier_name,
+            original_question,
+        }
+    }
+
+    fn for_supplier(q: RequestQuestion, supplier_id: i32) -> Self {
+        let own = q.supplier_id == Some(supplier_id);
+        // The asker reads its own words; everybody else the public wording
+        let question = match q.public_question {
+            Some(public) if !own => public,
+            _ => q.question,
+        };
+        QuestionView {
+            id: q.id,
+            request_id: q.request_id,
+            question,
+            answer: q.answer,
+            visibility: q.visibility,
+            created_at: q.created_at,
+            answered_at: q.answered_at,
+            own,
+            supplier_id: None,
+            supplier_name: None,
+            original_question: None,
+        }
+    }
+}
+
+fn check_text(violations: &mut Vec<Violation>, rule: &'static str, value: &str, label: &str) {
+    if value.trim().is_empty() {
+        violations.push(Violation::new(rule, format!("Escribe {}", label)));
+    } else if value.chars().count() > MAX_TEXT_LEN {
+        violations.push(Violation::new("text_length", format!("El texto no puede pasar de {} caracteres", MAX_TEXT_LEN)));
+    }
+}
+
+fn load_request(conn: &mut PgConnection, req_id: i32) -> Result<Request, HttpResponse> {
+    match requests::table.find(req_id).first::<Request>(conn) {
+        Ok(r) => Ok(r),
+        Err(diesel::result::Error::NotFound) => Err(HttpResponse::NotFound().body("Request not found")),
+        Err(e) => Err(HttpResponse::InternalServerError().body(e.to_string())),
+    }
+}
+
+/// Emails of the suppliers taking part in a request: those with a live offer, the invitees
+/// that didn't decline and, if any, the supplier that asked.
+fn bidder_emails(conn: &mut PgConnection, req: &Request, asker: Option<i32>) -> QueryResult<Vec<String>> {
+    let mut ids: Vec<i32> = offers::table
+        .filter(offers::request_id.eq(req.id))
+        .filter(offers::status.ne(OfferStatus::Withdrawn))
+        .select(offers::supplier_id)
+        .load(conn)?;
+    ids.extend(
+        request_invitations::table
+            .filter(request_invitations::request_id.eq(req.id))
+            .filter(request_invitations::status.ne(InvitationStatus::Declined))
+            .select(request_invitations::supplier_id)
+            .load::<i32>(conn)?,
+    );
+    ids.extend(asker);
+
+    suppliers::table
+        .filter(suppliers::id.eq_any(ids))
+        .select(suppliers::email)
+        .distinct()
+        .load(conn)
+}
+
+/// Clarification thread of a request. Staff see every question with who asked it; suppliers
+/// see the public answers and their own questions.
+pub async fn list_questions(
+    pool: web::Data<DbPool>,
+    actor: AuthUser,
+    path: web::Path<i32>,
+) -> impl Responder {
+    let req_id = path.into_inner();
+    let mut conn = pool.get().expect("couldn't get db connection from pool");
+
+    let request = match load_request(&mut conn, req_id) {
+        Ok(r) => r,
+        Err(response) => return response,
+    };
+
+    if actor.is_staff() {
+        let rows = request_questions::table
+            .left_join(suppliers::table)
+            .filter(request_questions::request_id.eq(req_id))
+            .order(request_questions::created_at.asc())
+            .select((request_questions::all_columns, suppliers::name.nullable()))
+            .load::<(RequestQuestion, Option<String>)>(&mut conn);
+        return match rows {
+            Ok(list) => {
+                let views: Vec<QuestionView> = list.into_iter().map(|(q, name)| QuestionView::for_staff(q, name)).collect();
+                HttpResponse::Ok().json(views)
+            },
+            Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+        };
+    }
+
+    if request.status == RequestStatus::Draft || !invitations::can_see(&mut conn, &request, actor.id).unwrap_or(false) {
+        return HttpResponse::NotFound().body("Request not found");
+    }
+
+    let rows = request_questions::table
+        .filter(request_questions::request_id.eq(req_id))
+        .filter(
+            request_questions::visibility
+                .eq(AnswerVisibility::Public)
+                .or(request_questions::supplier_id.eq(actor.id)),
+        )
+        .order(request_questions::created_at.asc())
+        .load::<RequestQuestion>(&mut conn);
+
+    match rows {
+        Ok(list) => {
+            let views: Vec<QuestionView> = list.into_iter().map(|q| QuestionView::for_supplier(q, actor.id)).collect();
+            HttpResponse::Ok().json(views)
+        },
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+/// A supplier asks about a request it can bid on. The buying team is emailed.
+pub async fn ask_question(
+    pool: web::Data<DbPool>,
+    events: web::Data<EventHub>,
+    actor: AuthUser,
+    path: web::Path<i32>,
+    item: web::Json<QuestionInput>,
+) -> impl Responder {
+    let req_id = path.into_inner();
+    let mut conn = pool.get().expect("couldn't get db connection from pool");
+
+    let request = match load_request(&mut conn, req_id) {
+        Ok(r) => r,
+        Err(response) => return response,
+    };
+    if request.status == RequestStatus::Draft || !invitations::can_see(&mut conn, &request, actor.id).unwrap_or(false) {
+        return HttpResponse::NotFound().body("Request not found");
+    }
+
+    let mut violations = Vec::new();
+    check_text(&mut violations, "question_required", &item.question, "tu pregunta");
+    if !request.status.is_bidding() {
+        violations.push(Violation::new("request_open", format!("La solicitud no está abierta (estado: {})", request.status.as_str())));
+    }
+    if request.deadline < chrono::Local::now().naive_local() {
+        violations.push(Violation::new("deadline_open", "La fecha límite de la solicitud ya pasó"));
+    }
+    if !violations.is_empty() {
+        return validation::rejected(violations);
+    }
+
+    let new_question = NewRequestQuestion {
+        request_id: req_id,
+        supplier_id: Some(actor.id),
+        question: item.question.trim().to_string(),
+    };
+
+    let inserted = diesel::insert_into(request_questions::table)
+        .values(&new_question)
+        .get_result::<RequestQuestion>(&mut conn);
+
+    match inserted {
+        Ok(q) => {
+            audit::record(&mut conn, &actor, "ask_question", "question", Some(q.id));
+            events.publish(Event::question(EventKind::QuestionAsked, &q, &request));
+            for to in scheduler::buying_team_emails(&mut conn) {
+                email_service::send_question_email(&pool, &to, &request.title, &q.question);
+            }
+            HttpResponse::Ok().json(QuestionView::for_supplier(q, actor.id))
+        },
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+/// Answers a question once. Private answers go to the asker only; public ones are shown to
+/// every supplier that can see the request and emailed to everyone taking part in it.
+pub async fn answer_question(
+    pool: web::Data<DbPool>,
+    events: web::Data<EventHub>,
+    actor: AuthUser,
+    path: web::Path<(i32, i32)>,
+    item: web::Json<AnswerInput>,
+) -> impl Responder {
+    let (req_id, question_id) = path.into_inner();
+    let input = item.into_inner();
+
+    let mut violations = Vec::new();
+    check_text(&mut violations, "answer_required", &input.answer, "la respuesta");
+    if let Some(public) = &input.public_question {
+        if input.visibility != AnswerVisibility::Public {
+            violations.push(Violation::new("public_question_private", "Solo una respuesta pública puede reformular la pregunta"));
+        } else {
+            check_text(&mut violations, "public_question_required", public, "la pregunta como la verán los proveedores");
+        }
+    }
+    if !violations.is_empty() {
+        return validation::rejected(violations);
+    }
+
+    let mut conn = pool.get().expect("couldn't get db connection from pool");
+
+    let request = match load_request(&mut conn, req_id) {
+        Ok(r) => r,
+        Err(response) => return response,
+    };
+
+    let answered = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+        let current = request_questions::table
+            .find(question_id)
+            .filter(request_questions::request_id.eq(req_id))
+            .for_update()
+            .first::<RequestQuestion>(conn)?;
+        if current.answer.is_some() {
+            return Ok(Err(HttpResponse::Conflict().body("La pregunta ya fue respondida")));
+        }
+
+        let q = diesel::update(request_questions::table.find(question_id))
+            .set((
+                request_questions::answer.eq(input.answer.trim()),
+                request_questions::visibility.eq(input.visibility),
+                request_questions::public_question.eq(input.public_question.as_deref().map(str::trim)),
+                request_questions::answered_by.eq(actor.id),
+                request_questions::answered_at.eq(chrono::Local::now().naive_local()),
+            ))
+            .get_result::<RequestQuestion>(conn)?;
+        Ok(Ok(q))
+    });
+
+    let q = match answered {
+        Ok(Ok(q)) => q,
+        Ok(Err(response)) => return response,
+        Err(diesel::result::Error::NotFound) => return HttpResponse::NotFound().body("Question not found"),
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    };
+
+    audit::record(&mut conn, &actor, "answer_question", "question", Some(q.id));
+    events.publish(Event::question(EventKind::QuestionAnswered, &q, &request));
+
+    let answer = q.answer.as_deref().unwrap_or_default();
+    match q.visibility {
+        Some(AnswerVisibility::Public) => {
+            let shown = q.public_question.as_deref().unwrap_or(&q.question);
+            for to in bidder_emails(&mut conn, &request, q.supplier_id).unwrap_or_default() {
+                email_service::send_clarification_email(&pool, &to, &request.title, shown, answer);
+            }
+        },
+        _ => {
+            let asker: Option<String> = q
+                .supplier_id
+                .and_then(|id| suppliers::table.find(id).select(suppliers::email).first(&mut conn).ok());
+            if let Some(to) = asker {
+                email_service::send_answer_email(&pool, &to, &request.title, &q.question, answer);
+            }
+        },
+    }
+
+    let name: Option<String> = q
+        .supplier_id
+        .and_then(|id| suppliers::table.find(id).select(suppliers::name).first(&mut conn).ok());
+    HttpResponse::Ok().json(QuestionView::for_staff(q, name))
+}
@@ -1,37 +1,882 @@
 use actix_web::{web, HttpResponse, Responder};
-use crate::db::{DbPool, models::{NewRequest, Request}, schema::requests};
+use chrono::{NaiveDate, NaiveDateTime};
+use crate::db::{DbPool, models::{Category, NewRequest, NewRequestLine, NewRequestVersion, Request, RequestChanges, RequestLine, RequestLineChanges, RequestVersion}};
+use crate::db::schema::{offers, request_categories, request_invitations, request_lines, request_versions, requests, suppliers};
+use diesel::pg::{Pg, PgConnection};
 use diesel::prelude::*;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use crate::api::{audit, auction, categories, currency, events::{Event, EventHub, EventKind}, invitations, pagination::{self, ListQuery, Page}, security::AuthUser, validation::{self, Violation}};
+use crate::db::status::{InvitationStatus, OfferStatus, RequestStatus};
+use crate::email_service;
+
+/// A request with its line items and categories, plus how many offers it has received. The count is only shown to staff.
+#[derive(Serialize)]
+pub struct RequestDetail {
+    #[serde(flatten)]
+    pub request: Request,
+    pub lines: Vec<RequestLine>,
+    pub categories: Vec<Category>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offer_count: Option<i64>,
+}
+
+/// One item of the bill of materials being requested.
+#[derive(Deserialize, Debug)]
+pub struct RequestLineInput {
+    pub description: String,
+    pub quantity: i32,
+    pub units: String,
+}
+
+/// Request payload. When `lines` is empty the request is a single line built from its header.
+#[derive(Deserialize)]
+pub struct CreateRequestInput {
+    #[serde(flatten)]
+    pub request: NewRequest,
+    #[serde(default)]
+    pub lines: Vec<RequestLineInput>,
+    /// Category ids; when given, `tags` is filled with their names
+    #[serde(default)]
+    pub categories: Vec<i32>,
+}
+
+/// Lines of the given requests, ordered by request and line number.
+pub(crate) fn load_lines(conn: &mut PgConnection, req_ids: &[i32]) -> QueryResult<Vec<RequestLine>> {
+    request_lines::table
+        .filter(request_lines::request_id.eq_any(req_ids))
+        .order((request_lines::request_id.asc(), request_lines::line_no.asc()))
+        .load::<RequestLine>(conn)
+}
+
+fn with_lines(conn: &mut PgConnection, request: Request, offer_count: Option<i64>) -> QueryResult<RequestDetail> {
+    let lines = load_lines(conn, &[request.id])?;
+    let categories = categories::load_linked(conn, &[request.id])?.into_iter().map(|l| l.category).collect();
+    Ok(RequestDetail { request, lines, categories, offer_count })
+}
+
+pub(crate) fn check_lines(violations: &mut Vec<Violation>, lines: &[RequestLineInput]) {
+    for (i, line) in lines.iter().enumerate() {
+        if line.description.trim().is_empty() {
+            violations.push(Violation::new("line_description_required", format!("La partida {} no tiene descripción", i + 1)));
+        }
+        if line.quantity <= 0 {
+            violations.push(Violation::new("line_quantity_positive", format!("La cantidad de la partida {} debe ser mayor a cero", i + 1)));
+        }
+        if line.units.trim().is_empty() {
+            violations.push(Violation::new("line_units_required", format!("La partida {} no tiene unidad", i + 1)));
+        }
+    }
+}
+
+/// Line changes of an edit or amendment: each line at most once, with something to change, a
+/// positive quantity and a unit. Whether the lines belong to the request is checked on apply.
+fn check_line_changes(violations: &mut Vec<Violation>, changes: &[RequestLineChanges]) {
+    let mut seen = Vec::new();
+    for change in changes {
+        if seen.contains(&change.id) {
+            violations.push(Violation::new("line_unique", format!("La partida {} se modificó más de una vez", change.id)));
+        }
+        seen.push(change.id);
+
+        if change.quantity.is_none() && change.units.is_none() {
+            violations.push(Violation::new("line_changes", format!("No se enviaron cambios para la partida {}", change.id)));
+        }
+        if change.quantity.is_some_and(|q| q <= 0) {
+            violations.push(Violation::new("line_quantity_positive", format!("La cantidad de la partida {} debe ser mayor a cero", change.id)));
+        }
+        if change.units.as_ref().is_some_and(|u| u.trim().is_empty()) {
+            violations.push(Violation::new("line_units_required", format!("La partida {} no tiene unidad", change.id)));
+        }
+    }
+}
+
+/// Stores the line items of a new request, numbered in the order received.
+pub(crate) fn insert_lines(
+    conn: &mut PgConnection,
+    req: &Request,
+    lines: Vec<RequestLineInput>,
+) -> QueryResult<Vec<RequestLine>> {
+    let new_lines: Vec<NewRequestLine> = if lines.is_empty() {
+        vec![NewRequestLine {
+            request_id: req.id,
+            line_no: 1,
+            description: req.title.clone(),
+            quantity: req.quantity,
+            units: req.units.clone(),
+        }]
+    } else {
+        lines
+            .into_iter()
+            .enumerate()
+            .map(|(i, line)| NewRequestLine {
+                request_id: req.id,
+                line_no: i as i32 + 1,
+                description: line.description,
+                quantity: line.quantity,
+                units: line.units,
+            })
+            .collect()
+    };
+
+    diesel::insert_into(request_lines::table)
+        .values(&new_lines)
+        .get_results::<RequestLine>(conn)
+}
+
+fn offer_count(conn: &mut PgConnection, req_id: i32) -> QueryResult<i64> {
+    offers::table
+        .filter(offers::request_id.eq(req_id))
+        .count()
+        .get_result(conn)
+}
+
+fn check_fields(
+    violations: &mut Vec<Violation>,
+    title: Option<&str>,
+    deadline: Option<NaiveDateTime>,
+    quantity: Option<i32>,
+    currency_code: Option<&str>,
+    required_date: Option<NaiveDate>,
+) {
+    if let Some(t) = title {
+        if t.trim().is_empty() {
+            violations.push(Violation::new("title_required", "El título no puede estar vacío"));
+        }
+    }
+    if let Some(d) = deadline {
+        if d <= chrono::Local::now().naive_local() {
+            violations.push(Violation::new("deadline_future", "La fecha límite debe ser futura"));
+        }
+    }
+    if let Some(q) = quantity {
+        if q <= 0 {
+            violations.push(Violation::new("quantity_positive", "La cantidad debe ser mayor a cero"));
+        }
+    }
+    if let Some(c) = currency_code {
+        if !currency::is_currency_code(c) {
+            violations.push(Violation::new("currency_code", "La moneda debe ser un código de tres letras (ej. MXN)"));
+        }
+    }
+    if let Some(r) = required_date {
+        if r < chrono::Local::now().date_naive() {
+            violations.push(Violation::new("required_date_future", "La fecha requerida de entrega no puede estar en el pasado"));
+        }
+    }
+}
+
+fn check_auction_fields(
+    violations: &mut Vec<Violation>,
+    min_decrement: Option<Decimal>,
+    extension_minutes: Option<i32>,
+    rounds: Option<i32>,
+    round_minutes: Option<i32>,
+) {
+    if let Some(d) = min_decrement {
+        if d < Decimal::ZERO {
+            violations.push(Violation::new("auction_min_decrement", "El decremento mínimo no puede ser negativo"));
+        }
+    }
+    if let Some(m) = extension_minutes {
+        if !(0..=auction::MAX_EXTENSION_MINUTES).contains(&m) {
+            violations.push(Violation::new(
+                "auction_extension_minutes",
+                format!("La extensión automática debe estar entre 0 y {} minutos", auction::MAX_EXTENSION_MINUTES),
+            ));
+        }
+    }
+    if let Some(r) = rounds {
+        if !(1..=auction::MAX_ROUNDS).contains(&r) {
+            violations.push(Violation::new(
+                "auction_rounds",
+                format!("La subasta debe tener entre 1 y {} rondas", auction::MAX_ROUNDS),
+            ));
+        }
+    }
+    if let Some(m) = round_minutes {
+        if !(1..=auction::MAX_ROUND_MINUTES).contains(&m) {
+            violations.push(Violation::new(
+                "auction_round_minutes",
+                format!("Cada ronda adicional debe durar entre 1 y {} minutos", auction::MAX_ROUND_MINUTES),
+            ));
+        }
+    }
+}
+
+/// A currency that passed `check_fields` still needs an exchange rate on file.
+fn check_currency_known(conn: &mut PgConnection, code: Option<&str>) -> Option<HttpResponse> {
+    let code = code?;
+    match currency::is_known(conn, code) {
+        Ok(true) => None,
+        Ok(false) => Some(validation::rejected(vec![Violation::new(
+            "currency_known",
+            format!("La moneda {} no tiene tipo de cambio registrado", code),
+        )])),
+        Err(e) => Some(HttpResponse::InternalServerError().body(e.to_string())),
+    }
+}
 
 pub async fn create_request(
     pool: web::Data<DbPool>,
-    item: web::Json<NewRequest>,
+    events: web::Data<EventHub>,
+    actor: AuthUser,
+    item: web::Json<CreateRequestInput>,
 ) -> impl Responder {
+    let CreateRequestInput { request: mut new_req, lines, categories: category_ids } = item.into_inner();
+
+    let mut violations = Vec::new();
+    check_fields(
+        &mut violations,
+        Some(new_req.title.as_str()),
+        Some(new_req.deadline),
+        Some(new_req.quantity),
+        Some(new_req.currency.as_str()),
+        new_req.required_date,
+    );
+    check_auction_fields(
+        &mut violations,
+        Some(new_req.auction_min_decrement),
+        Some(new_req.auction_extension_minutes),
+        Some(new_req.auction_rounds),
+        Some(new_req.auction_round_minutes),
+    );
+    check_lines(&mut violations, &lines);
+    if new_req.status != RequestStatus::Draft && new_req.status != RequestStatus::Published {
+        violations.push(Violation::new("initial_status", "Una solicitud nueva debe crearse como 'draft' o 'published'"));
+    }
+    if !violations.is_empty() {
+        return validation::rejected(violations);
+    }
+
     let mut conn = pool.get().expect("couldn't get db connection from pool");
+    if let Some(rejection) = check_currency_known(&mut conn, Some(new_req.currency.as_str())) {
+        return rejection;
+    }
+    let cats = match categories::check_categories(&mut conn, &category_ids) {
+        Ok(c) => c,
+        Err(rejection) => return rejection,
+    };
+    if !cats.is_empty() {
+        new_req.tags = categories::tags_of(&cats);
+    }
 
-    let new_request = diesel::insert_into(requests::table)
-        .values(&item.into_inner())
-        .get_result::<Request>(&mut conn);
+    let new_request = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+        let req = diesel::insert_into(requests::table)
+            .values(&new_req)
+            .get_result::<Request>(conn)?;
+        let lines = insert_lines(conn, &req, lines)?;
+        categories::link_request(conn, req.id, &cats)?;
+        record_version(conn, &req, "Creación", Some(actor.id))?;
+        Ok(RequestDetail { request: req, lines, categories: cats, offer_count: None })
+    });
 
     match new_request {
-        Ok(req) => HttpResponse::Ok().json(req),
+        Ok(detail) => {
+            audit::record(&mut conn, &actor, "create_request", "request", Some(detail.request.id));
+            events.publish(Event::request(EventKind::RequestCreated, &detail.request));
+            categories::notify_subscribers(&pool, &mut conn, &detail.request);
+            HttpResponse::Ok().json(detail)
+        },
         Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
     }
 }
 
+const REQUEST_SORTS: &[&str] = &["id", "deadline", "title"];
+
+/// Which categories a request list is restricted to.
+enum CategoryScope {
+    Any,
+    Only(i32),
+    /// A supplier's categories, plus the requests nobody categorized
+    Served(Vec<i32>),
+}
+
+/// ILIKE pattern matching `text` anywhere, with `%`, `_` and `\` taken literally.
+fn contains_pattern(text: &str) -> String {
+    let mut pattern = String::from("%");
+    for c in text.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            pattern.push('\\');
+        }
+        pattern.push(c);
+    }
+    pattern.push('%');
+    pattern
+}
+
+/// `viewer` is the supplier listing the requests, who only sees the invite-only ones it was invited to.
+fn filtered_requests(query: &ListQuery, wanted: RequestStatus, scope: &CategoryScope, viewer: Option<i32>) -> requests::BoxedQuery<'static, Pg> {
+    let mut q = requests::table.filter(requests::status.eq(wanted)).into_boxed();
+    if let Some(supplier_id) = viewer {
+        let invited = request_invitations::table
+            .filter(request_invitations::supplier_id.eq(supplier_id))
+            .filter(request_invitations::status.ne(InvitationStatus::Declined))
+            .select(request_invitations::request_id);
+        q = q.filter(requests::invite_only.eq(false).or(requests::id.eq_any(invited)));
+    }
+    match scope {
+        CategoryScope::Any => {},
+        CategoryScope::Only(cat_id) => {
+            q = q.filter(requests::id.eq_any(
+                request_categories::table
+                    .filter(request_categories::category_id.eq(*cat_id))
+                    .select(request_categories::request_id),
+            ));
+        },
+        CategoryScope::Served(cat_ids) => {
+            let uncategorized = diesel::dsl::not(
+                requests::id.eq_any(request_categories::table.select(request_categories::request_id)),
+            );
+            q = q.filter(
+                requests::id
+                    .eq_any(
+                        request_categories::table
+                            .filter(request_categories::category_id.eq_any(cat_ids.clone()))
+                            .select(request_categories::request_id),
+                    )
+                    .or(uncategorized),
+            );
+        },
+    }
+    if let Some(tag) = &query.tag {
+        q = q.filter(requests::tags.ilike(contains_pattern(tag.trim())).escape('\\'));
+    }
+    if let Some(start) = query.starts_at() {
+        q = q.filter(requests::deadline.ge(start));
+    }
+    if let Some(end) = query.ends_before() {
+        q = q.filter(requests::deadline.lt(end));
+    }
+    q
+}
+
+/// Requests accepting offers, a page at a time. Staff may list other statuses with `status`;
+/// `from`/`to` filter on the deadline and `category` on one category. Suppliers that declared
+/// categories only get the requests in them unless they pass `all=true`.
 pub async fn list_requests(
     pool: web::Data<DbPool>,
+    actor: AuthUser,
+    query: web::Query<ListQuery>,
 ) -> impl Responder {
+    let query = query.into_inner();
+    let Some((sort, desc)) = query.sort(REQUEST_SORTS, "deadline") else {
+        return pagination::invalid_sort(REQUEST_SORTS);
+    };
+
+    let wanted = match query.status.as_deref() {
+        Some(s) if actor.is_staff() => match RequestStatus::parse(s) {
+            Some(st) => st,
+            None => return pagination::invalid_status(s),
+        },
+        _ => RequestStatus::Published,
+    };
+
     let mut conn = pool.get().expect("couldn't get db connection from pool");
-    use crate::db::schema::requests::dsl::*;
 
-    // Default to published or all, prompt says "lista abierta"
-    // ERP imports are 'open', legacy manual might be 'published'
-    let results = requests
-        .filter(status.eq("open").or(status.eq("published")))
+    let viewer = (!actor.is_staff()).then_some(actor.id);
+    let scope = match query.category {
+        Some(cat_id) => CategoryScope::Only(cat_id),
+        None if actor.is_staff() || query.all.unwrap_or(false) => CategoryScope::Any,
+        None => match categories::subscriptions(&mut conn, actor.id) {
+            Ok(ids) if ids.is_empty() => CategoryScope::Any,
+            Ok(ids) => CategoryScope::Served(ids),
+            Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+        },
+    };
+
+    let total = match filtered_requests(&query, wanted, &scope, viewer).count().get_result::<i64>(&mut conn) {
+        Ok(t) => t,
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    };
+
+    let ordered = match (sort, desc) {
+        ("id", false) => filtered_requests(&query, wanted, &scope, viewer).order(requests::id.asc()),
+        ("id", true) => filtered_requests(&query, wanted, &scope, viewer).order(requests::id.desc()),
+        ("title", false) => filtered_requests(&query, wanted, &scope, viewer).order(requests::title.asc()),
+        ("title", true) => filtered_requests(&query, wanted, &scope, viewer).order(requests::title.desc()),
+        (_, true) => filtered_requests(&query, wanted, &scope, viewer).order(requests::deadline.desc()),
+        (_, false) => filtered_requests(&query, wanted, &scope, viewer).order(requests::deadline.asc()),
+    };
+
+    let results = ordered
+        .then_order_by(requests::id.asc())
+        .limit(query.per_page())
+        .offset(query.offset())
         .load::<Request>(&mut conn);
 
+    let list = match results {
+        Ok(list) => list,
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    };
+
+    let ids: Vec<i32> = list.iter().map(|r| r.id).collect();
+    let all_lines = match load_lines(&mut conn, &ids) {
+        Ok(l) => l,
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    };
+    let all_categories = match categories::load_linked(&mut conn, &ids) {
+        Ok(c) => c,
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    };
+
+    let details: Vec<RequestDetail> = list
+        .into_iter()
+        .map(|request| {
+            let lines = all_lines.iter().filter(|l| l.request_id == request.id).cloned().collect();
+            let categories = all_categories
+                .iter()
+                .filter(|l| l.request_id == request.id)
+                .map(|l| l.category.clone())
+                .collect();
+            RequestDetail { request, lines, categories, offer_count: None }
+        })
+        .collect();
+
+    HttpResponse::Ok().json(Page::new(details, total, &query))
+}
+
+pub async fn get_request(
+    pool: web::Data<DbPool>,
+    actor: AuthUser,
+    path: web::Path<i32>,
+) -> impl Responder {
+    let req_id = path.into_inner();
+    let mut conn = pool.get().expect("couldn't get db connection from pool");
+
+    let request = match requests::table.find(req_id).first::<Request>(&mut conn) {
+        Ok(r) => r,
+        Err(diesel::result::Error::NotFound) => return HttpResponse::NotFound().body("Request not found"),
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    };
+
+    if actor.is_staff() {
+        let detail = offer_count(&mut conn, req_id)
+            .and_then(|count| with_lines(&mut conn, request, Some(count)));
+        return match detail {
+            Ok(d) => HttpResponse::Ok().json(d),
+            Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+        };
+    }
+
+    // Suppliers see published requests and the ones they already bid on
+    let has_bid: i64 = offers::table
+        .filter(offers::request_id.eq(req_id))
+        .filter(offers::supplier_id.eq(actor.id))
+        .count()
+        .get_result(&mut conn)
+        .unwrap_or(0);
+
+    if !request.status.is_bidding() && has_bid == 0 {
+        return HttpResponse::NotFound().body("Request not found");
+    }
+    // Invite-only requests stay hidden from suppliers that weren't invited
+    match invitations::can_see(&mut conn, &request, actor.id) {
+        Ok(true) => {},
+        Ok(false) if has_bid > 0 => {},
+        Ok(false) => return HttpResponse::NotFound().body("Request not found"),
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    }
+
+    match with_lines(&mut conn, request, None) {
+        Ok(d) => HttpResponse::Ok().json(d),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+/// Stores the current terms of `req`, lines included, as an immutable version snapshot.
+pub(crate) fn record_version(
+    conn: &mut PgConnection,
+    req: &Request,
+    reason: &str,
+    amended_by: Option<i32>,
+) -> QueryResult<()> {
+    let lines = request_lines::table
+        .filter(request_lines::request_id.eq(req.id))
+        .order(request_lines::line_no.asc())
+        .load::<RequestLine>(conn)?;
+
+    let snapshot = NewRequestVersion {
+        request_id: req.id,
+        version: req.version,
+        title: req.title.clone(),
+        description: req.description.clone(),
+        deadline: req.deadline,
+        quantity: req.quantity,
+        units: req.units.clone(),
+        tags: req.tags.clone(),
+        reason: reason.to_string(),
+        amended_by,
+        bidding_mode: req.bidding_mode,
+        currency: req.currency.clone(),
+        required_date: req.required_date,
+        auction_min_decrement: req.auction_min_decrement,
+        auction_extension_minutes: req.auction_extension_minutes,
+        lines: serde_json::to_string(&lines).unwrap_or_else(|_| "[]".to_string()),
+        auction_rounds: req.auction_rounds,
+        auction_round_minutes: req.auction_round_minutes,
+    };
+
+    diesel::insert_into(request_versions::table)
+        .values(&snapshot)
+        .execute(conn)
+        .map(|_| ())
+}
+
+#[derive(Deserialize)]
+pub struct AmendmentInput {
+    #[serde(flatten)]
+    pub changes: RequestChanges,
+    pub reason: String,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Revision {
+    /// Plain correction, only while nobody has bid yet
+    Edit,
+    /// Change of terms on a published request that already has offers
+    Amendment,
+}
+
+/// Applies `changes` as a new version of the request. Offers quoted against an older
+/// version are flagged for re-confirmation and their suppliers are notified.
+fn revise_request(
+    pool: &DbPool,
+    events: &EventHub,
+    actor: &AuthUser,
+    req_id: i32,
+    changes: RequestChanges,
+    reason: &str,
+    kind: Revision,
+) -> HttpResponse {
+    let mut violations = Vec::new();
+    check_fields(
+        &mut violations,
+        changes.title.as_deref(),
+        changes.deadline,
+        changes.quantity,
+        changes.currency.as_deref(),
+        changes.required_date,
+    );
+    check_auction_fields(
+        &mut violations,
+        changes.auction_min_decrement,
+        changes.auction_extension_minutes,
+        changes.auction_rounds,
+        changes.auction_round_minutes,
+    );
+    if changes.title.is_none()
+        && changes.description.is_none()
+        && changes.deadline.is_none()
+        && changes.quantity.is_none()
+        && changes.units.is_none()
+        && changes.tags.is_none()
+        && changes.bidding_mode.is_none()
+        && changes.currency.is_none()
+        && changes.required_date.is_none()
+        && changes.auction_min_decrement.is_none()
+        && changes.auction_extension_minutes.is_none()
+        && changes.auction_rounds.is_none()
+        && changes.auction_round_minutes.is_none()
+        && changes.lines.is_empty()
+    {
+        violations.push(Violation::new("no_changes", "No se enviaron cambios"));
+    }
+    check_line_changes(&mut violations, &changes.lines);
+    if kind == Revision::Amendment && reason.trim().is_empty() {
+        violations.push(Violation::new("reason_required", "Indica el motivo de la enmienda"));
+    }
+    if !violations.is_empty() {
+        return validation::rejected(violations);
+    }
+
+    let mut conn = pool.get().expect("couldn't get db connection from pool");
+    if let Some(rejection) = check_currency_known(&mut conn, changes.currency.as_deref()) {
+        return rejection;
+    }
+
+    // Lines are never added to or removed from a request, so this holds inside the transaction
+    let line_ids = match request_lines::table
+        .filter(request_lines::request_id.eq(req_id))
+        .select(request_lines::id)
+        .load::<i32>(&mut conn)
+    {
+        Ok(ids) => ids,
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    };
+    let foreign: Vec<Violation> = changes
+        .lines
+        .iter()
+        .filter(|c| !line_ids.contains(&c.id))
+        .map(|c| Violation::new("line_exists", format!("La partida {} no pertenece a la solicitud", c.id)))
+        .collect();
+    if !foreign.is_empty() {
+        return validation::rejected(foreign);
+    }
+
+    let result = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+        let current = requests::table
+            .find(req_id)
+            .for_update()
+            .first::<Request>(conn)?;
+        let existing_offers = offer_count(conn, req_id)?;
+
+        let mut blocked = Vec::new();
+        match kind {
+            Revision::Edit => {
+                if current.status != RequestStatus::Draft && current.status != RequestStatus::Published {
+                    blocked.push(Violation::new(
+                        "request_editable",
+                        format!("No se puede editar una solicitud en estado {}", current.status.as_str()),
+                    ));
+                }
+                if existing_offers > 0 {
+                    blocked.push(Violation::new("no_offers", "La solicitud ya tiene ofertas; publica una enmienda"));
+                }
+            },
+            Revision::Amendment => {
+                if current.status != RequestStatus::Published {
+                    blocked.push(Violation::new("request_published", "Solo se pueden enmendar solicitudes publicadas"));
+                }
+                // Offers already received were sealed and priced under these terms
+                if existing_offers > 0 {
+                    if changes.bidding_mode.is_some_and(|m| m != current.bidding_mode) {
+                        blocked.push(Violation::new("bidding_mode_fixed", "La modalidad no puede cambiar cuando ya hay ofertas"));
+                    }
+                    if changes.currency.as_ref().is_some_and(|c| *c != current.currency) {
+                        blocked.push(Violation::new("currency_fixed", "La moneda no puede cambiar cuando ya hay ofertas"));
+                    }
+                    if changes.auction_min_decrement.is_some_and(|d| d != current.auction_min_decrement)
+                        || changes.auction_extension_minutes.is_some_and(|m| m != current.auction_extension_minutes)
+                        || changes.auction_rounds.is_some_and(|r| r != current.auction_rounds)
+                        || changes.auction_round_minutes.is_some_and(|m| m != current.auction_round_minutes)
+                    {
+                        blocked.push(Violation::new("auction_terms_fixed", "Las reglas de la subasta no pueden cambiar cuando ya hay ofertas"));
+                    }
+                }
+            },
+        }
+        if !blocked.is_empty() {
+            return Ok(Err(blocked));
+        }
+
+        let mut updated = diesel::update(requests::table.find(req_id))
+            .set((&changes, requests::version.eq(current.version + 1)))
+            .get_result::<Request>(conn)?;
+
+        // Single-line requests keep their only line in step with the header
+        let single_line = match line_ids[..] {
+            [line_id] => Some(line_id),
+            _ => None,
+        };
+        if let Some(line_id) = single_line.filter(|_| changes.quantity.is_some() || changes.units.is_some()) {
+            diesel::update(request_lines::table.find(line_id))
+                .set((
+                    request_lines::quantity.eq(updated.quantity),
+                    request_lines::units.eq(&updated.units),
+                ))
+                .execute(conn)?;
+        }
+
+        for change in &changes.lines {
+            diesel::update(request_lines::table.find(change.id)).set(change).execute(conn)?;
+        }
+        // ...and the other way round when the line itself was changed
+        if let Some(line_id) = single_line.filter(|_| !changes.lines.is_empty()) {
+            let line = request_lines::table.find(line_id).first::<RequestLine>(conn)?;
+            updated = diesel::update(requests::table.find(req_id))
+                .set((requests::quantity.eq(line.quantity), requests::units.eq(&line.units)))
+                .get_result::<Request>(conn)?;
+        }
+
+        record_version(conn, &updated, reason, Some(actor.id))?;
+
+        let flagged_suppliers = diesel::update(
+            offers::table
+                .filter(offers::request_id.eq(req_id))
+                .filter(offers::status.eq(OfferStatus::Sent))
+                .filter(offers::request_version.lt(updated.version)),
+        )
+        .set(offers::needs_reconfirmation.eq(true))
+        .returning(offers::supplier_id)
+        .get_results::<i32>(conn)?;
+
+        Ok(Ok((updated, flagged_suppliers)))
+    });
+
+    match result {
+        Ok(Ok((req, flagged_suppliers))) => {
+            let action = if kind == Revision::Edit { "update_request" } else { "amend_request" };
+            audit::record(&mut conn, actor, action, "request", Some(req.id));
+            events.publish(Event::request(EventKind::RequestUpdated, &req));
+
+            let emails: Vec<String> = suppliers::table
+                .filter(suppliers::id.eq_any(&flagged_suppliers))
+                .select(suppliers::email)
+                .load(&mut conn)
+                .unwrap_or_default();
+
+            for to in &emails {
+                email_service::send_request_amended_email(pool, to, &req.title, req.version);
+            }
+
+            let count = offer_count(&mut conn, req.id).unwrap_or(0);
+            match with_lines(&mut conn, req, Some(count)) {
+                Ok(d) => HttpResponse::Ok().json(d),
+                Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+            }
+        },
+        Ok(Err(blocked)) => HttpResponse::Conflict().json(serde_json::json!({
+            "error": "request_locked",
+            "violations": blocked
+        })),
+        Err(diesel::result::Error::NotFound) => HttpResponse::NotFound().body("Request not found"),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+pub async fn update_request(
+    pool: web::Data<DbPool>,
+    events: web::Data<EventHub>,
+    actor: AuthUser,
+    path: web::Path<i32>,
+    item: web::Json<RequestChanges>,
+) -> impl Responder {
+    revise_request(&pool, &events, &actor, path.into_inner(), item.into_inner(), "Edición", Revision::Edit)
+}
+
+pub async fn amend_request(
+    pool: web::Data<DbPool>,
+    events: web::Data<EventHub>,
+    actor: AuthUser,
+    path: web::Path<i32>,
+    item: web::Json<AmendmentInput>,
+) -> impl Responder {
+    let input = item.into_inner();
+    revise_request(&pool, &events, &actor, path.into_inner(), input.changes, &input.reason, Revision::Amendment)
+}
+
+pub async fn list_versions(
+    pool: web::Data<DbPool>,
+    path: web::Path<i32>,
+) -> impl Responder {
+    let req_id = path.into_inner();
+    let mut conn = pool.get().expect("couldn't get db connection from pool");
+    use crate::db::schema::request_versions::dsl::*;
+
+    let results = request_versions
+        .filter(request_id.eq(req_id))
+        .order(version.asc())
+        .load::<RequestVersion>(&mut conn);
+
     match results {
         Ok(list) => HttpResponse::Ok().json(list),
         Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
     }
 }
+
+fn transition_request(pool: &DbPool, events: &EventHub, actor: &AuthUser, req_id: i32, target: RequestStatus) -> HttpResponse {
+    let mut conn = pool.get().expect("couldn't get db connection from pool");
+    use crate::db::schema::requests::dsl::*;
+
+    let current = match requests.find(req_id).select(status).first::<RequestStatus>(&mut conn) {
+        Ok(s) => s,
+        Err(diesel::result::Error::NotFound) => return HttpResponse::NotFound().body("Request not found"),
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    };
+
+    if !current.can_transition_to(target) {
+        return invalid_transition(current, target);
+    }
+
+    // Guard on the status we validated so a concurrent change can't be overwritten
+    let updated = diesel::update(requests.find(req_id).filter(status.eq(current)))
+        .set(status.eq(target))
+        .get_result::<Request>(&mut conn);
+
+    match updated {
+        Ok(req) => {
+            let action = format!("request_{}", target.as_str());
+            audit::record(&mut conn, actor, &action, "request", Some(req.id));
+            // Suppliers never saw the draft, so publishing it announces a new request
+            let kind = if current == RequestStatus::Draft { EventKind::RequestCreated } else { EventKind::RequestUpdated };
+            events.publish(Event::request(kind, &req));
+            if current == RequestStatus::Draft {
+                categories::notify_subscribers(pool, &mut conn, &req);
+                invitations::notify_invitees(pool, events, &mut conn, &req);
+            }
+            HttpResponse::Ok().json(req)
+        },
+        Err(diesel::result::Error::NotFound) => {
+            HttpResponse::Conflict().body("La solicitud cambió de estado, vuelve a intentarlo")
+        },
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+fn invalid_transition(from: RequestStatus, to: RequestStatus) -> HttpResponse {
+    HttpResponse::Conflict().json(serde_json::json!({
+        "error": "invalid_transition",
+        "from": from,
+        "to": to
+    }))
+}
+
+pub async fn publish_request(
+    pool: web::Data<DbPool>,
+    events: web::Data<EventHub>,
+    actor: AuthUser,
+    path: web::Path<i32>,
+) -> impl Responder {
+    transition_request(&pool, &events, &actor, path.into_inner(), RequestStatus::Published)
+}
+
+/// Manually ends bidding and moves the request to evaluation; sealed offers become visible.
+pub async fn close_request(
+    pool: web::Data<DbPool>,
+    events: web::Data<EventHub>,
+    actor: AuthUser,
+    path: web::Path<i32>,
+) -> impl Responder {
+    transition_request(&pool, &events, &actor, path.into_inner(), RequestStatus::InEvaluation)
+}
+
+pub async fn cancel_request(
+    pool: web::Data<DbPool>,
+    events: web::Data<EventHub>,
+    actor: AuthUser,
+    path: web::Path<i32>,
+) -> impl Responder {
+    transition_request(&pool, &events, &actor, path.into_inner(), RequestStatus::Cancelled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::test_support::rules;
+
+    #[test]
+    fn contains_pattern_escapes_like_wildcards() {
+        assert_eq!(contains_pattern("acero"), "%acero%");
+        assert_eq!(contains_pattern("100%"), "%100\\%%");
+        assert_eq!(contains_pattern("a_b\\c"), "%a\\_b\\\\c%");
+    }
+
+    fn line_change(id: i32, quantity: Option<i32>, units: Option<&str>) -> RequestLineChanges {
+        RequestLineChanges { id, quantity, units: units.map(str::to_string) }
+    }
+
+    #[test]
+    fn line_changes_need_a_valid_value() {
+        let mut violations = Vec::new();
+        check_line_changes(&mut violations, &[line_change(1, Some(5), None), line_change(2, None, Some("kg"))]);
+        assert!(violations.is_empty());
+
+        check_line_changes(&mut violations, &[
+            line_change(1, None, None),
+            line_change(2, Some(0), Some(" ")),
+            line_change(2, Some(3), None),
+        ]);
+        assert_eq!(rules(&violations), ["line_changes", "line_quantity_positive", "line_units_required", "line_unique"]);
+    }
+}
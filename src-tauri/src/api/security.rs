@@ -0,0 +1,313 @@
+use std::env;
+use std::future::{ready, Ready};
+use actix_web::{
+    body::{EitherBody, MessageBody},
+    dev::{Payload, ServiceRequest, ServiceResponse},
+    error::{ErrorForbidden, ErrorInternalServerError, ErrorUnauthorized},
+    http::header,
+    middleware::Next,
+    web, Error, FromRequest, HttpMessage, HttpRequest,
+};
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+use crate::db::{DbPool, models::{Supplier, User}};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Admin,
+    Buyer,
+    Supplier,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Admin => "admin",
+            Role::Buyer => "buyer",
+            Role::Supplier => "supplier",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Role> {
+        match value {
+            "admin" => Some(Role::Admin),
+            "buyer" => Some(Role::Buyer),
+            "supplier" => Some(Role::Supplier),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Claims {
+    pub sub: String,
+    pub uid: i32,
+    pub role: Role,
+    pub exp: usize,
+}
+
+/// Keys used to sign and verify session tokens.
+///
+/// New tokens are always signed with `JWT_SECRET`. Tokens signed with any of the
+/// comma separated keys in `JWT_PREVIOUS_SECRETS` are still accepted, so the secret
+/// can be rotated without logging everybody out.
+///
+/// Release builds refuse to start without `JWT_SECRET`; debug builds fall back to a random
+/// secret.
+pub struct JwtKeys {
+    current: String,
+    previous: Vec<String>,
+}
+
+impl JwtKeys {
+    pub fn from_env() -> Result<Self, String> {
+        let current = match env::var("JWT_SECRET") {
+            Ok(s) if !s.is_empty() => s,
+            _ if cfg!(debug_assertions) => {
+                eprintln!("Warning: JWT_SECRET not set. Using a random secret, sessions will not survive a restart.");
+                uuid::Uuid::new_v4().to_string()
+            }
+            _ => return Err("JWT_SECRET is not set; it is required to sign session tokens".to_string()),
+        };
+
+        let previous = env::var("JWT_PREVIOUS_SECRETS")
+            .unwrap_or_default()
+            .split(',')
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect();
+
+        Ok(JwtKeys { current, previous })
+    }
+
+    pub fn sign(&self, claims: &Claims) -> jsonwebtoken::errors::Result<String> {
+        encode(&Header::default(), claims, &EncodingKey::from_secret(self.current.as_bytes()))
+    }
+
+    pub fn verify(&self, token: &str) -> Option<Claims> {
+        let validation = Validation::default();
+
+        std::iter::once(&self.current)
+            .chain(self.previous.iter())
+            .find_map(|secret| {
+                decode::<Claims>(token, &DecodingKey::from_secret(secret.as_bytes()), &validation)
+                    .ok()
+                    .map(|data| data.claims)
+            })
+    }
+}
+
+/// The authenticated caller, resolved from the bearer token by the middleware below.
+#[derive(Debug, Clone)]
+pub struct AuthUser {
+    pub id: i32,
+    pub email: String,
+    pub role: Role,
+}
+
+impl AuthUser {
+    pub fn is_staff(&self) -> bool {
+        matches!(self.role, Role::Admin | Role::Buyer)
+    }
+
+    /// Staff may act on any supplier; a supplier only on itself.
+    pub fn can_access_supplier(&self, supplier_id: i32) -> bool {
+        self.is_staff() || (self.role == Role::Supplier && self.id == supplier_id)
+    }
+}
+
+impl FromRequest for AuthUser {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(
+            req.extensions()
+                .get::<AuthUser>()
+                .cloned()
+                .ok_or_else(|| ErrorUnauthorized("Sesión requerida")),
+        )
+    }
+}
+
+fn load_caller(conn: &mut PgConnection, uid: i32, role: Role) -> Result<AuthUser, Error> {
+    match role {
+        Role::Supplier => {
+            use crate::db::schema::suppliers::dsl::*;
+
+            let supplier = suppliers
+                .find(uid)
+                .first::<Supplier>(conn)
+                .map_err(|_| ErrorUnauthorized("User not found"))?;
+
+            if !supplier.active {
+                return Err(ErrorForbidden("Cuenta pendiente de aprobación por el administrador."));
+            }
+
+            Ok(AuthUser { id: supplier.id, email: supplier.email, role: Role::Supplier })
+        }
+        Role::Admin | Role::Buyer => {
+            use crate::db::schema::users::dsl::*;
+
+            let user = users
+                .find(uid)
+                .first::<User>(conn)
+                .map_err(|_| ErrorUnauthorized("User not found"))?;
+
+            if !user.active {
+                return Err(ErrorForbidden("Cuenta desactivada"));
+            }
+
+            // The stored role wins over the token so demotions apply immediately
+            let user_role = Role::parse(&user.role).ok_or_else(|| ErrorForbidden("Rol inválido"))?;
+            Ok(AuthUser { id: user.id, email: user.email, role: user_role })
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct StreamToken {
+    token: String,
+}
+
+/// The one route that takes its token from the query string.
+const EVENT_STREAM_PATH: &str = "/api/eventos";
+
+/// Bearer token of the request. Browsers can't set headers on an `EventSource`, so the event
+/// stream, and only the event stream, may pass it as `?token=` instead. Access logs leave the
+/// query string out, see `api::access_log`.
+fn bearer_token(req: &HttpRequest) -> Option<String> {
+    let from_header = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "));
+    if let Some(token) = from_header {
+        return Some(token.to_string());
+    }
+
+    let wants_stream = req
+        .headers()
+        .get(header::ACCEPT)
+        .and_then(|h| h.to_str().ok())
+        .is_some_and(|accept| accept.contains("text/event-stream"));
+    if req.path() != EVENT_STREAM_PATH || !wants_stream {
+        return None;
+    }
+    web::Query::<StreamToken>::from_query(req.query_string())
+        .ok()
+        .map(|q| q.into_inner().token)
+}
+
+fn authenticate(req: &HttpRequest) -> Result<AuthUser, Error> {
+    let token = bearer_token(req).ok_or_else(|| ErrorUnauthorized("Missing bearer token"))?;
+
+    let keys = req
+        .app_data::<web::Data<JwtKeys>>()
+        .ok_or_else(|| ErrorInternalServerError("JWT keys not configured"))?;
+
+    let claims = keys
+        .verify(&token)
+        .ok_or_else(|| ErrorUnauthorized("Invalid or expired token"))?;
+
+    let pool = req
+        .app_data::<web::Data<DbPool>>()
+        .ok_or_else(|| ErrorInternalServerError("Database pool not configured"))?;
+    let mut conn = pool.get().map_err(ErrorInternalServerError)?;
+
+    load_caller(&mut conn, claims.uid, claims.role)
+}
+
+/// Whether `user` would still get in as it did: the account exists, is active and keeps its
+/// role. Long-lived connections such as the event stream check it again from time to time.
+pub(crate) fn still_authorized(conn: &mut PgConnection, user: &AuthUser) -> bool {
+    load_caller(conn, user.id, user.role).is_ok_and(|current| current.role == user.role)
+}
+
+async fn authorize<B: MessageBody>(
+    req: ServiceRequest,
+    next: Next<B>,
+    allowed: &[Role],
+) -> Result<ServiceResponse<EitherBody<B>>, Error> {
+    // Nested scopes share the caller resolved by the outermost guard
+    let existing = req.extensions().get::<AuthUser>().cloned();
+    let user = match existing {
+        Some(u) => u,
+        None => match authenticate(req.request()) {
+            Ok(u) => u,
+            Err(e) => return Ok(req.error_response(e).map_into_right_body()),
+        },
+    };
+
+    if !allowed.contains(&user.role) {
+        return Ok(req
+            .error_response(ErrorForbidden("No tienes permisos para esta operación"))
+            .map_into_right_body());
+    }
+
+    req.extensions_mut().insert(user);
+    next.call(req).await.map(|res| res.map_into_left_body())
+}
+
+pub async fn authenticated(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    authorize(req, next, &[Role::Admin, Role::Buyer, Role::Supplier]).await
+}
+
+pub async fn staff_only(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    authorize(req, next, &[Role::Admin, Role::Buyer]).await
+}
+
+pub async fn admin_only(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    authorize(req, next, &[Role::Admin]).await
+}
+
+pub async fn supplier_only(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    authorize(req, next, &[Role::Supplier]).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    fn stream_request(uri: &str) -> HttpRequest {
+        TestRequest::get()
+            .uri(uri)
+            .insert_header((header::ACCEPT, "text/event-stream"))
+            .to_http_request()
+    }
+
+    #[test]
+    fn query_token_is_only_read_on_the_event_stream() {
+        assert_eq!(bearer_token(&stream_request("/api/eventos?token=abc")).as_deref(), Some("abc"));
+        assert_eq!(bearer_token(&stream_request("/api/ofertas/1?token=abc")), None);
+        assert_eq!(bearer_token(&stream_request("/api/eventos/?token=abc")), None);
+
+        let plain = TestRequest::get().uri("/api/eventos?token=abc").to_http_request();
+        assert_eq!(bearer_token(&plain), None);
+    }
+
+    #[test]
+    fn header_token_works_on_any_route() {
+        let req = TestRequest::get()
+            .uri("/api/ofertas/1")
+            .insert_header((header::AUTHORIZATION, "Bearer abc"))
+            .to_http_request();
+        assert_eq!(bearer_token(&req).as_deref(), Some("abc"));
+    }
+}
@@ -1,4 +1,5 @@
 use actix_web::{web, HttpResponse, Responder};
+use crate::api::security::AuthUser;
 use crate::db::{DbPool, schema::suppliers};
 use diesel::prelude::*;
 use serde::Deserialize;
@@ -8,16 +9,15 @@ pub struct UpdateDocsInput {
     pub documents: String,
 }
 
-pub async fn update_docs(
-    pool: web::Data<DbPool>,
-    path: web::Path<i32>,
-    item: web::Json<UpdateDocsInput>,
-) -> impl Responder {
+fn forbidden() -> HttpResponse {
+    HttpResponse::Forbidden().body("No tienes acceso a este proveedor")
+}
+
+fn save_documents(pool: &DbPool, supplier_id: i32, documents: &str) -> HttpResponse {
     let mut conn = pool.get().expect("couldn't get db connection from pool");
-    let supplier_id = path.into_inner();
 
     let res = diesel::update(suppliers::table.filter(suppliers::id.eq(supplier_id)))
-        .set(suppliers::documents.eq(&item.documents))
+        .set(suppliers::documents.eq(documents))
         .execute(&mut conn);
 
     match res {
@@ -26,19 +26,58 @@ pub async fn update_docs(
     }
 }
 
-pub async fn get_supplier(
-    pool: web::Data<DbPool>,
-    path: web::Path<i32>,
-) -> impl Responder {
+fn supplier_profile(pool: &DbPool, supplier_id: i32) -> HttpResponse {
     let mut conn = pool.get().expect("couldn't get db connection from pool");
-    let supplier_id = path.into_inner();
 
-    let res = crate::db::schema::suppliers::table
+    let res = suppliers::table
         .find(supplier_id)
         .first::<crate::db::models::Supplier>(&mut conn);
 
     match res {
         Ok(s) => HttpResponse::Ok().json(s),
+        Err(diesel::result::Error::NotFound) => HttpResponse::NotFound().body("Supplier not found"),
         Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
     }
 }
+
+pub async fn update_docs(
+    pool: web::Data<DbPool>,
+    actor: AuthUser,
+    path: web::Path<i32>,
+    item: web::Json<UpdateDocsInput>,
+) -> impl Responder {
+    let supplier_id = path.into_inner();
+    if !actor.can_access_supplier(supplier_id) {
+        return forbidden();
+    }
+
+    save_documents(&pool, supplier_id, &item.documents)
+}
+
+pub async fn get_supplier(
+    pool: web::Data<DbPool>,
+    actor: AuthUser,
+    path: web::Path<i32>,
+) -> impl Responder {
+    let supplier_id = path.into_inner();
+    if !actor.can_access_supplier(supplier_id) {
+        return forbidden();
+    }
+
+    supplier_profile(&pool, supplier_id)
+}
+
+pub async fn my_profile(
+    pool: web::Data<DbPool>,
+    actor: AuthUser,
+) -> impl Responder {
+    supplier_profile(&pool, actor.id)
+}
+
+pub async fn update_my_docs(
+    pool: web::Data<DbPool>,
+    actor: AuthUser,
+    item: web::Json<UpdateDocsInput>,
+) -> impl Responder {
+    save_documents(&pool, actor.id, &item.documents)
+}
@@ -0,0 +1,13 @@
+//! Helpers shared by the unit tests of the API modules.
+use rust_decimal::Decimal;
+use crate::api::validation::Violation;
+
+/// Parses a decimal literal, e.g. `dec("12.50")`.
+pub(crate) fn dec(value: &str) -> Decimal {
+    value.parse().unwrap()
+}
+
+/// The rule of each violation, in order.
+pub(crate) fn rules(violations: &[Violation]) -> Vec<&'static str> {
+    violations.iter().map(|v| v.rule).collect()
+}
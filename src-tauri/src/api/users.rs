@@ -0,0 +1,146 @@
+use actix_web::{web, HttpResponse, Responder};
+use serde::Deserialize;
+use crate::api::{audit, pagination::{self, ListQuery, Page}, security::{AuthUser, Role}};
+use crate::db::{DbPool, models::{NewUser, User}, schema::users};
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+
+#[derive(Deserialize)]
+pub struct CreateUserInput {
+    pub name: String,
+    pub email: String,
+    pub password: String,
+    pub role: String,
+}
+
+#[derive(Deserialize)]
+pub struct UserActiveInput {
+    pub active: bool,
+}
+
+const USER_SORTS: &[&str] = &["name", "email", "created_at"];
+
+pub async fn list_users(
+    pool: web::Data<DbPool>,
+    query: web::Query<ListQuery>,
+) -> impl Responder {
+    let query = query.into_inner();
+    let Some((sort, desc)) = query.sort(USER_SORTS, "name") else {
+        return pagination::invalid_sort(USER_SORTS);
+    };
+    let mut conn = pool.get().expect("couldn't get db connection from pool");
+
+    let total = match users::table.count().get_result::<i64>(&mut conn) {
+        Ok(t) => t,
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    };
+
+    let q = users::table.into_boxed();
+    let ordered = match (sort, desc) {
+        ("email", false) => q.order(users::email.asc()),
+        ("email", true) => q.order(users::email.desc()),
+        ("created_at", false) => q.order(users::created_at.asc()),
+        ("created_at", true) => q.order(users::created_at.desc()),
+        (_, false) => q.order(users::name.asc()),
+        (_, true) => q.order(users::name.desc()),
+    };
+
+    let results = ordered
+        .then_order_by(users::id.asc())
+        .limit(query.per_page())
+        .offset(query.offset())
+        .load::<User>(&mut conn);
+
+    match results {
+        Ok(list) => HttpResponse::Ok().json(Page::new(list, total, &query)),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+pub async fn create_user(
+    pool: web::Data<DbPool>,
+    actor: AuthUser,
+    item: web::Json<CreateUserInput>,
+) -> impl Responder {
+    match Role::parse(&item.role) {
+        Some(Role::Admin) | Some(Role::Buyer) => {},
+        _ => return HttpResponse::BadRequest().body("El rol debe ser 'admin' o 'buyer'"),
+    }
+
+    let hashed = match bcrypt::hash(&item.password, bcrypt::DEFAULT_COST) {
+        Ok(h) => h,
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    };
+
+    let new_user = NewUser {
+        name: item.name.clone(),
+        email: item.email.clone(),
+        password_hash: hashed,
+        role: item.role.clone(),
+        active: true,
+    };
+
+    let mut conn = pool.get().expect("couldn't get db connection from pool");
+
+    let res = diesel::insert_into(users::table)
+        .values(&new_user)
+        .get_result::<User>(&mut conn);
+
+    match res {
+        Ok(u) => {
+            audit::record(&mut conn, &actor, "create_user", "user", Some(u.id));
+            HttpResponse::Ok().json(u)
+        },
+        Err(diesel::result::Error::DatabaseError(diesel::result::DatabaseErrorKind::UniqueViolation, _)) => {
+            HttpResponse::Conflict().body("El correo ya está registrado.")
+        },
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+/// Whether deactivating or demoting `user_id` would leave no active admin. Locks the active
+/// admins, so call it in the transaction that makes the change.
+fn leaves_no_active_admin(conn: &mut PgConnection, user_id: i32) -> QueryResult<bool> {
+    let admins: Vec<i32> = users::table
+        .filter(users::role.eq(Role::Admin.as_str()))
+        .filter(users::active.eq(true))
+        .select(users::id)
+        .for_update()
+        .load(conn)?;
+    Ok(admins == [user_id])
+}
+
+pub async fn set_user_active(
+    pool: web::Data<DbPool>,
+    actor: AuthUser,
+    path: web::Path<i32>,
+    item: web::Json<UserActiveInput>,
+) -> impl Responder {
+    let user_id = path.into_inner();
+    if user_id == actor.id && !item.active {
+        return HttpResponse::BadRequest().body("No puedes desactivar tu propia cuenta");
+    }
+
+    let mut conn = pool.get().expect("couldn't get db connection from pool");
+
+    let updated = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+        if !item.active && leaves_no_active_admin(conn, user_id)? {
+            return Ok(None);
+        }
+        diesel::update(users::table.find(user_id))
+            .set(users::active.eq(item.active))
+            .get_result::<User>(conn)
+            .map(Some)
+    });
+
+    match updated {
+        Ok(None) => HttpResponse::Conflict().body("Debe quedar al menos un administrador activo"),
+        Ok(Some(u)) => {
+            let action = if u.active { "activate_user" } else { "deactivate_user" };
+            audit::record(&mut conn, &actor, action, "user", Some(u.id));
+            HttpResponse::Ok().json(u)
+        },
+        Err(diesel::result::Error::NotFound) => HttpResponse::NotFound().body("User not found"),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
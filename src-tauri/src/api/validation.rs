@@ -0,0 +1,23 @@
+use actix_web::HttpResponse;
+use serde::Serialize;
+
+/// A single business rule broken by a request payload.
+#[derive(Serialize, Debug)]
+pub struct Violation {
+    pub rule: &'static str,
+    pub message: String,
+}
+
+impl Violation {
+    pub fn new(rule: &'static str, message: impl Into<String>) -> Self {
+        Violation { rule, message: message.into() }
+    }
+}
+
+/// 422 response listing every violated rule so clients can show them all at once.
+pub fn rejected(violations: Vec<Violation>) -> HttpResponse {
+    HttpResponse::UnprocessableEntity().json(serde_json::json!({
+        "error": "validation_failed",
+        "violations": violations
+    }))
+}
@@ -1,7 +1,10 @@
 pub mod schema;
 pub mod models;
+pub mod status;
 
+use std::env;
 use diesel::pg::PgConnection;
+use diesel::prelude::*;
 use diesel::r2d2::{ConnectionManager, Pool, PooledConnection};
 use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
 
@@ -23,3 +26,47 @@ pub fn establish_connection(database_url: &str) -> DbPool {
 
     pool
 }
+
+/// Creates the first administrator from `PORTAL_ADMIN_EMAIL` / `PORTAL_ADMIN_PASSWORD`
+/// (and optionally `PORTAL_ADMIN_NAME`) when no admin account exists yet.
+pub fn bootstrap_admin(pool: &DbPool) {
+    let admin_email = env::var("PORTAL_ADMIN_EMAIL").unwrap_or_default();
+    let admin_password = env::var("PORTAL_ADMIN_PASSWORD").unwrap_or_default();
+    if admin_email.is_empty() || admin_password.is_empty() {
+        return;
+    }
+
+    let mut conn = pool.get().expect("couldn't get db connection from pool");
+    use schema::users::dsl::*;
+
+    let admins: i64 = users
+        .filter(role.eq("admin"))
+        .count()
+        .get_result(&mut conn)
+        .unwrap_or(0);
+
+    if admins > 0 {
+        return;
+    }
+
+    let hashed = match bcrypt::hash(&admin_password, bcrypt::DEFAULT_COST) {
+        Ok(h) => h,
+        Err(e) => {
+            eprintln!("Error hashing admin password: {}", e);
+            return;
+        }
+    };
+
+    let new_admin = models::NewUser {
+        name: env::var("PORTAL_ADMIN_NAME").unwrap_or_else(|_| "Administrador".to_string()),
+        email: admin_email.clone(),
+        password_hash: hashed,
+        role: "admin".to_string(),
+        active: true,
+    };
+
+    match diesel::insert_into(users).values(&new_admin).execute(&mut conn) {
+        Ok(_) => println!("Initial admin account created for {}", admin_email),
+        Err(e) => eprintln!("Error creating initial admin account: {}", e),
+    }
+}
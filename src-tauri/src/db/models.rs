@@ -1,7 +1,9 @@
 use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
-use crate::db::schema::{suppliers, requests, offers};
-use chrono::NaiveDateTime;
+use crate::db::schema::{suppliers, requests, offers, users, audit_log, request_versions, request_lines, offer_lines, exchange_rates, tax_settings, scoring_weights, offer_evaluations, offer_revisions, categories, request_categories, supplier_categories, request_invitations, request_questions, supplier_documents, uploads};
+use chrono::{NaiveDate, NaiveDateTime};
+use rust_decimal::Decimal;
+use crate::db::status::{AnswerVisibility, BiddingMode, DocumentType, InvitationStatus, OfferStatus, RequestStatus, ReviewStatus};
 
 #[derive(Queryable, Serialize, Deserialize, Debug, Clone)]
 pub struct Supplier {
@@ -46,8 +48,20 @@ pub struct Request {
     pub quantity: i32,
     pub units: String,
     pub tags: String,
-    pub status: String,
+    pub status: RequestStatus,
     pub origin_erp: String,
+    pub bidding_mode: BiddingMode,
+    pub version: i32,
+    pub currency: String,
+    pub awarded_evaluation_id: Option<i32>,
+    pub required_date: Option<NaiveDate>,
+    pub auction_min_decrement: Decimal,
+    pub auction_extension_minutes: i32,
+    pub invite_only: bool,
+    pub auction_rounds: i32,
+    pub auction_round_minutes: i32,
+    /// Round of the auction taking bids, from 1 to `auction_rounds`
+    pub auction_round: i32,
 }
 
 #[derive(Insertable, Deserialize, Debug)]
@@ -58,9 +72,84 @@ pub struct NewRequest {
     pub deadline: NaiveDateTime,
     pub quantity: i32,
     pub units: String,
+    #[serde(default)]
     pub tags: String,
-    pub status: String,
+    #[serde(default = "default_request_status")]
+    pub status: RequestStatus,
     pub origin_erp: String,
+    #[serde(default = "default_bidding_mode")]
+    pub bidding_mode: BiddingMode,
+    #[serde(default = "default_currency")]
+    pub currency: String,
+    #[serde(default)]
+    pub required_date: Option<NaiveDate>,
+    #[serde(default)]
+    pub auction_min_decrement: Decimal,
+    #[serde(default = "default_extension_minutes")]
+    pub auction_extension_minutes: i32,
+    /// Only invited suppliers can see the request and bid on it
+    #[serde(default)]
+    pub invite_only: bool,
+    #[serde(default = "default_auction_rounds")]
+    pub auction_rounds: i32,
+    #[serde(default = "default_round_minutes")]
+    pub auction_round_minutes: i32,
+}
+
+/// Partial edit of a request; fields left out of the JSON body are not touched.
+#[derive(AsChangeset, Deserialize, Debug)]
+#[diesel(table_name = requests)]
+pub struct RequestChanges {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub deadline: Option<NaiveDateTime>,
+    pub quantity: Option<i32>,
+    pub units: Option<String>,
+    pub tags: Option<String>,
+    pub bidding_mode: Option<BiddingMode>,
+    pub currency: Option<String>,
+    pub required_date: Option<NaiveDate>,
+    pub auction_min_decrement: Option<Decimal>,
+    pub auction_extension_minutes: Option<i32>,
+    pub auction_rounds: Option<i32>,
+    pub auction_round_minutes: Option<i32>,
+    /// Changes to individual lines, applied after the header
+    #[serde(default)]
+    #[diesel(skip_update)]
+    pub lines: Vec<RequestLineChanges>,
+}
+
+/// Partial edit of one line of a request, by line id.
+#[derive(AsChangeset, Deserialize, Debug)]
+#[diesel(table_name = request_lines)]
+pub struct RequestLineChanges {
+    pub id: i32,
+    pub quantity: Option<i32>,
+    pub units: Option<String>,
+}
+
+fn default_request_status() -> RequestStatus {
+    RequestStatus::Published
+}
+
+fn default_bidding_mode() -> BiddingMode {
+    BiddingMode::Open
+}
+
+fn default_currency() -> String {
+    "MXN".to_string()
+}
+
+fn default_extension_minutes() -> i32 {
+    5
+}
+
+fn default_auction_rounds() -> i32 {
+    1
+}
+
+fn default_round_minutes() -> i32 {
+    10
 }
 
 #[derive(Queryable, Serialize, Deserialize, Debug, Clone)]
@@ -68,26 +157,48 @@ pub struct Offer {
     pub id: i32,
     pub supplier_id: i32,
     pub request_id: i32,
-    pub price: f64,
+    pub price: Decimal,
     pub delivery_time: String,
     pub conditions: String,
     pub attachments: String,
     pub photo: Option<String>,
-    pub status: String,
+    pub status: OfferStatus,
     pub created_at: NaiveDateTime,
+    pub request_version: i32,
+    pub needs_reconfirmation: bool,
+    pub currency: String,
+    pub tax_rate: Decimal,
+    pub tax_amount: Decimal,
+    pub withholding_rate: Decimal,
+    pub withholding_amount: Decimal,
+    pub total: Decimal,
+    pub delivery_days: Option<i32>,
+    pub promised_date: Option<NaiveDate>,
+    pub revision: i32,
+    pub updated_at: NaiveDateTime,
 }
 
-#[derive(Insertable, Deserialize, Debug)]
-#[diesel(table_name = offers)]
+/// Values of a new offer, also used to overwrite an offer with a revision.
+#[derive(Insertable, AsChangeset, Debug)]
+#[diesel(table_name = offers, treat_none_as_null = true)]
 pub struct NewOffer {
     pub supplier_id: i32,
     pub request_id: i32,
-    pub price: f64,
+    pub price: Decimal,
     pub delivery_time: String,
     pub conditions: String,
     pub attachments: String,
     pub photo: Option<String>,
-    pub status: String,
+    pub status: OfferStatus,
+    pub request_version: i32,
+    pub currency: String,
+    pub tax_rate: Decimal,
+    pub tax_amount: Decimal,
+    pub withholding_rate: Decimal,
+    pub withholding_amount: Decimal,
+    pub total: Decimal,
+    pub delivery_days: Option<i32>,
+    pub promised_date: Option<NaiveDate>,
 }
 
 #[derive(Queryable, Serialize, Deserialize, Debug, Clone)]
@@ -113,3 +224,365 @@ pub struct UpdateEmailConfig {
     pub ui_theme: String,
     pub login_image_url: String,
 }
+
+#[derive(Queryable, Serialize, Deserialize, Debug, Clone)]
+pub struct User {
+    pub id: i32,
+    pub name: String,
+    pub email: String,
+    #[serde(skip_serializing)]
+    pub password_hash: String,
+    pub role: String,
+    pub active: bool,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = users)]
+pub struct NewUser {
+    pub name: String,
+    pub email: String,
+    pub password_hash: String,
+    pub role: String,
+    pub active: bool,
+}
+
+#[derive(Queryable, Serialize, Deserialize, Debug, Clone)]
+pub struct AuditEntry {
+    pub id: i32,
+    pub user_id: i32,
+    pub action: String,
+    pub entity: String,
+    pub entity_id: Option<i32>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = audit_log)]
+pub struct NewAuditEntry {
+    pub user_id: i32,
+    pub action: String,
+    pub entity: String,
+    pub entity_id: Option<i32>,
+}
+
+#[derive(Queryable, Serialize, Deserialize, Debug, Clone)]
+pub struct RequestVersion {
+    pub id: i32,
+    pub request_id: i32,
+    pub version: i32,
+    pub title: String,
+    pub description: String,
+    pub deadline: NaiveDateTime,
+    pub quantity: i32,
+    pub units: String,
+    pub tags: String,
+    pub reason: String,
+    pub amended_by: Option<i32>,
+    pub created_at: NaiveDateTime,
+    pub bidding_mode: BiddingMode,
+    pub currency: String,
+    pub required_date: Option<NaiveDate>,
+    pub auction_min_decrement: Decimal,
+    pub auction_extension_minutes: i32,
+    /// JSON array of the request lines at this version
+    pub lines: String,
+    pub auction_rounds: i32,
+    pub auction_round_minutes: i32,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = request_versions)]
+pub struct NewRequestVersion {
+    pub request_id: i32,
+    pub version: i32,
+    pub title: String,
+    pub description: String,
+    pub deadline: NaiveDateTime,
+    pub quantity: i32,
+    pub units: String,
+    pub tags: String,
+    pub reason: String,
+    pub amended_by: Option<i32>,
+    pub bidding_mode: BiddingMode,
+    pub currency: String,
+    pub required_date: Option<NaiveDate>,
+    pub auction_min_decrement: Decimal,
+    pub auction_extension_minutes: i32,
+    pub lines: String,
+    pub auction_rounds: i32,
+    pub auction_round_minutes: i32,
+}
+
+#[derive(Queryable, Serialize, Deserialize, Debug, Clone)]
+pub struct RequestLine {
+    pub id: i32,
+    pub request_id: i32,
+    pub line_no: i32,
+    pub description: String,
+    pub quantity: i32,
+    pub units: String,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = request_lines)]
+pub struct NewRequestLine {
+    pub request_id: i32,
+    pub line_no: i32,
+    pub description: String,
+    pub quantity: i32,
+    pub units: String,
+}
+
+/// Price and delivery quoted by an offer for one line of the request.
+/// `price` is the line total, i.e. `unit_price` times the requested quantity.
+#[derive(Queryable, Serialize, Deserialize, Debug, Clone)]
+pub struct OfferLine {
+    pub id: i32,
+    pub offer_id: i32,
+    pub request_line_id: i32,
+    pub price: Decimal,
+    pub delivery_time: String,
+    pub status: OfferStatus,
+    pub unit_price: Decimal,
+    pub delivery_days: Option<i32>,
+    pub promised_date: Option<NaiveDate>,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = offer_lines)]
+pub struct NewOfferLine {
+    pub offer_id: i32,
+    pub request_line_id: i32,
+    pub price: Decimal,
+    pub delivery_time: String,
+    pub status: OfferStatus,
+    pub unit_price: Decimal,
+    pub delivery_days: Option<i32>,
+    pub promised_date: Option<NaiveDate>,
+}
+
+/// Value of one unit of `currency` in the base currency (MXN) from `effective_date` on.
+#[derive(Queryable, Serialize, Deserialize, Debug, Clone)]
+pub struct ExchangeRate {
+    pub id: i32,
+    pub currency: String,
+    pub rate: Decimal,
+    pub effective_date: NaiveDate,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable, AsChangeset, Deserialize, Debug)]
+#[diesel(table_name = exchange_rates)]
+pub struct NewExchangeRate {
+    pub currency: String,
+    pub rate: Decimal,
+    pub effective_date: NaiveDate,
+}
+
+/// Tax rates applied to new offers, as fractions (0.16 = 16%).
+#[derive(Queryable, Serialize, Deserialize, Debug, Clone)]
+pub struct TaxSettings {
+    pub id: i32,
+    pub iva_rate: Decimal,
+    pub withholding_rate: Decimal,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(AsChangeset, Deserialize, Debug)]
+#[diesel(table_name = tax_settings)]
+pub struct UpdateTaxSettings {
+    pub iva_rate: Decimal,
+    pub withholding_rate: Decimal,
+}
+
+#[derive(Queryable, Serialize, Deserialize, Debug, Clone)]
+pub struct ScoringWeights {
+    pub id: i32,
+    pub price: f64,
+    pub delivery: f64,
+    pub compliance: f64,
+    pub experience: f64,
+}
+
+#[derive(AsChangeset, Serialize, Deserialize, Debug, Clone, Default)]
+#[diesel(table_name = scoring_weights)]
+pub struct UpdateScoringWeights {
+    pub price: f64,
+    pub delivery: f64,
+    pub compliance: f64,
+    pub experience: f64,
+}
+
+#[derive(Queryable, Debug, Clone)]
+pub struct OfferEvaluation {
+    pub id: i32,
+    pub request_id: i32,
+    pub weights: String,
+    pub ranking: String,
+    pub created_by: i32,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = offer_evaluations)]
+pub struct NewOfferEvaluation {
+    pub request_id: i32,
+    pub weights: String,
+    pub ranking: String,
+    pub created_by: i32,
+}
+
+/// One immutable revision of an offer. `lines` holds the quoted lines as JSON.
+#[derive(Queryable, Serialize, Debug, Clone)]
+pub struct OfferRevision {
+    pub id: i32,
+    pub offer_id: i32,
+    pub revision: i32,
+    pub status: OfferStatus,
+    pub price: Decimal,
+    pub currency: String,
+    pub tax_amount: Decimal,
+    pub withholding_amount: Decimal,
+    pub total: Decimal,
+    pub delivery_days: Option<i32>,
+    pub promised_date: Option<NaiveDate>,
+    pub delivery_time: String,
+    pub conditions: String,
+    pub attachments: String,
+    pub photo: Option<String>,
+    #[serde(skip_serializing)]
+    pub lines: String,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = offer_revisions)]
+pub struct NewOfferRevision {
+    pub offer_id: i32,
+    pub revision: i32,
+    pub status: OfferStatus,
+    pub price: Decimal,
+    pub currency: String,
+    pub tax_amount: Decimal,
+    pub withholding_amount: Decimal,
+    pub total: Decimal,
+    pub delivery_days: Option<i32>,
+    pub promised_date: Option<NaiveDate>,
+    pub delivery_time: String,
+    pub conditions: String,
+    pub attachments: String,
+    pub photo: Option<String>,
+    pub lines: String,
+}
+
+#[derive(Queryable, Serialize, Deserialize, Debug, Clone)]
+pub struct Category {
+    pub id: i32,
+    pub slug: String,
+    pub name: String,
+    pub active: bool,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = categories)]
+pub struct NewCategory {
+    pub slug: String,
+    pub name: String,
+    pub active: bool,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = request_categories)]
+pub struct RequestCategory {
+    pub request_id: i32,
+    pub category_id: i32,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = supplier_categories)]
+pub struct SupplierCategory {
+    pub supplier_id: i32,
+    pub category_id: i32,
+}
+
+#[derive(Queryable, Serialize, Deserialize, Debug, Clone)]
+pub struct RequestInvitation {
+    pub id: i32,
+    pub request_id: i32,
+    pub supplier_id: i32,
+    pub status: InvitationStatus,
+    pub invited_by: Option<i32>,
+    pub created_at: NaiveDateTime,
+    pub responded_at: Option<NaiveDateTime>,
+    pub notified_at: Option<NaiveDateTime>,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = request_invitations)]
+pub struct NewRequestInvitation {
+    pub request_id: i32,
+    pub supplier_id: i32,
+    pub invited_by: Option<i32>,
+}
+
+#[derive(Queryable, Serialize, Deserialize, Debug, Clone)]
+pub struct RequestQuestion {
+    pub id: i32,
+    pub request_id: i32,
+    pub supplier_id: Option<i32>,
+    pub question: String,
+    pub public_question: Option<String>,
+    pub answer: Option<String>,
+    pub visibility: Option<AnswerVisibility>,
+    pub answered_by: Option<i32>,
+    pub created_at: NaiveDateTime,
+    pub answered_at: Option<NaiveDateTime>,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = request_questions)]
+pub struct NewRequestQuestion {
+    pub request_id: i32,
+    pub supplier_id: Option<i32>,
+    pub question: String,
+}
+
+#[derive(Queryable, Serialize, Deserialize, Debug, Clone)]
+pub struct SupplierDocument {
+    pub id: i32,
+    pub supplier_id: i32,
+    pub doc_type: DocumentType,
+    pub file: String,
+    pub issued_on: Option<NaiveDate>,
+    pub expires_on: Option<NaiveDate>,
+    pub review_status: ReviewStatus,
+    pub review_notes: String,
+    pub reviewed_by: Option<i32>,
+    pub reviewed_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+    /// Smallest expiry reminder already emailed, in days before `expires_on`
+    pub reminder_days: Option<i32>,
+    /// When the scheduler handled the document passing `expires_on`
+    pub lapsed_at: Option<NaiveDateTime>,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = supplier_documents)]
+pub struct NewSupplierDocument {
+    pub supplier_id: i32,
+    pub doc_type: DocumentType,
+    pub file: String,
+    pub issued_on: Option<NaiveDate>,
+    pub expires_on: Option<NaiveDate>,
+}
+
+/// Who uploaded a file: `supplier_id` for suppliers, `user_id` for staff.
+#[derive(Insertable, Debug)]
+#[diesel(table_name = uploads)]
+pub struct NewUpload {
+    pub file: String,
+    pub supplier_id: Option<i32>,
+    pub user_id: Option<i32>,
+}
@@ -40,6 +40,17 @@ diesel::table! {
         tags -> Text,
         status -> Varchar,
         origin_erp -> Varchar,
+        bidding_mode -> Varchar,
+        version -> Int4,
+        currency -> Varchar,
+        awarded_evaluation_id -> Nullable<Int4>,
+        required_date -> Nullable<Date>,
+        auction_min_decrement -> Numeric,
+        auction_extension_minutes -> Int4,
+        invite_only -> Bool,
+        auction_rounds -> Int4,
+        auction_round_minutes -> Int4,
+        auction_round -> Int4,
     }
 }
 
@@ -48,22 +59,282 @@ diesel::table! {
         id -> Int4,
         supplier_id -> Int4,
         request_id -> Int4,
+        price -> Numeric,
+        delivery_time -> Varchar,
+        conditions -> Text,
+        attachments -> Text,
+        photo -> Nullable<Text>,
+        status -> Varchar,
+        created_at -> Timestamp,
+        request_version -> Int4,
+        needs_reconfirmation -> Bool,
+        currency -> Varchar,
+        tax_rate -> Numeric,
+        tax_amount -> Numeric,
+        withholding_rate -> Numeric,
+        withholding_amount -> Numeric,
+        total -> Numeric,
+        delivery_days -> Nullable<Int4>,
+        promised_date -> Nullable<Date>,
+        revision -> Int4,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    request_versions (id) {
+        id -> Int4,
+        request_id -> Int4,
+        version -> Int4,
+        title -> Varchar,
+        description -> Text,
+        deadline -> Timestamp,
+        quantity -> Int4,
+        units -> Varchar,
+        tags -> Text,
+        reason -> Text,
+        amended_by -> Nullable<Int4>,
+        created_at -> Timestamp,
+        bidding_mode -> Varchar,
+        currency -> Varchar,
+        required_date -> Nullable<Date>,
+        auction_min_decrement -> Numeric,
+        auction_extension_minutes -> Int4,
+        lines -> Text,
+        auction_rounds -> Int4,
+        auction_round_minutes -> Int4,
+    }
+}
+
+diesel::table! {
+    users (id) {
+        id -> Int4,
+        name -> Varchar,
+        email -> Varchar,
+        password_hash -> Varchar,
+        role -> Varchar,
+        active -> Bool,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    audit_log (id) {
+        id -> Int4,
+        user_id -> Int4,
+        action -> Varchar,
+        entity -> Varchar,
+        entity_id -> Nullable<Int4>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    request_lines (id) {
+        id -> Int4,
+        request_id -> Int4,
+        line_no -> Int4,
+        description -> Text,
+        quantity -> Int4,
+        units -> Varchar,
+    }
+}
+
+diesel::table! {
+    offer_lines (id) {
+        id -> Int4,
+        offer_id -> Int4,
+        request_line_id -> Int4,
+        price -> Numeric,
+        delivery_time -> Varchar,
+        status -> Varchar,
+        unit_price -> Numeric,
+        delivery_days -> Nullable<Int4>,
+        promised_date -> Nullable<Date>,
+    }
+}
+
+diesel::table! {
+    exchange_rates (id) {
+        id -> Int4,
+        currency -> Varchar,
+        rate -> Numeric,
+        effective_date -> Date,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    tax_settings (id) {
+        id -> Int4,
+        iva_rate -> Numeric,
+        withholding_rate -> Numeric,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    scoring_weights (id) {
+        id -> Int4,
         price -> Float8,
+        delivery -> Float8,
+        compliance -> Float8,
+        experience -> Float8,
+    }
+}
+
+diesel::table! {
+    offer_evaluations (id) {
+        id -> Int4,
+        request_id -> Int4,
+        weights -> Text,
+        ranking -> Text,
+        created_by -> Int4,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    offer_revisions (id) {
+        id -> Int4,
+        offer_id -> Int4,
+        revision -> Int4,
+        status -> Varchar,
+        price -> Numeric,
+        currency -> Varchar,
+        tax_amount -> Numeric,
+        withholding_amount -> Numeric,
+        total -> Numeric,
+        delivery_days -> Nullable<Int4>,
+        promised_date -> Nullable<Date>,
         delivery_time -> Varchar,
         conditions -> Text,
         attachments -> Text,
         photo -> Nullable<Text>,
+        lines -> Text,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    categories (id) {
+        id -> Int4,
+        slug -> Varchar,
+        name -> Varchar,
+        active -> Bool,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    request_categories (request_id, category_id) {
+        request_id -> Int4,
+        category_id -> Int4,
+    }
+}
+
+diesel::table! {
+    supplier_categories (supplier_id, category_id) {
+        supplier_id -> Int4,
+        category_id -> Int4,
+    }
+}
+
+diesel::table! {
+    request_invitations (id) {
+        id -> Int4,
+        request_id -> Int4,
+        supplier_id -> Int4,
         status -> Varchar,
+        invited_by -> Nullable<Int4>,
+        created_at -> Timestamp,
+        responded_at -> Nullable<Timestamp>,
+        notified_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    request_questions (id) {
+        id -> Int4,
+        request_id -> Int4,
+        supplier_id -> Nullable<Int4>,
+        question -> Text,
+        public_question -> Nullable<Text>,
+        answer -> Nullable<Text>,
+        visibility -> Nullable<Varchar>,
+        answered_by -> Nullable<Int4>,
+        created_at -> Timestamp,
+        answered_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    supplier_documents (id) {
+        id -> Int4,
+        supplier_id -> Int4,
+        doc_type -> Varchar,
+        file -> Varchar,
+        issued_on -> Nullable<Date>,
+        expires_on -> Nullable<Date>,
+        review_status -> Varchar,
+        review_notes -> Text,
+        reviewed_by -> Nullable<Int4>,
+        reviewed_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+        reminder_days -> Nullable<Int4>,
+        lapsed_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    uploads (file) {
+        file -> Varchar,
+        supplier_id -> Nullable<Int4>,
+        user_id -> Nullable<Int4>,
         created_at -> Timestamp,
     }
 }
 
 diesel::joinable!(offers -> suppliers (supplier_id));
 diesel::joinable!(offers -> requests (request_id));
+diesel::joinable!(audit_log -> users (user_id));
+diesel::joinable!(request_versions -> requests (request_id));
+diesel::joinable!(request_lines -> requests (request_id));
+diesel::joinable!(offer_lines -> offers (offer_id));
+diesel::joinable!(offer_lines -> request_lines (request_line_id));
+diesel::joinable!(offer_evaluations -> users (created_by));
+diesel::joinable!(offer_revisions -> offers (offer_id));
+diesel::joinable!(request_categories -> requests (request_id));
+diesel::joinable!(request_categories -> categories (category_id));
+diesel::joinable!(supplier_categories -> suppliers (supplier_id));
+diesel::joinable!(supplier_categories -> categories (category_id));
+diesel::joinable!(request_invitations -> requests (request_id));
+diesel::joinable!(request_invitations -> suppliers (supplier_id));
+diesel::joinable!(request_questions -> requests (request_id));
+diesel::joinable!(request_questions -> suppliers (supplier_id));
+diesel::joinable!(supplier_documents -> suppliers (supplier_id));
+diesel::joinable!(uploads -> suppliers (supplier_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
     email_config,
     suppliers,
     requests,
     offers,
+    users,
+    audit_log,
+    request_versions,
+    request_lines,
+    offer_lines,
+    exchange_rates,
+    tax_settings,
+    scoring_weights,
+    offer_evaluations,
+    offer_revisions,
+    categories,
+    request_categories,
+    supplier_categories,
+    request_invitations,
+    request_questions,
+    supplier_documents,
+    uploads,
 );
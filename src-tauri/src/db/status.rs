@@ -0,0 +1,252 @@
+use std::io::Write;
+use diesel::deserialize::{self, FromSql, FromSqlRow};
+use diesel::expression::AsExpression;
+use diesel::pg::{Pg, PgValue};
+use diesel::serialize::{self, IsNull, Output, ToSql};
+use diesel::sql_types::Text;
+use serde::{Deserialize, Serialize};
+
+/// Declares an enum stored as text. `ALL`, `as_str`, `parse`, the JSON names and the Diesel
+/// mapping all come from the one `Variant = "value"` table, so they can't drift apart.
+macro_rules! text_enum {
+    (
+        $(#[$meta:meta])*
+        pub enum $name:ident ($what:literal) {
+            $($(#[$variant_meta:meta])* $variant:ident = $value:literal,)+
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, AsExpression, FromSqlRow)]
+        #[diesel(sql_type = Text)]
+        pub enum $name {
+            $($(#[$variant_meta])* #[serde(rename = $value)] $variant,)+
+        }
+
+        impl $name {
+            pub const ALL: [$name; [$($value),+].len()] = [$($name::$variant),+];
+
+            pub fn as_str(&self) -> &'static str {
+                match self {
+                    $($name::$variant => $value,)+
+                }
+            }
+
+            pub fn parse(value: &str) -> Option<$name> {
+                $name::ALL.into_iter().find(|v| v.as_str() == value)
+            }
+        }
+
+        impl ToSql<Text, Pg> for $name {
+            fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+                out.write_all(self.as_str().as_bytes())?;
+                Ok(IsNull::No)
+            }
+        }
+
+        impl FromSql<Text, Pg> for $name {
+            fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+                let value = <String as FromSql<Text, Pg>>::from_sql(bytes)?;
+                $name::parse(&value).ok_or_else(|| format!("Unrecognized {}: {}", $what, value).into())
+            }
+        }
+    };
+}
+
+text_enum! {
+    /// Lifecycle of a purchase request.
+    ///
+    /// draft -> published -> in_evaluation -> awarded, with cancelled reachable from any
+    /// non-final state and expired used for requests whose deadline passed without bids.
+    pub enum RequestStatus ("request status") {
+        Draft = "draft",
+        Published = "published",
+        InEvaluation = "in_evaluation",
+        Awarded = "awarded",
+        Cancelled = "cancelled",
+        Expired = "expired",
+    }
+}
+
+impl RequestStatus {
+    /// Suppliers may only bid while a request is published.
+    pub fn is_bidding(&self) -> bool {
+        *self == RequestStatus::Published
+    }
+
+    pub fn can_transition_to(&self, next: RequestStatus) -> bool {
+        use RequestStatus::*;
+
+        matches!(
+            (*self, next),
+            (Draft, Published)
+                | (Draft, Cancelled)
+                | (Published, InEvaluation)
+                | (Published, Awarded)
+                | (Published, Expired)
+                | (Published, Cancelled)
+                | (InEvaluation, Awarded)
+                | (InEvaluation, Cancelled)
+                | (Expired, Cancelled)
+        )
+    }
+}
+
+text_enum! {
+    /// How suppliers compete on a request.
+    pub enum BiddingMode ("bidding mode") {
+        /// Buyers see offers as they arrive
+        Open = "open",
+        /// Offers stay hidden from buyers until the request closes
+        Sealed = "sealed",
+        /// Timed reverse auction with rank-only feedback
+        Auction = "auction",
+    }
+}
+
+text_enum! {
+    /// Status of a single offer. Values keep the names already used by the portal UI.
+    pub enum OfferStatus ("offer status") {
+        Sent = "sent",
+        Won = "ganadora",
+        Rejected = "rechazada",
+        /// Pulled back by the supplier while the request was still open
+        Withdrawn = "retirada",
+    }
+}
+
+impl OfferStatus {
+    pub fn can_transition_to(&self, next: OfferStatus) -> bool {
+        use OfferStatus::*;
+
+        matches!((*self, next), (Sent, Won) | (Sent, Rejected) | (Sent, Withdrawn))
+    }
+}
+
+text_enum! {
+    /// Answer of a supplier to an invitation to an invite-only request.
+    pub enum InvitationStatus ("invitation status") {
+        Pending = "pending",
+        Accepted = "accepted",
+        Declined = "declined",
+    }
+}
+
+text_enum! {
+    /// Who can read the answer to a clarification question.
+    pub enum AnswerVisibility ("answer visibility") {
+        /// Only the supplier that asked
+        Private = "private",
+        /// Every supplier that can see the request, without saying who asked
+        Public = "public",
+    }
+}
+
+text_enum! {
+    /// Kind of compliance document a supplier uploads.
+    pub enum DocumentType ("document type") {
+        /// Cédula de identificación fiscal (RFC)
+        Rfc = "rfc",
+        ConstanciaSituacionFiscal = "constancia_situacion_fiscal",
+        /// Opinión de cumplimiento de obligaciones fiscales (SAT 32-D)
+        OpinionCumplimiento = "opinion_cumplimiento",
+        /// Bank statement with the account payments go to
+        EstadoCuenta = "estado_cuenta",
+        CertificadoIso = "certificado_iso",
+    }
+}
+
+impl DocumentType {
+    /// Name shown to people, e.g. in emails.
+    pub fn label(&self) -> &'static str {
+        match self {
+            DocumentType::Rfc => "Cédula RFC",
+            DocumentType::ConstanciaSituacionFiscal => "Constancia de situación fiscal",
+            DocumentType::OpinionCumplimiento => "Opinión de cumplimiento",
+            DocumentType::EstadoCuenta => "Estado de cuenta bancario",
+            DocumentType::CertificadoIso => "Certificado ISO",
+        }
+    }
+
+    /// Documents a supplier needs to stay approved in compliance; when one of them expires the
+    /// supplier can't bid until it is renewed and re-approved.
+    pub fn is_required(&self) -> bool {
+        matches!(self, DocumentType::Rfc | DocumentType::ConstanciaSituacionFiscal | DocumentType::OpinionCumplimiento)
+    }
+
+    /// Documents that are only valid until a date, so they must be registered with `expires_on`.
+    pub fn expires(&self) -> bool {
+        matches!(self, DocumentType::OpinionCumplimiento | DocumentType::CertificadoIso)
+    }
+}
+
+text_enum! {
+    /// Review of a supplier document by an admin.
+    pub enum ReviewStatus ("review status") {
+        Pending = "pending",
+        Approved = "approved",
+        Rejected = "rejected",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn request_lifecycle_moves_forward_only() {
+        use RequestStatus::*;
+
+        assert!(Draft.can_transition_to(Published));
+        assert!(Published.can_transition_to(InEvaluation));
+        assert!(InEvaluation.can_transition_to(Awarded));
+        assert!(Published.can_transition_to(Expired));
+        assert!(Expired.can_transition_to(Cancelled));
+
+        assert!(!Published.can_transition_to(Draft));
+        assert!(!Draft.can_transition_to(Awarded));
+        assert!(!Expired.can_transition_to(Published));
+        assert!(!InEvaluation.can_transition_to(Published));
+    }
+
+    #[test]
+    fn closed_requests_and_offers_are_final() {
+        for next in RequestStatus::ALL {
+            assert!(!RequestStatus::Awarded.can_transition_to(next));
+            assert!(!RequestStatus::Cancelled.can_transition_to(next));
+        }
+        for current in [OfferStatus::Won, OfferStatus::Rejected, OfferStatus::Withdrawn] {
+            for next in OfferStatus::ALL {
+                assert!(!current.can_transition_to(next));
+            }
+        }
+    }
+
+    #[test]
+    fn sent_offer_can_be_decided_or_withdrawn() {
+        assert!(OfferStatus::Sent.can_transition_to(OfferStatus::Won));
+        assert!(OfferStatus::Sent.can_transition_to(OfferStatus::Rejected));
+        assert!(OfferStatus::Sent.can_transition_to(OfferStatus::Withdrawn));
+        assert!(!OfferStatus::Sent.can_transition_to(OfferStatus::Sent));
+    }
+
+    #[test]
+    fn statuses_round_trip_through_their_names() {
+        for status in RequestStatus::ALL {
+            assert_eq!(RequestStatus::parse(status.as_str()), Some(status));
+        }
+        for status in OfferStatus::ALL {
+            assert_eq!(OfferStatus::parse(status.as_str()), Some(status));
+        }
+        for visibility in AnswerVisibility::ALL {
+            assert_eq!(AnswerVisibility::parse(visibility.as_str()), Some(visibility));
+        }
+        assert_eq!(RequestStatus::parse("open"), None);
+    }
+
+    #[test]
+    fn json_names_match_the_stored_names() {
+        assert_eq!(serde_json::to_value(OfferStatus::Won).unwrap(), "ganadora");
+        assert_eq!(serde_json::to_value(RequestStatus::InEvaluation).unwrap(), "in_evaluation");
+        assert_eq!(serde_json::from_str::<DocumentType>("\"opinion_cumplimiento\"").unwrap(), DocumentType::OpinionCumplimiento);
+    }
+}
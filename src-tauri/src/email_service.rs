@@ -84,3 +84,101 @@ pub fn send_welcome_email(pool: &DbPool, to: &str) {
     send_email_with_pool(pool, to, "Registro Recibido - Portal Proveedores", "Gracias por registrarte en nuestro portal. Tu cuenta está en revisión. En breve recibirás una respuesta.");
 }
 
+pub fn send_request_deadline_email(pool: &DbPool, to: &str, title: &str, offer_count: i64) {
+    let body = if offer_count == 0 {
+        format!("La solicitud \"{}\" llegó a su fecha límite sin recibir ofertas y fue marcada como vencida.", title)
+    } else {
+        format!("La solicitud \"{}\" llegó a su fecha límite con {} oferta(s) y está lista para evaluación.", title, offer_count)
+    };
+    send_email_with_pool(pool, to, "Solicitud Vencida - Portal", &body);
+}
+
+pub fn send_request_amended_email(pool: &DbPool, to: &str, title: &str, version: i32) {
+    let body = format!(
+        "La solicitud \"{}\" fue modificada (versión {}). Revisa los nuevos términos y confirma o actualiza tu oferta en el portal.",
+        title, version
+    );
+    send_email_with_pool(pool, to, "Solicitud Modificada - Portal", &body);
+}
+
+pub fn send_new_request_email(pool: &DbPool, to: &str, title: &str) {
+    let body = format!(
+        "Se publicó la solicitud \"{}\" en una de las categorías que atiendes. Ingresa al portal para enviar tu oferta.",
+        title
+    );
+    send_email_with_pool(pool, to, "Nueva Solicitud - Portal", &body);
+}
+
+pub fn send_invitation_email(pool: &DbPool, to: &str, title: &str, deadline: chrono::NaiveDateTime, link: &str) {
+    let body = format!(
+        "Fuiste invitado a cotizar la solicitud \"{}\" (fecha límite: {}). Solo los proveedores invitados pueden verla. Acepta o rechaza la invitación y envía tu oferta en: {}",
+        title,
+        deadline.format("%d/%m/%Y %H:%M"),
+        link
+    );
+    send_email_with_pool(pool, to, "Invitación a Cotizar - Portal", &body);
+}
+
+pub fn send_question_email(pool: &DbPool, to: &str, title: &str, question: &str) {
+    let body = format!(
+        "Un proveedor preguntó sobre la solicitud \"{}\":\n\n{}\n\nResponde desde el portal, de forma privada o pública.",
+        title, question
+    );
+    send_email_with_pool(pool, to, "Nueva Pregunta - Portal", &body);
+}
+
+pub fn send_answer_email(pool: &DbPool, to: &str, title: &str, question: &str, answer: &str) {
+    let body = format!(
+        "Respondieron tu pregunta sobre la solicitud \"{}\".\n\nPregunta: {}\nRespuesta: {}",
+        title, question, answer
+    );
+    send_email_with_pool(pool, to, "Respuesta a tu Pregunta - Portal", &body);
+}
+
+pub fn send_clarification_email(pool: &DbPool, to: &str, title: &str, question: &str, answer: &str) {
+    let body = format!(
+        "Se publicó una aclaración para todos los participantes de la solicitud \"{}\".\n\nPregunta: {}\nRespuesta: {}",
+        title, question, answer
+    );
+    send_email_with_pool(pool, to, "Aclaración Publicada - Portal", &body);
+}
+
+pub fn send_document_reviewed_email(pool: &DbPool, to: &str, document: &str, approved: bool, notes: &str) {
+    let body = if approved {
+        format!("Tu documento \"{}\" fue revisado y aprobado.", document)
+    } else {
+        format!("Tu documento \"{}\" fue rechazado: {}\n\nSube una versión corregida desde el portal.", document, notes)
+    };
+    send_email_with_pool(pool, to, "Revisión de Documento - Portal", &body);
+}
+
+pub fn send_document_expiring_email(pool: &DbPool, to: &str, document: &str, expires_on: chrono::NaiveDate, days_left: i32) {
+    let when = if days_left == 0 { "hoy".to_string() } else { format!("en {} día(s)", days_left) };
+    let body = format!(
+        "Tu documento \"{}\" vence {} ({}). Sube la versión renovada desde el portal para seguir participando en las solicitudes.",
+        document,
+        when,
+        expires_on.format("%d/%m/%Y")
+    );
+    send_email_with_pool(pool, to, "Documento por Vencer - Portal", &body);
+}
+
+pub fn send_document_expired_email(pool: &DbPool, to: &str, document: &str, suspended: bool) {
+    let body = if suspended {
+        format!(
+            "Tu documento \"{}\" venció. Tu cuenta quedó suspendida en cumplimiento y no podrás enviar ofertas hasta que subas uno vigente y sea aprobado.",
+            document
+        )
+    } else {
+        format!("Tu documento \"{}\" venció. Sube la versión renovada desde el portal.", document)
+    };
+    send_email_with_pool(pool, to, "Documento Vencido - Portal", &body);
+}
+
+pub fn send_supplier_suspended_email(pool: &DbPool, to: &str, supplier: &str, document: &str) {
+    let body = format!(
+        "El documento \"{}\" del proveedor {} venció y su aprobación de cumplimiento fue retirada. Podrá ofertar de nuevo cuando se revise el documento renovado y se apruebe otra vez.",
+        document, supplier
+    );
+    send_email_with_pool(pool, to, "Proveedor Suspendido en Cumplimiento - Portal", &body);
+}
@@ -1,24 +1,40 @@
 pub mod api;
 pub mod db;
 pub mod email_service;
+pub mod scheduler;
 
 use std::thread;
-use actix_web::{App, HttpServer, middleware::Logger};
+use actix_web::{App, HttpServer};
 use actix_cors::Cors;
 
 pub fn start_actix_server(db_url: String, port: u16) {
+    // Checked before the server thread starts, so a missing secret stops the app right away
+    let jwt_keys = match api::security::JwtKeys::from_env() {
+        Ok(keys) => actix_web::web::Data::new(keys),
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+
     thread::spawn(move || {
         let sys = actix_web::rt::System::new();
         sys.block_on(async move {
             let pool = db::establish_connection(&db_url);
+            db::bootstrap_admin(&pool);
+            let events = api::events::EventHub::new();
+            scheduler::start(pool.clone(), events.clone());
+            let events = actix_web::web::Data::new(events);
             
             println!("Starting server at http://0.0.0.0:{}", port);
             
             HttpServer::new(move || {
                 App::new()
-                    .wrap(Logger::default())
+                    .wrap(api::access_log())
                     .wrap(Cors::permissive())
                     .app_data(actix_web::web::Data::new(pool.clone()))
+                    .app_data(jwt_keys.clone())
+                    .app_data(events.clone())
                     .configure(api::config)
             })
             .bind(("0.0.0.0", port))
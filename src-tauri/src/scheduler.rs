@@ -0,0 +1,359 @@
+use std::{env, sync::mpsc, thread, time::Duration};
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use crate::api::auction;
+use crate::api::events::{Event, EventHub, EventKind};
+use crate::api::security::Role;
+use crate::api::documents::is_superseded;
+use crate::api::offers::{award_offer, normalized_offers, notify_award, AwardError};
+use crate::db::{DbPool, models::{Offer, Request, SupplierDocument}, schema, status::{OfferStatus, RequestStatus, ReviewStatus}};
+use crate::email_service;
+
+/// Starts the background jobs on their own thread, next to the API server: request
+/// deadlines and supplier document expiry.
+///
+/// `SCHEDULER_INTERVAL_SECS` controls how often they run (default 60). Setting
+/// `AUTO_AWARD_LOWEST_PRICE=true` awards overdue requests to their cheapest offer.
+pub fn start(pool: DbPool, events: EventHub) {
+    let interval = env::var("SCHEDULER_INTERVAL_SECS")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .unwrap_or(60);
+
+    let outbox = Outbox::start(pool.clone());
+    thread::spawn(move || loop {
+        expire_overdue_requests(&pool, &events, &outbox);
+        remind_expiring_documents(&pool, &outbox);
+        handle_lapsed_documents(&pool, &events, &outbox);
+        thread::sleep(Duration::from_secs(interval));
+    });
+}
+
+type Mail = Box<dyn FnOnce(&DbPool) + Send>;
+
+/// Emails of the jobs, sent in order from a thread of their own so a slow or unreachable SMTP
+/// server doesn't hold up the next run.
+struct Outbox {
+    sender: mpsc::Sender<Mail>,
+}
+
+impl Outbox {
+    fn start(pool: DbPool) -> Self {
+        let (sender, receiver) = mpsc::channel::<Mail>();
+        thread::spawn(move || {
+            for mail in receiver {
+                mail(&pool);
+            }
+        });
+        Outbox { sender }
+    }
+
+    fn send(&self, mail: impl FnOnce(&DbPool) + Send + 'static) {
+        if self.sender.send(Box::new(mail)).is_err() {
+            eprintln!("Scheduler outbox is closed; email dropped");
+        }
+    }
+}
+
+fn env_flag(name: &str) -> bool {
+    matches!(env::var(name).as_deref(), Ok("1") | Ok("true") | Ok("yes"))
+}
+
+/// Moves published requests past their deadline to evaluation, or to expired when no
+/// offer arrived. Auctions with bids and rounds left open their next round instead. Rows are claimed with `FOR UPDATE SKIP LOCKED`, so several server
+/// instances can run this against the same database without handling a request twice.
+fn expire_overdue_requests(pool: &DbPool, events: &EventHub, outbox: &Outbox) {
+    let mut conn = match pool.get() {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("Scheduler could not get a db connection: {}", e);
+            return;
+        }
+    };
+    let now = chrono::Local::now().naive_local();
+
+    let closed = conn.transaction::<(Vec<(Request, i64)>, Vec<Request>), diesel::result::Error, _>(|conn| {
+        use schema::requests::dsl::*;
+
+        let overdue = requests
+            .filter(status.eq(RequestStatus::Published))
+            .filter(deadline.le(now))
+            .for_update()
+            .skip_locked()
+            .load::<Request>(conn)?;
+
+        let mut closed = Vec::with_capacity(overdue.len());
+        let mut next_rounds = Vec::new();
+        for req in overdue {
+            let offer_count: i64 = schema::offers::table
+                .filter(schema::offers::request_id.eq(req.id))
+                .filter(schema::offers::status.eq(OfferStatus::Sent))
+                .count()
+                .get_result(conn)?;
+
+            if auction::is_auction(&req) && auction::has_rounds_left(&req) && offer_count > 0 {
+                next_rounds.push(auction::start_next_round(conn, &req, now)?);
+                continue;
+            }
+
+            let next = if offer_count == 0 { RequestStatus::Expired } else { RequestStatus::InEvaluation };
+            let updated = diesel::update(requests.find(req.id))
+                .set(status.eq(next))
+                .get_result::<Request>(conn)?;
+
+            closed.push((updated, offer_count));
+        }
+
+        Ok((closed, next_rounds))
+    });
+
+    let (closed, next_rounds) = match closed {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("Error expiring overdue requests: {}", e);
+            return;
+        }
+    };
+
+    for req in next_rounds {
+        println!("Auction {} moved to round {} of {}", req.id, req.auction_round, req.auction_rounds);
+        events.publish(Event::request(EventKind::RequestUpdated, &req));
+    }
+
+    if closed.is_empty() {
+        return;
+    }
+
+    let auto_award = env_flag("AUTO_AWARD_LOWEST_PRICE");
+    let buyers = buying_team_emails(&mut conn);
+
+    for (req, offer_count) in closed {
+        println!("Request {} reached its deadline with {} offer(s), now {}", req.id, offer_count, req.status.as_str());
+        events.publish(Event::request(EventKind::RequestUpdated, &req));
+
+        for to in &buyers {
+            let (to, title) = (to.clone(), req.title.clone());
+            outbox.send(move |pool| email_service::send_request_deadline_email(pool, &to, &title, offer_count));
+        }
+
+        if auto_award && offer_count > 0 {
+            award_lowest_price(pool, events, &mut conn, &req);
+        }
+    }
+}
+
+/// Days before `expires_on` at which suppliers are reminded to renew a document.
+const REMINDER_DAYS: [i32; 3] = [30, 15, 1];
+
+/// Reminder to send for a document expiring in `days_left` days, given the last one `sent`:
+/// the closest threshold not passed yet, e.g. the 15-day one for a document expiring in 10
+/// days, unless it or a later one already went out.
+fn reminder_due(days_left: i32, sent: Option<i32>) -> Option<i32> {
+    let threshold = REMINDER_DAYS.iter().rev().copied().find(|t| days_left <= *t)?;
+    sent.is_none_or(|s| s > threshold).then_some(threshold)
+}
+
+fn supplier_email(conn: &mut PgConnection, supplier_id: i32) -> Option<String> {
+    schema::suppliers::table
+        .find(supplier_id)
+        .select(schema::suppliers::email)
+        .first::<String>(conn)
+        .ok()
+}
+
+/// Emails suppliers whose current documents expire within `REMINDER_DAYS`. Each reminder is
+/// claimed by lowering `reminder_days` with a conditional update, so it goes out once even
+/// with several server instances.
+fn remind_expiring_documents(pool: &DbPool, outbox: &Outbox) {
+    let mut conn = match pool.get() {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("Scheduler could not get a db connection: {}", e);
+            return;
+        }
+    };
+    let today = chrono::Local::now().date_naive();
+    let horizon = today + chrono::Duration::days(REMINDER_DAYS[0] as i64);
+
+    use schema::supplier_documents::dsl::*;
+
+    let expiring = supplier_documents
+        .filter(expires_on.ge(today))
+        .filter(expires_on.le(horizon))
+        .filter(review_status.ne(ReviewStatus::Rejected))
+        .load::<SupplierDocument>(&mut conn);
+
+    let expiring = match expiring {
+        Ok(list) => list,
+        Err(e) => {
+            eprintln!("Error loading expiring documents: {}", e);
+            return;
+        }
+    };
+
+    for doc in expiring {
+        let Some(expiry) = doc.expires_on else { continue };
+        let days_left = (expiry - today).num_days() as i32;
+        let Some(threshold) = reminder_due(days_left, doc.reminder_days) else { continue };
+        if is_superseded(&mut conn, &doc).unwrap_or(true) {
+            continue;
+        }
+
+        let claimed = diesel::update(
+            supplier_documents
+                .find(doc.id)
+                .filter(reminder_days.is_null().or(reminder_days.gt(threshold))),
+        )
+        .set(reminder_days.eq(threshold))
+        .execute(&mut conn);
+
+        if let Ok(1) = claimed {
+            if let Some(to) = supplier_email(&mut conn, doc.supplier_id) {
+                let label = doc.doc_type.label();
+                outbox.send(move |pool| email_service::send_document_expiring_email(pool, &to, label, expiry, days_left));
+            }
+        }
+    }
+}
+
+/// Marks documents past `expires_on` as lapsed and tells their supplier. When the current
+/// document of a required type lapses the supplier loses its compliance approval, so it can't
+/// bid until it uploads a renewed one and an admin approves it again.
+fn handle_lapsed_documents(pool: &DbPool, events: &EventHub, outbox: &Outbox) {
+    let mut conn = match pool.get() {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("Scheduler could not get a db connection: {}", e);
+            return;
+        }
+    };
+    let today = chrono::Local::now().date_naive();
+    let now = chrono::Local::now().naive_local();
+
+    use schema::supplier_documents::dsl::*;
+
+    let lapsed = supplier_documents
+        .filter(expires_on.lt(today))
+        .filter(lapsed_at.is_null())
+        .load::<SupplierDocument>(&mut conn);
+
+    let lapsed = match lapsed {
+        Ok(list) => list,
+        Err(e) => {
+            eprintln!("Error loading lapsed documents: {}", e);
+            return;
+        }
+    };
+
+    let mut buyers: Option<Vec<String>> = None;
+
+    for doc in lapsed {
+        let claimed = diesel::update(supplier_documents.find(doc.id).filter(lapsed_at.is_null()))
+            .set(lapsed_at.eq(now))
+            .execute(&mut conn);
+        if !matches!(claimed, Ok(1)) || is_superseded(&mut conn, &doc).unwrap_or(true) {
+            continue;
+        }
+
+        let downgraded = doc.doc_type.is_required() && revoke_approval(&mut conn, doc.supplier_id);
+
+        if let Some(to) = supplier_email(&mut conn, doc.supplier_id) {
+            let label = doc.doc_type.label();
+            outbox.send(move |pool| email_service::send_document_expired_email(pool, &to, label, downgraded));
+        }
+
+        if downgraded {
+            println!("Supplier {} lost its compliance approval: document {} expired", doc.supplier_id, doc.id);
+            events.publish(Event::supplier(EventKind::SupplierUpdated, doc.supplier_id));
+
+            let name: String = schema::suppliers::table
+                .find(doc.supplier_id)
+                .select(schema::suppliers::name)
+                .first(&mut conn)
+                .unwrap_or_default();
+            let label = doc.doc_type.label();
+            for to in buyers.get_or_insert_with(|| buying_team_emails(&mut conn)).iter() {
+                let (to, name) = (to.clone(), name.clone());
+                outbox.send(move |pool| email_service::send_supplier_suspended_email(pool, &to, &name, label));
+            }
+        }
+    }
+}
+
+/// Clears the compliance approval of a supplier; false if it wasn't approved.
+fn revoke_approval(conn: &mut PgConnection, supplier_id: i32) -> bool {
+    use schema::suppliers::dsl::*;
+
+    let revoked = diesel::update(suppliers.find(supplier_id).filter(is_approved.eq(true)))
+        .set(is_approved.eq(false))
+        .execute(conn);
+    matches!(revoked, Ok(1))
+}
+
+/// Active buyers; admins run the portal but don't follow the purchases.
+pub(crate) fn buying_team_emails(conn: &mut PgConnection) -> Vec<String> {
+    use schema::users::dsl::*;
+
+    users
+        .filter(active.eq(true))
+        .filter(role.eq(Role::Buyer.as_str()))
+        .select(email)
+        .load::<String>(conn)
+        .unwrap_or_default()
+}
+
+/// Awards the cheapest offer, in the request currency, that quotes every line of the
+/// request. Requests whose lines are only covered by partial offers are left for the
+/// buyers to split, and offers in a currency without exchange rate are skipped.
+fn award_lowest_price(pool: &DbPool, events: &EventHub, conn: &mut PgConnection, req: &Request) {
+    let today = chrono::Local::now().date_naive();
+    let candidates: Vec<Offer> = normalized_offers(conn, req, today)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|c| c.normalized_price.is_some())
+        .map(|c| c.offer)
+        .filter(|o| o.status == OfferStatus::Sent && !o.needs_reconfirmation)
+        .collect();
+
+    for candidate in candidates {
+        match award_offer(conn, candidate.id, None) {
+            Ok(award) => {
+                if award.newly_awarded {
+                    println!("Request {} auto-awarded to offer {}", req.id, award.winner.id);
+                    notify_award(pool, events, conn, &award);
+                }
+                return;
+            },
+            Err(AwardError::IncompleteOffer) => continue,
+            Err(_) => {
+                eprintln!("Could not auto-award request {}", req.id);
+                return;
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn picks_the_closest_reminder_not_passed_yet() {
+        assert_eq!(reminder_due(30, None), Some(30));
+        assert_eq!(reminder_due(20, None), Some(30));
+        assert_eq!(reminder_due(10, None), Some(15));
+        assert_eq!(reminder_due(1, None), Some(1));
+        assert_eq!(reminder_due(0, None), Some(1));
+        assert_eq!(reminder_due(31, None), None);
+    }
+
+    #[test]
+    fn each_reminder_goes_out_once() {
+        assert_eq!(reminder_due(20, Some(30)), None);
+        assert_eq!(reminder_due(15, Some(30)), Some(15));
+        assert_eq!(reminder_due(10, Some(15)), None);
+        // A document registered close to expiry skips straight to the nearest reminder
+        assert_eq!(reminder_due(1, Some(30)), Some(1));
+        assert_eq!(reminder_due(1, Some(1)), None);
+    }
+}
@@ -9,6 +9,8 @@ import { useEffect } from 'react';
 
 function App() {
   const isAuthenticated = !!localStorage.getItem('token');
+  const isStaff = ['admin', 'buyer'].includes(localStorage.getItem('role') || '');
+  const home = isStaff ? "/admin" : "/dashboard";
 
   useEffect(() => {
     // 1. Aplicar tema guardado localmente de inmediato para evitar parpadeos
@@ -17,7 +19,7 @@ function App() {
 
     const fetchTheme = async () => {
       try {
-        const res = await axios.get("http://localhost:8080/api/admin/config/email");
+        const res = await axios.get("http://localhost:8080/api/config/public");
         if (res.data && res.data.ui_theme) {
           const theme = res.data.ui_theme;
           document.body.setAttribute('data-theme', theme);
@@ -34,11 +36,11 @@ function App() {
     <Router>
       <div className="container">
         <Routes>
-          <Route path="/login" element={!isAuthenticated ? <Login /> : <Navigate to="/dashboard" />} />
+          <Route path="/login" element={!isAuthenticated ? <Login /> : <Navigate to={home} />} />
           <Route path="/register" element={!isAuthenticated ? <Register /> : <Navigate to="/dashboard" />} />
           <Route path="/dashboard" element={isAuthenticated ? <Dashboard /> : <Navigate to="/login" />} />
-          <Route path="/admin" element={<Admin />} />
-          <Route path="/" element={<Navigate to={isAuthenticated ? "/dashboard" : "/login"} />} />
+          <Route path="/admin" element={isAuthenticated && isStaff ? <Admin /> : <Navigate to="/login" />} />
+          <Route path="/" element={<Navigate to={isAuthenticated ? home : "/login"} />} />
         </Routes>
       </div>
     </Router>
@@ -21,7 +21,9 @@ interface Offer {
     id: number;
     supplier_id: number;
     request_id: number;
-    price: number;
+    price: string; // decimal serialized as text, before taxes
+    total: string; // price + IVA - withholding
+    currency: string;
     delivery_time: string;
     attachments: string;
     status: string;
@@ -39,6 +41,16 @@ interface Request {
     origin_erp: string;
 }
 
+// Los archivos de proveedores requieren sesión, así que se descargan con el token y se abren aquí
+const openFile = async (name: string) => {
+    try {
+        const res = await axios.get(`${API_URL}/archivos/${name}`, { responseType: 'blob' });
+        window.open(URL.createObjectURL(res.data), '_blank');
+    } catch (err) {
+        alert("No se pudo abrir el archivo");
+    }
+};
+
 const renderDocs = (docs: string) => {
     if (!docs) return <em style={{ color: '#666' }}>Sin documentos</em>;
     return docs.split(',').map((d, i) => {
@@ -47,9 +59,8 @@ const renderDocs = (docs: string) => {
         return (
             <a
                 key={i}
-                href={`${API_URL}/uploads/${name}`}
-                target="_blank"
-                rel="noreferrer"
+                href="#"
+                onClick={e => { e.preventDefault(); openFile(name); }}
                 style={{ color: '#3498db', textDecoration: 'underline', marginRight: '8px', fontSize: '0.85rem' }}
             >
                 📄 {name.length > 20 ? name.substring(0, 10) + '...' + name.substring(name.length - 5) : name}
@@ -89,6 +100,18 @@ export default function Admin() {
         fetchConfig();
         fetchOffers();
         fetchRequests();
+
+        // Actualizaciones en tiempo real de solicitudes, ofertas y proveedores
+        const events = new EventSource(`${API_URL}/eventos?token=${encodeURIComponent(localStorage.getItem('token') || '')}`);
+        ['request_created', 'request_updated'].forEach((kind) => events.addEventListener(kind, fetchRequests));
+        ['offer_created', 'offer_updated', 'offer_withdrawn', 'offer_won', 'offer_rejected'].forEach((kind) => events.addEventListener(kind, fetchOffers));
+        ['supplier_approved', 'supplier_updated'].forEach((kind) => events.addEventListener(kind, fetchPending));
+        events.addEventListener('resync', () => {
+            fetchPending();
+            fetchOffers();
+            fetchRequests();
+        });
+        return () => events.close();
     }, []);
 
     const fetchConfig = async () => {
@@ -180,11 +203,11 @@ export default function Admin() {
     const fetchPending = async () => {
         try {
             const res = await axios.get(`${API_URL}/admin/suppliers`);
-            setSuppliers(res.data);
+            setSuppliers(res.data.items);
 
             // Also fetch approved
             const resApproved = await axios.get(`${API_URL}/admin/suppliers/approved`);
-            setApprovedSuppliers(resApproved.data);
+            setApprovedSuppliers(resApproved.data.items);
         } catch (e) {
             console.error(e);
         }
@@ -193,7 +216,7 @@ export default function Admin() {
     const fetchOffers = async () => {
         try {
             const res = await axios.get(`${API_URL}/admin/ofertas`);
-            setOffers(res.data);
+            setOffers(res.data.items);
         } catch (e) {
             console.error(e);
         }
@@ -202,7 +225,7 @@ export default function Admin() {
     const fetchRequests = async () => {
         try {
             const res = await axios.get(`${API_URL}/solicitudes`);
-            setRequests(res.data);
+            setRequests(res.data.items);
         } catch (e) {
             console.error(e);
         }
@@ -378,7 +401,7 @@ export default function Admin() {
                                         <th>Cotización Enviada</th>
                                         <th>Req. ID</th>
                                         <th>Prov. ID</th>
-                                        <th>Precio</th>
+                                        <th>Total (con impuestos)</th>
                                         <th>Entrega</th>
                                         <th>Adjunto</th>
                                         <th>Estado</th>
@@ -391,7 +414,7 @@ export default function Admin() {
                                             <td>{o.created_at ? new Date(o.created_at).toLocaleString() : '-'}</td>
                                             <td>REQ-{o.request_id}</td>
                                             <td>SUP-{o.supplier_id}</td>
-                                            <td>${o.price}</td>
+                                            <td>${o.total} {o.currency}</td>
                                             <td>{o.delivery_time}</td>
                                             <td>{o.attachments}</td>
                                             <td>
@@ -436,7 +459,7 @@ export default function Admin() {
                                             <td>{new Date(r.deadline).toLocaleDateString()}</td>
                                             <td>
                                                 <span className={`status-badge`} style={{
-                                                    background: r.status === 'open' ? '#3498db' : '#2ecc71'
+                                                    background: r.status === 'published' ? '#3498db' : '#2ecc71'
                                                 }}>
                                                     {r.status.toUpperCase()}
                                                 </span>
@@ -26,7 +26,7 @@ export default function Dashboard() {
 
     const [quoteForm, setQuoteForm] = useState({
         price: '',
-        delivery_time: '',
+        delivery_days: '',
         attachments: 'oferta.pdf',
         photo: ''
     });
@@ -35,13 +35,18 @@ export default function Dashboard() {
         fetchRequests();
         loadQuotedStatus();
         fetchSupplierProfile();
+
+        // El servidor avisa cuando cambian las solicitudes, sin necesidad de recargar
+        const events = new EventSource(`${API_URL}/eventos?token=${encodeURIComponent(localStorage.getItem('token') || '')}`);
+        ['request_created', 'request_updated', 'resync'].forEach((kind) => events.addEventListener(kind, fetchRequests));
+        return () => events.close();
     }, []);
 
     const fetchSupplierProfile = async () => {
         const id = localStorage.getItem('supplier_id');
         if (id) {
             try {
-                const res = await axios.get(`${API_URL}/suppliers/${id}`);
+                const res = await axios.get(`${API_URL}/me`);
                 setSupplierDocs(res.data.documents || "");
                 setIsReviewed(res.data.is_reviewed);
             } catch (e) {
@@ -55,7 +60,7 @@ export default function Dashboard() {
         if (!id) return;
         setSavingDocs(true);
         try {
-            await axios.put(`${API_URL}/suppliers/${id}/docs`, { documents: supplierDocs });
+            await axios.put(`${API_URL}/me/docs`, { documents: supplierDocs });
             alert("Documentación actualizada correctamente");
         } catch (e) {
             console.error(e);
@@ -92,7 +97,7 @@ export default function Dashboard() {
     const fetchRequests = async () => {
         try {
             const res = await axios.get(`${API_URL}/solicitudes`);
-            setRequests(res.data);
+            setRequests(res.data.items);
         } catch (err) {
             console.error(err);
         }
@@ -107,7 +112,7 @@ export default function Dashboard() {
         setQuotingReqId(id);
         setQuoteForm({
             price: '',
-            delivery_time: 'Inmediato',
+            delivery_days: '',
             attachments: 'oferta.pdf',
             photo: ''
         });
@@ -126,14 +131,12 @@ export default function Dashboard() {
             }
 
             await axios.post(`${API_URL}/ofertas`, {
-                supplier_id: supplierId,
                 request_id: reqId,
                 price: parseFloat(quoteForm.price),
-                delivery_time: quoteForm.delivery_time,
+                delivery_days: parseInt(quoteForm.delivery_days),
                 conditions: "Standard terms",
                 attachments: quoteForm.attachments,
-                photo: quoteForm.photo || null,
-                status: "sent"
+                photo: quoteForm.photo || null
             });
             alert("Oferta enviada correctamente!");
             saveQuotedStatus(reqId); // Updates state and localStorage
@@ -141,6 +144,10 @@ export default function Dashboard() {
         } catch (e: any) {
             console.error(e);
             const msg = e.response?.data || e.message || "Error desconocido";
+            if (msg?.violations) {
+                alert(`Error enviando oferta:\n${msg.violations.map((v: any) => `- ${v.message}`).join('\n')}`);
+                return;
+            }
             alert(`Error enviando oferta: ${typeof msg === 'object' ? JSON.stringify(msg) : msg}`);
         }
     };
@@ -212,11 +219,12 @@ export default function Dashboard() {
                                                             style={{ padding: '8px', background: 'var(--bg-input)', border: '1px solid var(--border-input)', color: 'var(--text-main)', borderRadius: '4px' }}
                                                         />
                                                         <input
-                                                            type="text"
-                                                            placeholder="Tiempo de entrega (ej: 3 días)"
+                                                            type="number"
+                                                            min="0"
+                                                            placeholder="Días de entrega (ej: 3)"
                                                             required
-                                                            value={quoteForm.delivery_time}
-                                                            onChange={e => setQuoteForm({ ...quoteForm, delivery_time: e.target.value })}
+                                                            value={quoteForm.delivery_days}
+                                                            onChange={e => setQuoteForm({ ...quoteForm, delivery_days: e.target.value })}
                                                             style={{ padding: '8px', background: 'var(--bg-input)', border: '1px solid var(--border-input)', color: 'var(--text-main)', borderRadius: '4px' }}
                                                         />
 
@@ -14,9 +14,14 @@ export default function Login() {
         try {
             const res = await axios.post(`${API_URL}/login`, { email, password });
             localStorage.setItem('token', res.data.token);
-            localStorage.setItem('supplier_id', res.data.user.id);
-            localStorage.setItem('supplier_name', res.data.user.name);
-            window.location.href = "/dashboard";
+            localStorage.setItem('role', res.data.user.role);
+            if (res.data.user.role === 'supplier') {
+                localStorage.setItem('supplier_id', res.data.user.id);
+                localStorage.setItem('supplier_name', res.data.user.name);
+                window.location.href = "/dashboard";
+            } else {
+                window.location.href = "/admin";
+            }
         } catch (err: any) {
             setError(err.response?.data || 'Invalid credentials or server error');
             console.error(err);
@@ -28,7 +33,7 @@ export default function Login() {
     React.useEffect(() => {
         const fetchConfig = async () => {
             try {
-                const res = await axios.get(`${API_URL}/admin/config/email`);
+                const res = await axios.get(`${API_URL}/config/public`);
                 setConfig(res.data);
             } catch (e) {
                 console.error("No se pudo cargar la configuración de imagen:", e);
@@ -28,13 +28,7 @@ export default function Register() {
                 contact: formData.contact,
                 email: formData.email,
                 phone: formData.phone,
-                password_hash: formData.password,
-                created_at: new Date().toISOString().slice(0, 19),
-                documents: "",
-                active: false,
-                is_reviewed: false,
-                is_approved: false,
-                is_audited: false
+                password: formData.password
             };
             await axios.post(`${API_URL}/register`, payload);
             alert("Gracias por registrarse en nuestro portal. En breve recibirá una respuesta.");